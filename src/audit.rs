@@ -0,0 +1,231 @@
+//! Audit logging of requests to routes marked `audited: true`.
+//!
+//! [Audit] wraps the app, or a scope, while the routes' resources mark the requests they
+//! receive, so a request is attributed to its route however it's reached, e.g. under a
+//! `web::scope` prefix, with a trailing slash or at its alias.
+
+use std::{
+    cell::RefCell,
+    future::{ready, Future, Ready},
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    HttpMessage,
+};
+
+use crate::{compat::FromActix, redact::MASK, Route, RouteCatalog};
+
+/// The authenticated caller of a request, inserted into the request's extensions by the
+/// app's authentication middleware so [Audit] can report it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal(pub String);
+
+/// A handled request to an audited route.
+#[derive(Debug, Clone)]
+pub struct AuditEvent<Catalog> {
+    pub route: Catalog,
+    pub principal: Option<String>,
+    /// The values of the route's path segments, e.g. `{tenant}`, by name. Those named in the
+    /// route's [Route::SENSITIVE_FIELDS](crate::Route::SENSITIVE_FIELDS) are masked.
+    pub path_params: Vec<(String, String)>,
+    pub status: http::StatusCode,
+}
+
+/// Where the [Audit] middleware sends its events, e.g. a compliance log.
+pub trait AuditSink<Catalog> {
+    fn record(&self, event: AuditEvent<Catalog>);
+}
+
+/// Middleware that reports every request to a route in `Catalog` that is marked
+/// `audited: true` to `S`, once it has been handled, including those that fail with an error.
+///
+/// Only routes registered with [Router::typed_route](crate::Router::typed_route), or through
+/// their module's [Module::register](crate::Module::register), are marked for it.
+pub struct Audit<Catalog, S> {
+    _marker: PhantomData<Catalog>,
+    sink: Rc<S>,
+}
+
+impl<Catalog: RouteCatalog, S: AuditSink<Catalog>> Audit<Catalog, S> {
+    pub fn new(sink: S) -> Self {
+        Audit {
+            _marker: PhantomData,
+            sink: Rc::new(sink),
+        }
+    }
+}
+
+impl<Catalog, S, B, Svc> Transform<Svc, ServiceRequest> for Audit<Catalog, S>
+where
+    Catalog: RouteCatalog,
+    S: AuditSink<Catalog> + 'static,
+    Svc: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AuditMiddleware<Catalog, S, Svc>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: Svc) -> Self::Future {
+        ready(Ok(AuditMiddleware {
+            _marker: PhantomData,
+            sink: Rc::clone(&self.sink),
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct AuditMiddleware<Catalog, S, Svc> {
+    _marker: PhantomData<Catalog>,
+    sink: Rc<S>,
+    service: Rc<Svc>,
+}
+
+impl<Catalog, S, B, Svc> Service<ServiceRequest> for AuditMiddleware<Catalog, S, Svc>
+where
+    Catalog: RouteCatalog,
+    S: AuditSink<Catalog> + 'static,
+    Svc: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let sink = Rc::clone(&self.sink);
+        let service = Rc::clone(&self.service);
+        let slot = AuditSlot::default();
+        req.extensions_mut().insert(slot.clone());
+        Box::pin(async move {
+            let response = service.call(req).await;
+
+            let Some(request) = slot.0.take() else {
+                return response;
+            };
+            let route = Catalog::ALL.iter().copied().find(|id| {
+                id.audited()
+                    && id.name() == request.route.name
+                    && id.uri() == request.route.uri
+                    && id.method() == request.route.method
+            });
+            if let Some(route) = route {
+                let status = match &response {
+                    Ok(response) => response.status(),
+                    Err(err) => err.as_response_error().status_code(),
+                };
+                sink.record(AuditEvent {
+                    route,
+                    principal: request.principal,
+                    path_params: request.path_params,
+                    status: http::StatusCode::from_actix(&status),
+                });
+            }
+            response
+        })
+    }
+}
+
+/// What [Audit] reports about a request, filled in by the resource of the route it reaches.
+struct AuditedRequest {
+    route: AuditedRoute,
+    principal: Option<String>,
+    path_params: Vec<(String, String)>,
+}
+
+/// Where a route's resource leaves the [AuditedRequest] for the [Audit] middleware, which puts
+/// it in the request's extensions, as the request itself is gone if a service fails with an
+/// error.
+#[derive(Clone, Default)]
+struct AuditSlot(Rc<RefCell<Option<AuditedRequest>>>);
+
+/// The parts of an audited route that identify it in a [RouteCatalog].
+#[derive(Clone)]
+pub(crate) struct AuditedRoute {
+    name: &'static str,
+    method: http::Method,
+    uri: &'static str,
+    sensitive_fields: &'static [&'static str],
+}
+
+impl AuditedRoute {
+    /// `Route`, if it's marked `audited: true`.
+    pub(crate) fn of<Route: self::Route>() -> Option<Self> {
+        Route::AUDITED.then_some(AuditedRoute {
+            name: Route::NAME,
+            method: Route::METHOD,
+            uri: Route::URI,
+            sensitive_fields: Route::SENSITIVE_FIELDS,
+        })
+    }
+}
+
+/// Middleware on an audited route's resource that marks the requests it receives for [Audit].
+pub(crate) struct AuditMarker(pub Option<AuditedRoute>);
+
+impl<S, B> Transform<S, ServiceRequest> for AuditMarker
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AuditMarkerMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuditMarkerMiddleware {
+            route: self.0.clone(),
+            service,
+        }))
+    }
+}
+
+pub(crate) struct AuditMarkerMiddleware<S> {
+    route: Option<AuditedRoute>,
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for AuditMarkerMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = S::Future;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(route) = &self.route {
+            let extensions = req.extensions();
+            if let Some(slot) = extensions.get::<AuditSlot>() {
+                *slot.0.borrow_mut() = Some(AuditedRequest {
+                    route: route.clone(),
+                    principal: extensions
+                        .get::<Principal>()
+                        .map(|principal| principal.0.clone()),
+                    path_params: req
+                        .match_info()
+                        .iter()
+                        .map(|(name, value)| {
+                            let value = if route.sensitive_fields.contains(&name) {
+                                MASK
+                            } else {
+                                value
+                            };
+                            (name.to_owned(), value.to_owned())
+                        })
+                        .collect(),
+                });
+            }
+        }
+        self.service.call(req)
+    }
+}
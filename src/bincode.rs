@@ -0,0 +1,87 @@
+//! Bodies encoded with [bincode](::bincode), for endpoints where both ends are Rust.
+
+use std::{future::Future, ops::Deref, pin::Pin};
+
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    format::{self, BodyFormat, FormatBody},
+    BodyError, FromRequest, IntoResponse, NoBody, RequestBuilder, Response, TransportResponse,
+};
+
+/// The content type bincode bodies are sent with.
+pub const CONTENT_TYPE: &str = "application/vnd.bincode";
+
+/// The bincode [BodyFormat].
+pub struct BincodeFormat;
+
+impl BodyFormat for BincodeFormat {
+    const CONTENT_TYPE: &'static str = CONTENT_TYPE;
+
+    type Error = ::bincode::Error;
+
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Self::Error> {
+        ::bincode::serialize(value)
+    }
+
+    fn deserialize<T: DeserializeOwned>(body: &[u8]) -> Result<T, Self::Error> {
+        ::bincode::deserialize(body)
+    }
+}
+
+/// A type that indicates that the request or response guarantees that its body will be
+/// bincode that successfully deserializes into a `T`.
+pub type BincodeBody<T> = FormatBody<BincodeFormat, T>;
+
+impl<T, Route: crate::Route<RequestBody = BincodeBody<T>>, Query>
+    RequestBuilder<Route, Query, NoBody>
+{
+    pub fn bincode(self, body: T) -> RequestBuilder<Route, Query, BincodeBody<T>> {
+        self.body(body)
+    }
+}
+
+impl<
+        T: DeserializeOwned,
+        Route: crate::Route<ResponseBody = BincodeBody<T>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    pub async fn bincode(self) -> Result<T, BodyError<::bincode::Error, R::Error>> {
+        self.decode().await
+    }
+}
+
+/// An extractor and responder for bincode bodies, the counterpart of [BincodeBody].
+pub struct Bincode<T>(pub T);
+
+impl<T: DeserializeOwned + 'static> actix_web::FromRequest for Bincode<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let body = format::extract::<BincodeFormat, T>(req, payload);
+        Box::pin(async move { body.await.map(Bincode) })
+    }
+}
+
+impl<T: Serialize> actix_web::Responder for Bincode<T> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        format::respond::<BincodeFormat, T>(&self.0)
+    }
+}
+
+impl<T: DeserializeOwned, Query> FromRequest<Query, BincodeBody<T>> for Bincode<T> {}
+
+impl<T: Serialize> IntoResponse<BincodeBody<T>> for Bincode<T> {}
+
+impl<T> Deref for Bincode<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
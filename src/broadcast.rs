@@ -0,0 +1,79 @@
+//! Fanning typed events out to every connected client, e.g. from the handler that makes a
+//! change to the event streams of everyone watching.
+//!
+//! ```
+//! # use actix_web::{web::{Data, Path}, HttpResponse, Responder};
+//! # use typed_routing::broadcast::TypedBroadcast;
+//! # #[derive(Clone, serde::Serialize)]
+//! # enum OrderEvent { Updated(u64) }
+//! async fn order_events(orders: Data<TypedBroadcast<OrderEvent>>) -> impl Responder {
+//!     orders.sse()
+//! }
+//!
+//! async fn update_order(orders: Data<TypedBroadcast<OrderEvent>>, id: Path<u64>) -> impl Responder {
+//!     // ...
+//!     orders.send(OrderEvent::Updated(*id));
+//!     HttpResponse::NoContent()
+//! }
+//! ```
+
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::sse::{Event, Sse};
+
+/// The error a subscriber receives when it fell too far behind, and missed events.
+#[derive(Debug, Error)]
+#[error("The subscriber fell behind and missed {0} events")]
+pub struct Lagged(pub u64);
+
+/// A channel that sends each event to every subscriber. Cloning it shares the channel.
+#[derive(Debug, Clone)]
+pub struct TypedBroadcast<T> {
+    sender: broadcast::Sender<T>,
+}
+
+impl<T: Clone + 'static> TypedBroadcast<T> {
+    /// A channel that keeps the last `capacity` events for subscribers that haven't received
+    /// them yet. Subscribers that fall further behind skip ahead and are told they [Lagged].
+    pub fn new(capacity: usize) -> Self {
+        TypedBroadcast {
+            sender: broadcast::Sender::new(capacity),
+        }
+    }
+
+    /// Send `event` to the current subscribers, returning how many there are. Events sent while
+    /// there are none are dropped.
+    pub fn send(&self, event: T) -> usize {
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// The events sent from now on.
+    pub fn subscribe(&self) -> impl Stream<Item = Result<T, Lagged>> {
+        futures_util::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            match receiver.recv().await {
+                Ok(event) => Some((Ok(event), receiver)),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    Some((Err(Lagged(missed)), receiver))
+                }
+                Err(broadcast::error::RecvError::Closed) => None,
+            }
+        })
+    }
+
+    /// Stream the events sent from now on to a client, skipping any it lags behind on.
+    pub fn sse(&self) -> Sse<impl Stream<Item = Event<T>>>
+    where
+        T: Serialize,
+    {
+        Sse(self
+            .subscribe()
+            .filter_map(|event| futures_util::future::ready(event.ok().map(Event::new))))
+    }
+}
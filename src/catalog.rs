@@ -0,0 +1,245 @@
+//! Comparing two versions of a [SchemaCatalog], e.g. the last release's and the current
+//! build's, to catch breaking API changes in release tooling.
+//!
+//! Changes are classified from the point of view of clients built against `old`: a change is
+//! breaking if such a client could send a request the new server rejects, or fail to decode
+//! its response.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::schema::{RouteFingerprint, SchemaCatalog};
+
+/// The changes between two catalogs, from [diff].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CatalogDiff {
+    pub changes: Vec<RouteChange>,
+}
+
+impl CatalogDiff {
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|change| change.breaking)
+    }
+
+    pub fn breaking(&self) -> impl Iterator<Item = &RouteChange> {
+        self.changes.iter().filter(|change| change.breaking)
+    }
+}
+
+/// A change to one route.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteChange {
+    /// The route's name.
+    pub route: String,
+    pub breaking: bool,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+}
+
+/// The part of a route a field belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Part {
+    Query,
+    RequestBody,
+    ResponseBody,
+}
+
+impl Part {
+    /// Whether the server receives this part, rather than sending it.
+    fn received(self) -> bool {
+        matches!(self, Part::Query | Part::RequestBody)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// The route's method or URI changed.
+    Moved {
+        method: String,
+        uri: String,
+    },
+    AddedField {
+        part: Part,
+        field: String,
+        required: bool,
+    },
+    RemovedField {
+        part: Part,
+        field: String,
+        required: bool,
+    },
+    /// A field became required, or stopped being required.
+    ChangedRequired {
+        part: Part,
+        field: String,
+        required: bool,
+    },
+    /// A field's schema changed.
+    ChangedField {
+        part: Part,
+        field: String,
+    },
+    /// The schema changed in a way that isn't broken down into fields, e.g. in a nested type,
+    /// or either catalog doesn't have the route's schemas.
+    ChangedSchema {
+        part: Option<Part>,
+    },
+}
+
+impl ChangeKind {
+    fn breaking(&self) -> bool {
+        match self {
+            ChangeKind::Added => false,
+            ChangeKind::Removed | ChangeKind::Moved { .. } => true,
+            // Old clients don't send new fields, and new clients don't expect them.
+            ChangeKind::AddedField { part, required, .. } => part.received() && *required,
+            // Unknown fields are ignored when deserializing, so old clients can still send
+            // removed ones, but can't do without removed response fields they relied on.
+            ChangeKind::RemovedField { part, required, .. } => !part.received() && *required,
+            ChangeKind::ChangedRequired { part, required, .. } => part.received() == *required,
+            ChangeKind::ChangedField { .. } | ChangeKind::ChangedSchema { .. } => true,
+        }
+    }
+}
+
+/// The changes from `old` to `new`, matching routes by name.
+pub fn diff(old: &SchemaCatalog, new: &SchemaCatalog) -> CatalogDiff {
+    let mut kinds: Vec<(String, ChangeKind)> = Vec::new();
+    for old_route in &old.0 {
+        let Some(new_route) = new.0.iter().find(|route| route.name == old_route.name) else {
+            kinds.push((old_route.name.clone(), ChangeKind::Removed));
+            continue;
+        };
+        kinds.extend(
+            route_changes(old_route, new_route)
+                .into_iter()
+                .map(|kind| (old_route.name.clone(), kind)),
+        );
+    }
+    for new_route in &new.0 {
+        if !old.0.iter().any(|route| route.name == new_route.name) {
+            kinds.push((new_route.name.clone(), ChangeKind::Added));
+        }
+    }
+    CatalogDiff {
+        changes: kinds
+            .into_iter()
+            .map(|(route, kind)| RouteChange {
+                route,
+                breaking: kind.breaking(),
+                kind,
+            })
+            .collect(),
+    }
+}
+
+fn route_changes(old: &RouteFingerprint, new: &RouteFingerprint) -> Vec<ChangeKind> {
+    let mut changes = Vec::new();
+    if old.method != new.method || old.uri != new.uri {
+        changes.push(ChangeKind::Moved {
+            method: new.method.clone(),
+            uri: new.uri.clone(),
+        });
+    }
+    if old.fingerprint == new.fingerprint {
+        return changes;
+    }
+    for (part, old_schema, new_schema) in [
+        (Part::Query, &old.query, &new.query),
+        (Part::RequestBody, &old.request, &new.request),
+        (Part::ResponseBody, &old.response, &new.response),
+    ] {
+        if old_schema != new_schema {
+            changes.extend(schema_changes(
+                part,
+                old_schema.as_ref(),
+                new_schema.as_ref(),
+            ));
+        }
+    }
+    if changes.is_empty() {
+        changes.push(ChangeKind::ChangedSchema { part: None });
+    }
+    changes
+}
+
+/// The changes to the top level fields of an object schema, and a [ChangeKind::ChangedSchema]
+/// if anything else in it, e.g. a nested type's definition, changed too.
+fn schema_changes(part: Part, old: Option<&Value>, new: Option<&Value>) -> Vec<ChangeKind> {
+    let changed_schema = ChangeKind::ChangedSchema { part: Some(part) };
+    let (Some(old), Some(new)) = (old, new) else {
+        return vec![changed_schema];
+    };
+    let (Some((old_fields, old_required)), Some((new_fields, new_required))) =
+        (fields(old), fields(new))
+    else {
+        return vec![changed_schema];
+    };
+
+    let mut changes = Vec::new();
+    for (field, old_schema) in old_fields {
+        let was_required = old_required.contains(&field.as_str());
+        match new_fields.get(field) {
+            None => changes.push(ChangeKind::RemovedField {
+                part,
+                field: field.clone(),
+                required: was_required,
+            }),
+            Some(new_schema) => {
+                if new_schema != old_schema {
+                    changes.push(ChangeKind::ChangedField {
+                        part,
+                        field: field.clone(),
+                    });
+                }
+                let required = new_required.contains(&field.as_str());
+                if required != was_required {
+                    changes.push(ChangeKind::ChangedRequired {
+                        part,
+                        field: field.clone(),
+                        required,
+                    });
+                }
+            }
+        }
+    }
+    for field in new_fields.keys() {
+        if !old_fields.contains_key(field) {
+            changes.push(ChangeKind::AddedField {
+                part,
+                field: field.clone(),
+                required: new_required.contains(&field.as_str()),
+            });
+        }
+    }
+    if rest(old) != rest(new) {
+        changes.push(changed_schema);
+    }
+    changes
+}
+
+/// A schema without its fields, or the parts that don't affect what it accepts.
+fn rest(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Some(schema) = schema.as_object_mut() {
+        for key in ["properties", "required", "title", "description"] {
+            schema.remove(key);
+        }
+    }
+    schema
+}
+
+/// An object schema's properties and the names of the required ones.
+fn fields(schema: &Value) -> Option<(&serde_json::Map<String, Value>, Vec<&str>)> {
+    let properties = schema.get("properties")?.as_object()?;
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    Some((properties, required))
+}
@@ -0,0 +1,290 @@
+//! Injecting faults into a client's requests, e.g. in tests or a staging build, to exercise its
+//! error handling for slow, failing or garbled responses.
+//!
+//! ```
+//! # mod api {
+//! #     use typed_routing::{erased::ErasedRoute, Method, NoBody, NoQuery, Route, RouteCatalog};
+//! #     pub struct GetOrder;
+//! #     impl Route for GetOrder {
+//! #         type Query = NoQuery;
+//! #         type RequestBody = NoBody;
+//! #         type ResponseBody = NoBody;
+//! #         const METHOD: Method = Method::GET;
+//! #         const NAME: &'static str = "GetOrder";
+//! #         const URI_PART: &'static str = "/orders/{id}";
+//! #         const URI: &'static str = "/orders/{id}";
+//! #     }
+//! #     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+//! #     pub enum RouteId { GetOrder }
+//! #     impl std::fmt::Display for RouteId {
+//! #         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { f.write_str(GetOrder::NAME) }
+//! #     }
+//! #     impl RouteCatalog for RouteId {
+//! #         const ALL: &'static [Self] = &[RouteId::GetOrder];
+//! #         fn name(self) -> &'static str { GetOrder::NAME }
+//! #         fn method(self) -> Method { GetOrder::METHOD }
+//! #         fn uri(self) -> &'static str { GetOrder::URI }
+//! #         fn required_scopes(self) -> &'static [&'static str] { &[] }
+//! #         fn audited(self) -> bool { false }
+//! #         fn feature_flag(self) -> Option<&'static str> { None }
+//! #         fn alias(self) -> Option<&'static str> { None }
+//! #         fn sensitive_fields(self) -> &'static [&'static str] { &[] }
+//! #         fn erased(self) -> &'static dyn ErasedRoute { &GetOrder }
+//! #     }
+//! # }
+//! # #[cfg(feature = "hyper")]
+//! # fn transport(base_url: typed_routing::http::Uri) {
+//! # use std::time::Duration;
+//! # use typed_routing::{chaos::{Chaos, FaultConfig}, hyper::Hyper, StatusCode};
+//! let transport = Chaos::<_, api::RouteId, _>::new(Hyper::new(base_url), tokio::time::sleep)
+//!     .seed(42)
+//!     .route(
+//!         api::RouteId::GetOrder,
+//!         FaultConfig::new()
+//!             .latency(Duration::from_secs(2), 0.1)
+//!             .server_error(StatusCode::SERVICE_UNAVAILABLE, 0.05)
+//!             .malformed_json(0.05),
+//!     );
+//! # }
+//! ```
+//!
+//! Faults are chosen with a seeded pseudo-random generator by default, so the same seed and the
+//! same sequence of requests inject the same faults. Tests can pick them outright with
+//! [Chaos::rng].
+
+use std::{collections::HashMap, future::Future, marker::PhantomData, sync::Mutex, time::Duration};
+
+use bytes::Bytes;
+use futures_util::future::Either;
+
+use crate::{
+    clock::{Rng, SeededRng},
+    ClientTransport, RouteCatalog, RouteName, TransportResponse,
+};
+
+/// The body of responses given a [Fault::MalformedJson]: JSON that ends partway through.
+const MALFORMED_JSON: &[u8] = br#"{"injected": "#;
+
+/// A fault that can be injected into a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Wait before sending the request.
+    Latency(Duration),
+    /// Respond with this status and no body, without sending the request.
+    ServerError(http::StatusCode),
+    /// Cut the response body off halfway.
+    TruncatedBody,
+    /// Replace the response body with invalid JSON.
+    MalformedJson,
+}
+
+/// Which faults to inject into a route's requests, and how often.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultConfig {
+    /// Each fault and the fraction of requests, from `0.0` to `1.0`, it's injected into. Each
+    /// is rolled for separately, so a request can be both delayed and fail.
+    pub faults: Vec<(Fault, f64)>,
+}
+
+impl FaultConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fault(mut self, fault: Fault, rate: f64) -> Self {
+        self.faults.push((fault, rate));
+        self
+    }
+
+    pub fn latency(self, delay: Duration, rate: f64) -> Self {
+        self.fault(Fault::Latency(delay), rate)
+    }
+
+    pub fn server_error(self, status: http::StatusCode, rate: f64) -> Self {
+        self.fault(Fault::ServerError(status), rate)
+    }
+
+    pub fn truncated_body(self, rate: f64) -> Self {
+        self.fault(Fault::TruncatedBody, rate)
+    }
+
+    pub fn malformed_json(self, rate: f64) -> Self {
+        self.fault(Fault::MalformedJson, rate)
+    }
+}
+
+/// A [ClientTransport] that injects faults into requests to the routes in `Catalog` that have
+/// been given a [FaultConfig], directly or with [Chaos::default_config].
+///
+/// `sleep` should return a future that completes after the given duration, using whichever
+/// runtime the client runs on.
+pub struct Chaos<T, Catalog, S, R = SeededRng> {
+    _marker: PhantomData<Catalog>,
+    inner: T,
+    sleep: S,
+    default: Option<FaultConfig>,
+    configs: HashMap<Catalog, FaultConfig>,
+    rng: Mutex<R>,
+}
+
+impl<T, Catalog: RouteCatalog, S> Chaos<T, Catalog, S> {
+    pub fn new(inner: T, sleep: S) -> Self {
+        Chaos {
+            _marker: PhantomData,
+            inner,
+            sleep,
+            default: None,
+            configs: HashMap::new(),
+            rng: Mutex::new(SeededRng::new(0)),
+        }
+    }
+
+    /// Seed the generator faults are chosen with. Defaults to `0`.
+    pub fn seed(self, seed: u64) -> Self {
+        self.rng(SeededRng::new(seed))
+    }
+}
+
+impl<T, Catalog: RouteCatalog, S, R: Rng> Chaos<T, Catalog, S, R> {
+    /// Choose faults with `rng`. A fault is injected when the number it gives is below the
+    /// fault's rate.
+    pub fn rng<R2: Rng>(self, rng: R2) -> Chaos<T, Catalog, S, R2> {
+        Chaos {
+            _marker: PhantomData,
+            inner: self.inner,
+            sleep: self.sleep,
+            default: self.default,
+            configs: self.configs,
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// Inject faults with `config` into every route in `Catalog` that hasn't been given its own.
+    pub fn default_config(mut self, config: FaultConfig) -> Self {
+        self.default = Some(config);
+        self
+    }
+
+    pub fn route(mut self, route: Catalog, config: FaultConfig) -> Self {
+        self.configs.insert(route, config);
+        self
+    }
+
+    fn config(&self, request: &http::Request<Bytes>) -> Option<&FaultConfig> {
+        let name = request.extensions().get::<RouteName>()?;
+        let route = Catalog::ALL
+            .iter()
+            .copied()
+            .find(|id| id.name() == name.0 && id.method() == request.method())?;
+        self.configs.get(&route).or(self.default.as_ref())
+    }
+
+    /// The faults to inject into a request, rolled for in order.
+    fn roll(&self, config: &FaultConfig) -> Vec<Fault> {
+        let mut rng = self.rng.lock().unwrap();
+        config
+            .faults
+            .iter()
+            .filter(|(_, rate)| rng.next_f64() < *rate)
+            .map(|(fault, _)| *fault)
+            .collect()
+    }
+}
+
+impl<T, Catalog, S, F, R> ClientTransport for Chaos<T, Catalog, S, R>
+where
+    T: ClientTransport,
+    Catalog: RouteCatalog,
+    R: Rng,
+    S: Fn(Duration) -> F,
+    F: Future<Output = ()>,
+{
+    type Response = ChaosResponse<T::Response>;
+    type Error = T::Error;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let faults = self
+            .config(&request)
+            .map(|config| self.roll(config))
+            .unwrap_or_default();
+
+        let mut body = BodyFault::None;
+        for fault in faults {
+            match fault {
+                Fault::Latency(delay) => (self.sleep)(delay).await,
+                Fault::ServerError(status) => {
+                    return Ok(ChaosResponse(Faulted::ServerError {
+                        status,
+                        headers: http::HeaderMap::new(),
+                    }))
+                }
+                Fault::TruncatedBody => body = BodyFault::Truncated,
+                Fault::MalformedJson => body = BodyFault::Malformed,
+            }
+        }
+        let response = self.inner.send(request).await?;
+        Ok(ChaosResponse(Faulted::Sent { response, body }))
+    }
+}
+
+enum BodyFault {
+    None,
+    Truncated,
+    Malformed,
+}
+
+/// The response from a [Chaos] transport, with any faults injected into it.
+pub struct ChaosResponse<R>(Faulted<R>);
+
+enum Faulted<R> {
+    Sent {
+        response: R,
+        body: BodyFault,
+    },
+    ServerError {
+        status: http::StatusCode,
+        headers: http::HeaderMap,
+    },
+}
+
+impl<R: TransportResponse> TransportResponse for ChaosResponse<R> {
+    type Error = R::Error;
+
+    fn status(&self) -> http::StatusCode {
+        match &self.0 {
+            Faulted::Sent { response, .. } => response.status(),
+            Faulted::ServerError { status, .. } => *status,
+        }
+    }
+
+    fn headers(&self) -> &http::HeaderMap {
+        match &self.0 {
+            Faulted::Sent { response, .. } => response.headers(),
+            Faulted::ServerError { headers, .. } => headers,
+        }
+    }
+
+    async fn bytes(self) -> Result<Bytes, Self::Error> {
+        match self.0 {
+            Faulted::Sent { response, body } => {
+                let bytes = response.bytes().await?;
+                Ok(match body {
+                    BodyFault::None => bytes,
+                    BodyFault::Truncated => bytes.slice(..bytes.len() / 2),
+                    BodyFault::Malformed => Bytes::from_static(MALFORMED_JSON),
+                })
+            }
+            Faulted::ServerError { .. } => Ok(Bytes::new()),
+        }
+    }
+
+    fn chunks(self) -> impl futures_util::Stream<Item = Result<Bytes, Self::Error>> {
+        match self.0 {
+            Faulted::Sent {
+                response,
+                body: BodyFault::None,
+            } => Either::Left(response.chunks()),
+            faulted => Either::Right(futures_util::stream::once(ChaosResponse(faulted).bytes())),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Per-route circuit breaking, so a client stops sending requests to a backend that keeps
+//! failing them.
+
+use std::{collections::HashMap, marker::PhantomData, sync::Mutex, time::Duration};
+
+use bytes::Bytes;
+use thiserror::Error;
+use web_time::Instant;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    ClientTransport, RouteCatalog, RouteName, TransportResponse,
+};
+
+/// When a route's circuit opens, and how it recovers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitConfig {
+    /// How many failures in a row open the circuit.
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before probe requests are let through.
+    pub open_for: Duration,
+    /// How many probe requests may be in flight at once while the circuit is half-open.
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitConfig {
+    fn default() -> Self {
+        CircuitConfig {
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+            half_open_probes: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { probes: u32 },
+}
+
+/// The error returned instead of sending a request while its route's circuit is open.
+#[derive(Debug, Clone, Error)]
+#[error("The circuit for {route} is open")]
+pub struct CircuitOpen<Catalog: RouteCatalog> {
+    pub route: Catalog,
+    /// How long until probe requests will be let through, zero if the circuit is already
+    /// half-open and waiting on probes.
+    pub retry_after: Duration,
+}
+
+/// The error returned by a [CircuitBreaker] transport.
+#[derive(Debug, Error)]
+pub enum CircuitError<Catalog: RouteCatalog, TransportError> {
+    #[error(transparent)]
+    Open(CircuitOpen<Catalog>),
+    #[error("Failed to send the request")]
+    Transport(#[source] TransportError),
+}
+
+/// A [ClientTransport] that tracks failures, i.e. transport errors and 5xx responses, of each
+/// route in `Catalog`, and fails requests with [CircuitOpen] once a route has failed too many
+/// times in a row.
+///
+/// Only routes that have been given a [CircuitConfig], directly or with
+/// [CircuitBreaker::default_config], are broken. Requests to routes outside of `Catalog` are
+/// always sent.
+pub struct CircuitBreaker<T, Catalog, C = SystemClock> {
+    _marker: PhantomData<Catalog>,
+    inner: T,
+    clock: C,
+    default: Option<CircuitConfig>,
+    configs: HashMap<Catalog, CircuitConfig>,
+    states: Mutex<HashMap<Catalog, State>>,
+}
+
+impl<T, Catalog: RouteCatalog> CircuitBreaker<T, Catalog> {
+    pub fn new(inner: T) -> Self {
+        CircuitBreaker {
+            _marker: PhantomData,
+            inner,
+            clock: SystemClock,
+            default: None,
+            configs: HashMap::new(),
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T, Catalog: RouteCatalog, C: Clock> CircuitBreaker<T, Catalog, C> {
+    /// Time how long circuits stay open with `clock`, e.g. a
+    /// [ManualClock](crate::clock::ManualClock) in tests.
+    pub fn clock<C2: Clock>(self, clock: C2) -> CircuitBreaker<T, Catalog, C2> {
+        CircuitBreaker {
+            _marker: PhantomData,
+            inner: self.inner,
+            clock,
+            default: self.default,
+            configs: self.configs,
+            states: self.states,
+        }
+    }
+
+    /// Break every route in `Catalog` that hasn't been given its own config with `config`.
+    pub fn default_config(mut self, config: CircuitConfig) -> Self {
+        self.default = Some(config);
+        self
+    }
+
+    /// Break `route` with `config`.
+    pub fn route(mut self, route: Catalog, config: CircuitConfig) -> Self {
+        self.configs.insert(route, config);
+        self
+    }
+
+    fn config(&self, route: Catalog) -> Option<CircuitConfig> {
+        self.configs.get(&route).copied().or(self.default)
+    }
+
+    /// Let a request to `route` through, or say why not.
+    fn admit(&self, route: Catalog, config: CircuitConfig) -> Result<(), CircuitOpen<Catalog>> {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(route).or_insert(State::Closed { failures: 0 });
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } => {
+                let now = self.clock.now();
+                if now < until {
+                    return Err(CircuitOpen {
+                        route,
+                        retry_after: until - now,
+                    });
+                }
+                *state = State::HalfOpen { probes: 1 };
+                Ok(())
+            }
+            State::HalfOpen { probes } if probes < config.half_open_probes => {
+                *state = State::HalfOpen { probes: probes + 1 };
+                Ok(())
+            }
+            State::HalfOpen { .. } => Err(CircuitOpen {
+                route,
+                retry_after: Duration::ZERO,
+            }),
+        }
+    }
+
+    fn record(&self, route: Catalog, config: CircuitConfig, succeeded: bool) {
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(route).or_insert(State::Closed { failures: 0 });
+        let open = State::Open {
+            until: self.clock.now() + config.open_for,
+        };
+        *state = match (*state, succeeded) {
+            (_, true) => State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 < config.failure_threshold => {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            // A request sent before the circuit opened may finish after it.
+            (State::Open { until }, false) => State::Open { until },
+            (_, false) => open,
+        };
+    }
+}
+
+impl<T: ClientTransport, Catalog: RouteCatalog, C: Clock> ClientTransport
+    for CircuitBreaker<T, Catalog, C>
+{
+    type Response = T::Response;
+    type Error = CircuitError<Catalog, T::Error>;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let route = request.extensions().get::<RouteName>().and_then(|name| {
+            Catalog::ALL
+                .iter()
+                .copied()
+                .find(|id| id.name() == name.0 && id.method() == request.method())
+        });
+        let Some((route, config)) = route.and_then(|route| Some((route, self.config(route)?)))
+        else {
+            return self
+                .inner
+                .send(request)
+                .await
+                .map_err(CircuitError::Transport);
+        };
+
+        self.admit(route, config).map_err(CircuitError::Open)?;
+        let result = self.inner.send(request).await;
+        let succeeded = matches!(&result, Ok(response) if !response.status().is_server_error());
+        self.record(route, config, succeeded);
+        result.map_err(CircuitError::Transport)
+    }
+}
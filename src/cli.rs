@@ -0,0 +1,316 @@
+//! An admin and debugging command line for any API defined with this crate, with a [clap]
+//! subcommand per route.
+//!
+//! A route's path parameters become positional arguments, and the fields of its query and body
+//! types become flags, so those types need to derive [clap::Args] as well as their serde traits.
+//!
+//! ```
+//! # use serde::Serialize;
+//! # use typed_routing::{cli::Cli, hyper::Hyper};
+//! #[derive(Serialize, clap::Args)]
+//! pub struct Search {
+//!     #[arg(long)]
+//!     name: String,
+//! }
+//! # mod routes {
+//! #     use typed_routing::{JsonBody, Method, NoBody, NoQuery, Query, Route};
+//! #     pub struct GetOrder;
+//! #     impl Route for GetOrder {
+//! #         type Query = NoQuery;
+//! #         type RequestBody = NoBody;
+//! #         type ResponseBody = JsonBody<String>;
+//! #         const METHOD: Method = Method::GET;
+//! #         const NAME: &'static str = "GetOrder";
+//! #         const URI_PART: &'static str = "/orders/{id}";
+//! #         const URI: &'static str = "/orders/{id}";
+//! #     }
+//! #     pub struct SearchOrders;
+//! #     impl Route for SearchOrders {
+//! #         type Query = Query<super::Search>;
+//! #         type RequestBody = NoBody;
+//! #         type ResponseBody = JsonBody<Vec<String>>;
+//! #         const METHOD: Method = Method::GET;
+//! #         const NAME: &'static str = "SearchOrders";
+//! #         const URI_PART: &'static str = "/orders";
+//! #         const URI: &'static str = "/orders";
+//! #     }
+//! # }
+//! # async fn run() {
+//!
+//! let transport = Hyper::new("http://localhost:8080".parse().unwrap());
+//! Cli::new("orders")
+//!     .route::<routes::GetOrder>()
+//!     .route::<routes::SearchOrders>()
+//!     .main(&transport)
+//!     .await;
+//! # }
+//! # fn main() {}
+//! ```
+
+use std::{error::Error, marker::PhantomData};
+
+use clap::{ArgMatches, Command};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::{
+    links::{fill_uri, placeholder},
+    redact::redact_body,
+    ApplyToRequestBody, ApplyToRequestHead, ClientTransport, FormBody, JsonBody, NoBody, NoQuery,
+    Query, RequestBuilder, Route, SensitiveFields, TransportResponse,
+};
+
+/// Implemented by query and request body types whose contents can be given as command line
+/// flags.
+pub trait CliArgs: Sized {
+    /// Add the flags to a route's subcommand.
+    fn augment(command: Command) -> Command;
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error>;
+}
+
+impl CliArgs for NoQuery {
+    fn augment(command: Command) -> Command {
+        command
+    }
+
+    fn from_matches(_matches: &ArgMatches) -> Result<Self, clap::Error> {
+        Ok(NoQuery)
+    }
+}
+
+impl CliArgs for NoBody {
+    fn augment(command: Command) -> Command {
+        command
+    }
+
+    fn from_matches(_matches: &ArgMatches) -> Result<Self, clap::Error> {
+        Ok(NoBody)
+    }
+}
+
+impl<T: clap::Args, F> CliArgs for Query<T, F> {
+    fn augment(command: Command) -> Command {
+        T::augment_args(command)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        T::from_arg_matches(matches).map(|query| Query(query, PhantomData))
+    }
+}
+
+impl<T: clap::Args> CliArgs for JsonBody<T> {
+    fn augment(command: Command) -> Command {
+        T::augment_args(command)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        T::from_arg_matches(matches).map(JsonBody)
+    }
+}
+
+impl<T: clap::Args> CliArgs for FormBody<T> {
+    fn augment(command: Command) -> Command {
+        T::augment_args(command)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        T::from_arg_matches(matches).map(FormBody)
+    }
+}
+
+/// Implemented by response body types that the command line can print.
+pub trait CliOutput {
+    fn render(body: &[u8]) -> Result<String, Box<dyn Error>>;
+}
+
+impl CliOutput for NoBody {
+    fn render(body: &[u8]) -> Result<String, Box<dyn Error>> {
+        Ok(String::from_utf8_lossy(body).into_owned())
+    }
+}
+
+/// JSON responses are decoded into their typed body, so that a response that doesn't match the
+/// route fails, and printed indented.
+impl<T: DeserializeOwned + Serialize> CliOutput for JsonBody<T> {
+    fn render(body: &[u8]) -> Result<String, Box<dyn Error>> {
+        let body: T = serde_json::from_slice(body)?;
+        Ok(serde_json::to_string_pretty(&body)?)
+    }
+}
+
+/// The error returned by [Cli::run].
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error(transparent)]
+    Args(#[from] clap::Error),
+    #[error("Failed to build the request")]
+    Build(#[source] Box<dyn Error>),
+    #[error("Failed to send the request")]
+    Transport(#[source] Box<dyn Error>),
+    /// The request failed, with the response body's sensitive fields masked.
+    #[error("The request failed with status {status}: {body}")]
+    Status {
+        status: http::StatusCode,
+        body: String,
+    },
+    #[error("Failed to read the response")]
+    Body(#[source] Box<dyn Error>),
+}
+
+type BuildRequest = fn(&ArgMatches) -> Result<http::Request<bytes::Bytes>, CliError>;
+type Render = fn(&[u8]) -> Result<String, Box<dyn Error>>;
+
+struct CliRoute {
+    name: String,
+    request: BuildRequest,
+    render: Render,
+}
+
+/// A command line with a subcommand for each added route.
+pub struct Cli {
+    command: Command,
+    routes: Vec<CliRoute>,
+}
+
+impl Cli {
+    /// A command line called `name`, with no routes yet.
+    pub fn new(name: impl Into<clap::builder::Str>) -> Self {
+        Self {
+            command: Command::new(name).subcommand_required(true),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Edit the top-level command, e.g. to set its version or description.
+    pub fn command(mut self, f: impl FnOnce(Command) -> Command) -> Self {
+        self.command = f(self.command);
+        self
+    }
+
+    /// Add a subcommand for `R`, named after the route in kebab case, e.g. `get-order` for
+    /// `GetOrder`.
+    pub fn route<R>(mut self) -> Self
+    where
+        R: Route,
+        R::Query: CliArgs,
+        R::RequestBody: CliArgs,
+        R::ResponseBody: CliOutput,
+        <R::Query as ApplyToRequestHead>::Error: Error + 'static,
+        <R::RequestBody as ApplyToRequestBody>::Error: Error + 'static,
+    {
+        let name = kebab_case(R::NAME);
+        let mut subcommand = Command::new(name.clone()).about(format!("{} {}", R::METHOD, R::URI));
+        for param in R::URI.split('/').filter_map(placeholder) {
+            subcommand = subcommand.arg(clap::Arg::new(param.to_owned()).required(true));
+        }
+        subcommand = R::RequestBody::augment(R::Query::augment(subcommand));
+
+        self.command = self.command.subcommand(subcommand);
+        self.routes.push(CliRoute {
+            name,
+            request: request::<R>,
+            render: R::ResponseBody::render,
+        });
+        self
+    }
+
+    /// Parse `args`, send the chosen route's request with `transport` and return its response
+    /// body, ready to print.
+    pub async fn run<T, I>(&self, transport: &T, args: I) -> Result<String, CliError>
+    where
+        T: ClientTransport,
+        T::Error: Error + 'static,
+        <T::Response as TransportResponse>::Error: Error + 'static,
+        I: IntoIterator,
+        I::Item: Into<std::ffi::OsString> + Clone,
+    {
+        let matches = self.command.clone().try_get_matches_from(args)?;
+        let (name, matches) = matches
+            .subcommand()
+            .expect("the command requires a subcommand");
+        let route = self
+            .routes
+            .iter()
+            .find(|route| route.name == name)
+            .expect("every subcommand has a route");
+
+        let request = (route.request)(matches)?;
+        let sensitive = request
+            .extensions()
+            .get::<SensitiveFields>()
+            .map_or(&[][..], |fields| fields.0);
+        let response = transport
+            .send(request)
+            .await
+            .map_err(|err| CliError::Transport(Box::new(err)))?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| CliError::Body(Box::new(err)))?;
+        if !status.is_success() {
+            return Err(CliError::Status {
+                status,
+                body: String::from_utf8_lossy(&redact_body(&headers, &body, sensitive))
+                    .into_owned(),
+            });
+        }
+        (route.render)(&body).map_err(CliError::Body)
+    }
+
+    /// Run with the process's arguments and print the result, exiting with a non-zero status if
+    /// it failed.
+    pub async fn main<T>(&self, transport: &T)
+    where
+        T: ClientTransport,
+        T::Error: Error + 'static,
+        <T::Response as TransportResponse>::Error: Error + 'static,
+    {
+        match self.run(transport, std::env::args_os()).await {
+            Ok(output) => println!("{output}"),
+            Err(CliError::Args(err)) => err.exit(),
+            Err(err) => {
+                eprintln!("{err}");
+                let mut source = err.source();
+                while let Some(err) = source {
+                    eprintln!("  caused by: {err}");
+                    source = err.source();
+                }
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+fn request<R>(matches: &ArgMatches) -> Result<http::Request<bytes::Bytes>, CliError>
+where
+    R: Route,
+    R::Query: CliArgs,
+    R::RequestBody: CliArgs,
+    <R::Query as ApplyToRequestHead>::Error: Error + 'static,
+    <R::RequestBody as ApplyToRequestBody>::Error: Error + 'static,
+{
+    let uri = fill_uri(R::URI, |name| {
+        matches.get_one::<String>(name).map(String::as_str)
+    })
+    .map_err(|err| CliError::Build(Box::new(err)))?;
+    RequestBuilder::<R, NoQuery, NoBody>::with_uri(&uri)
+        .with_query(R::Query::from_matches(matches)?)
+        .with_body(R::RequestBody::from_matches(matches)?)
+        .build()
+        .map(|request| request.into_untyped_request())
+        .map_err(|err| CliError::Build(Box::new(err)))
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::with_capacity(name.len() + 4);
+    for (i, c) in name.char_indices() {
+        if c.is_ascii_uppercase() && i > 0 {
+            kebab.push('-');
+        }
+        kebab.push(c.to_ascii_lowercase());
+    }
+    kebab.replace('_', "-")
+}
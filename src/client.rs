@@ -0,0 +1,102 @@
+//! Headers sent with every request from a client, such as a `User-Agent`, an API version or a
+//! per-request correlation id, without adding them to each [RequestBuilder](crate::RequestBuilder).
+//!
+//! ```
+//! # #[cfg(feature = "hyper")]
+//! # fn client(base_url: typed_routing::http::Uri) {
+//! # use std::sync::atomic::{AtomicU64, Ordering};
+//! # use typed_routing::{client::Client, hyper::Hyper, HeaderName, HeaderValue};
+//! let next_id = AtomicU64::new(1);
+//! let client = Client::builder(Hyper::new(base_url))
+//!     .user_agent(HeaderValue::from_static("inventory-sync/1.4"))
+//!     .default_header(HeaderName::from_static("x-api-version"), HeaderValue::from_static("2"))
+//!     .default_header_with(HeaderName::from_static("x-correlation-id"), move || {
+//!         HeaderValue::from(next_id.fetch_add(1, Ordering::Relaxed))
+//!     })
+//!     .build();
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http::{HeaderName, HeaderValue};
+
+use crate::ClientTransport;
+
+#[derive(Clone)]
+enum DefaultValue {
+    Fixed(HeaderValue),
+    Dynamic(Arc<dyn Fn() -> HeaderValue + Send + Sync>),
+}
+
+/// A [ClientTransport] that adds its default headers to every request that doesn't already
+/// have them. Build one with [Client::builder].
+#[derive(Clone)]
+pub struct Client<T> {
+    inner: T,
+    headers: Vec<(HeaderName, DefaultValue)>,
+}
+
+impl<T> Client<T> {
+    pub fn builder(inner: T) -> ClientBuilder<T> {
+        ClientBuilder {
+            client: Client {
+                inner,
+                headers: Vec::new(),
+            },
+        }
+    }
+}
+
+pub struct ClientBuilder<T> {
+    client: Client<T>,
+}
+
+impl<T> ClientBuilder<T> {
+    /// Send `value` in the `name` header. Setting the same header again replaces it.
+    pub fn default_header(self, name: HeaderName, value: HeaderValue) -> Self {
+        self.set(name, DefaultValue::Fixed(value))
+    }
+
+    /// Send the `name` header with a value made by `value` for each request.
+    pub fn default_header_with(
+        self,
+        name: HeaderName,
+        value: impl Fn() -> HeaderValue + Send + Sync + 'static,
+    ) -> Self {
+        self.set(name, DefaultValue::Dynamic(Arc::new(value)))
+    }
+
+    pub fn user_agent(self, value: HeaderValue) -> Self {
+        self.default_header(http::header::USER_AGENT, value)
+    }
+
+    fn set(mut self, name: HeaderName, value: DefaultValue) -> Self {
+        self.client
+            .headers
+            .retain(|(existing, _)| *existing != name);
+        self.client.headers.push((name, value));
+        self
+    }
+
+    pub fn build(self) -> Client<T> {
+        self.client
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for Client<T> {
+    type Response = T::Response;
+    type Error = T::Error;
+
+    async fn send(&self, mut request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let headers = request.headers_mut();
+        for (name, value) in &self.headers {
+            headers.entry(name).or_insert_with(|| match value {
+                DefaultValue::Fixed(value) => value.clone(),
+                DefaultValue::Dynamic(value) => value(),
+            });
+        }
+        self.inner.send(request).await
+    }
+}
@@ -0,0 +1,157 @@
+//! Time and randomness for the client's resilience layers, e.g. [CircuitBreaker] and [Chaos],
+//! so tests can control them instead of waiting in real time.
+//!
+//! ```
+//! # mod api {
+//! #     use typed_routing::{erased::ErasedRoute, Method, NoBody, NoQuery, Route, RouteCatalog};
+//! #     pub struct GetOrder;
+//! #     impl Route for GetOrder {
+//! #         type Query = NoQuery;
+//! #         type RequestBody = NoBody;
+//! #         type ResponseBody = NoBody;
+//! #         const METHOD: Method = Method::GET;
+//! #         const NAME: &'static str = "GetOrder";
+//! #         const URI_PART: &'static str = "/orders/{id}";
+//! #         const URI: &'static str = "/orders/{id}";
+//! #     }
+//! #     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+//! #     pub enum RouteId { GetOrder }
+//! #     impl std::fmt::Display for RouteId {
+//! #         fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { f.write_str(GetOrder::NAME) }
+//! #     }
+//! #     impl RouteCatalog for RouteId {
+//! #         const ALL: &'static [Self] = &[RouteId::GetOrder];
+//! #         fn name(self) -> &'static str { GetOrder::NAME }
+//! #         fn method(self) -> Method { GetOrder::METHOD }
+//! #         fn uri(self) -> &'static str { GetOrder::URI }
+//! #         fn required_scopes(self) -> &'static [&'static str] { &[] }
+//! #         fn audited(self) -> bool { false }
+//! #         fn feature_flag(self) -> Option<&'static str> { None }
+//! #         fn alias(self) -> Option<&'static str> { None }
+//! #         fn sensitive_fields(self) -> &'static [&'static str] { &[] }
+//! #         fn erased(self) -> &'static dyn ErasedRoute { &GetOrder }
+//! #     }
+//! # }
+//! # use std::time::Duration;
+//! # use typed_routing::{circuit::{CircuitBreaker, CircuitConfig}, clock::ManualClock};
+//! # fn transport<T>(replayer: T) {
+//! let clock = ManualClock::new();
+//! let transport = CircuitBreaker::<_, api::RouteId>::new(replayer)
+//!     .default_config(CircuitConfig::default())
+//!     .clock(clock.clone());
+//! // ... fail requests until the circuit opens
+//! clock.advance(Duration::from_secs(30));
+//! // ... the next request is a probe
+//! # }
+//! ```
+//!
+//! [ManualClock::sleep] can be given to the layers that take a `sleep` function, e.g.
+//! [Poller](crate::poll::Poller) or [ResilientStream](crate::sse::ResilientStream), so their
+//! waits complete at once and move the clock forward instead.
+//!
+//! [CircuitBreaker]: crate::circuit::CircuitBreaker
+//! [Chaos]: crate::chaos::Chaos
+
+use std::{
+    future::{ready, Ready},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use web_time::Instant;
+
+/// A source of the current time.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when it's told to. Cloning it shares the time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock {
+            start: Instant::now(),
+            elapsed: Arc::default(),
+        }
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    /// Move the clock forward by `duration`, and complete at once.
+    pub fn sleep(&self, duration: Duration) -> Ready<()> {
+        self.advance(duration);
+        ready(())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+}
+
+/// A source of random numbers.
+pub trait Rng {
+    fn next_u64(&mut self) -> u64;
+
+    /// A number from `0.0` up to, but not including, `1.0`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A SplitMix64 generator: fast and reproducible from its seed, but not cryptographically
+/// secure.
+#[derive(Debug, Clone, Default)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+}
+
+impl Rng for SeededRng {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Always gives the same number, e.g. to fix a jitter or fault roll in a test.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedRng(pub u64);
+
+impl Rng for FixedRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0
+    }
+}
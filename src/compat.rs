@@ -0,0 +1,98 @@
+//! Conversions between the `http` 1.x types in this crate's API, re-exported at its root, and
+//! the `http` 0.2 types that actix-web 4 still uses, e.g. in handlers and middleware.
+//!
+//! ```
+//! use typed_routing::compat::{FromActix, ToActix};
+//! # use actix_web::HttpResponse;
+//! # let response = HttpResponse::NotFound().finish();
+//!
+//! let status = typed_routing::StatusCode::from_actix(&response.status());
+//! let builder = HttpResponse::build(status.to_actix());
+//! # assert_eq!(status, typed_routing::StatusCode::NOT_FOUND);
+//! ```
+
+use crate::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+
+/// Converts a type from this crate's version of `http` into actix-web's.
+pub trait ToActix {
+    type Actix;
+
+    fn to_actix(&self) -> Self::Actix;
+}
+
+/// Converts a type from actix-web's version of `http` into this crate's.
+pub trait FromActix: Sized {
+    type Actix;
+
+    fn from_actix(actix: &Self::Actix) -> Self;
+}
+
+impl ToActix for Method {
+    type Actix = actix_web::http::Method;
+
+    fn to_actix(&self) -> Self::Actix {
+        actix_web::http::Method::from_bytes(self.as_str().as_bytes())
+            .expect("every method is valid in both versions")
+    }
+}
+
+impl FromActix for Method {
+    type Actix = actix_web::http::Method;
+
+    fn from_actix(actix: &Self::Actix) -> Self {
+        Method::from_bytes(actix.as_str().as_bytes())
+            .expect("every method is valid in both versions")
+    }
+}
+
+impl ToActix for StatusCode {
+    type Actix = actix_web::http::StatusCode;
+
+    fn to_actix(&self) -> Self::Actix {
+        actix_web::http::StatusCode::from_u16(self.as_u16())
+            .expect("every status code is valid in both versions")
+    }
+}
+
+impl FromActix for StatusCode {
+    type Actix = actix_web::http::StatusCode;
+
+    fn from_actix(actix: &Self::Actix) -> Self {
+        StatusCode::from_u16(actix.as_u16()).expect("every status code is valid in both versions")
+    }
+}
+
+impl ToActix for HeaderMap {
+    type Actix = actix_web::http::header::HeaderMap;
+
+    fn to_actix(&self) -> Self::Actix {
+        let mut headers = actix_web::http::header::HeaderMap::new();
+        for (name, value) in self {
+            headers.append(
+                actix_web::http::header::HeaderName::from_bytes(name.as_str().as_bytes())
+                    .expect("every header name is valid in both versions"),
+                actix_web::http::header::HeaderValue::from_bytes(value.as_bytes())
+                    .expect("every header value is valid in both versions"),
+            );
+        }
+        headers
+    }
+}
+
+impl FromActix for HeaderMap {
+    type Actix = actix_web::http::header::HeaderMap;
+
+    fn from_actix(actix: &Self::Actix) -> Self {
+        actix
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_bytes(name.as_str().as_bytes())
+                        .expect("every header name is valid in both versions"),
+                    HeaderValue::from_bytes(value.as_bytes())
+                        .expect("every header value is valid in both versions"),
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,69 @@
+//! CSV response bodies made up of typed records, (de)serialized with the [csv](::csv) crate.
+
+use std::marker::PhantomData;
+
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{BodyError, ContentType, IntoResponse, NoCheckResponse, Response, TransportResponse};
+
+/// The content type CSV bodies are sent with.
+pub const CONTENT_TYPE: &str = "text/csv";
+
+/// A type that indicates that the response guarantees that its body will be CSV with a
+/// header row, where each record successfully deserializes into a `T`.
+pub struct CsvBody<T>(PhantomData<T>);
+
+impl<T> ContentType for CsvBody<T> {
+    fn matches(media_type: &str) -> bool {
+        media_type == CONTENT_TYPE
+    }
+}
+
+impl<T: DeserializeOwned, Route: crate::Route<ResponseBody = CsvBody<T>>, R: TransportResponse>
+    Response<Route, R>
+{
+    pub async fn records(self) -> Result<Vec<T>, BodyError<::csv::Error, R::Error>> {
+        let body = self.body().await.map_err(BodyError::Transport)?;
+        ::csv::Reader::from_reader(&body[..])
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(BodyError::Format)
+    }
+}
+
+/// A responder that writes each item of `I` as a CSV record, preceded by a header row.
+pub struct Csv<I>(pub I);
+
+impl<I: IntoIterator> actix_web::Responder for Csv<I>
+where
+    I::Item: Serialize,
+{
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        match write_records(self.0) {
+            Ok(body) => HttpResponse::Ok().content_type(CONTENT_TYPE).body(body),
+            Err(err) => HttpResponse::from_error(actix_web::error::ErrorInternalServerError(err)),
+        }
+    }
+}
+
+fn write_records<I: IntoIterator>(records: I) -> Result<Vec<u8>, ::csv::Error>
+where
+    I::Item: Serialize,
+{
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer
+        .into_inner()
+        .map_err(|err| ::csv::Error::from(err.into_error()))
+}
+
+impl<T: Serialize, I: IntoIterator<Item = T>> IntoResponse<CsvBody<T>> for Csv<I> {}
+
+impl<R, T: IntoResponse<CsvBody<R>>, E> IntoResponse<CsvBody<R>> for Result<T, E> {}
+
+impl<R, T> IntoResponse<CsvBody<R>> for NoCheckResponse<T> {}
@@ -0,0 +1,197 @@
+//! Logging typed requests and their responses with [log], e.g. to the browser console with
+//! `console_log`, while debugging a client.
+//!
+//! The values of a route's [Route::SENSITIVE_FIELDS](crate::Route::SENSITIVE_FIELDS) are
+//! masked, see [redact](crate::redact).
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use bytes::Bytes;
+use serde_json::Value;
+
+use crate::{
+    redact::{is_form, redact_json, redact_pairs},
+    ClientTransport, RouteName, SensitiveFields, TransportResponse,
+};
+
+/// The log target everything is logged under, so it can be filtered separately.
+pub const LOG_TARGET: &str = "typed_routing";
+
+/// Bodies longer than this are cut short unless configured otherwise.
+const DEFAULT_MAX_BODY: usize = 2048;
+
+/// Turns a [DebugLog] transport's logging on and off at runtime, e.g. from a debug menu.
+///
+/// Clones share the same switch.
+#[derive(Debug, Clone)]
+pub struct DebugSwitch(Arc<AtomicBool>);
+
+impl DebugSwitch {
+    pub fn enable(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn disable(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A [ClientTransport] that logs each request's method, URI, query and body, and each
+/// response's status and body, at the debug level, before passing them on.
+pub struct DebugLog<T> {
+    inner: T,
+    switch: DebugSwitch,
+    max_body: usize,
+}
+
+impl<T> DebugLog<T> {
+    /// Log requests sent with `inner`, starting out enabled.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            switch: DebugSwitch(Arc::new(AtomicBool::new(true))),
+            max_body: DEFAULT_MAX_BODY,
+        }
+    }
+
+    /// Cut logged bodies short after `max_body` bytes.
+    pub fn max_body(mut self, max_body: usize) -> Self {
+        self.max_body = max_body;
+        self
+    }
+
+    /// The switch this transport's logging is controlled with.
+    pub fn switch(&self) -> DebugSwitch {
+        self.switch.clone()
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for DebugLog<T> {
+    type Response = DebugLogResponse<T::Response>;
+    type Error = T::Error;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let route = request
+            .extensions()
+            .get::<RouteName>()
+            .map_or("untyped", |name| name.0);
+        let sensitive = request
+            .extensions()
+            .get::<SensitiveFields>()
+            .map_or(&[][..], |fields| fields.0);
+
+        if self.switch.is_enabled() {
+            log::debug!(
+                target: LOG_TARGET,
+                "{route}: {} {}",
+                request.method(),
+                request.uri().path()
+            );
+            if let Some(query) = request.uri().query() {
+                log::debug!(
+                    target: LOG_TARGET,
+                    "{route}: query {:?}",
+                    redact_pairs(query, sensitive)
+                );
+            }
+            if !request.body().is_empty() {
+                log::debug!(
+                    target: LOG_TARGET,
+                    "{route}: request body {}",
+                    render_body(request.headers(), request.body(), sensitive, self.max_body)
+                );
+            }
+        }
+
+        let response = self.inner.send(request).await?;
+        if self.switch.is_enabled() {
+            log::debug!(target: LOG_TARGET, "{route}: {}", response.status());
+        }
+        Ok(DebugLogResponse {
+            inner: response,
+            route,
+            sensitive,
+            switch: self.switch.clone(),
+            max_body: self.max_body,
+        })
+    }
+}
+
+/// A response received by [DebugLog], which logs its status as soon as it's received and its
+/// body once it has been read.
+pub struct DebugLogResponse<R> {
+    inner: R,
+    route: &'static str,
+    sensitive: &'static [&'static str],
+    switch: DebugSwitch,
+    max_body: usize,
+}
+
+impl<R: TransportResponse> TransportResponse for DebugLogResponse<R> {
+    type Error = R::Error;
+
+    fn status(&self) -> http::StatusCode {
+        self.inner.status()
+    }
+
+    fn headers(&self) -> &http::HeaderMap {
+        self.inner.headers()
+    }
+
+    async fn bytes(self) -> Result<Bytes, Self::Error> {
+        let headers = self.inner.headers().clone();
+        let body = self.inner.bytes().await?;
+        if self.switch.is_enabled() {
+            log::debug!(
+                target: LOG_TARGET,
+                "{}: response body {}",
+                self.route,
+                render_body(&headers, &body, self.sensitive, self.max_body)
+            );
+        }
+        Ok(body)
+    }
+}
+
+/// A body as it's logged: JSON pretty-printed and forms as their pairs, both with sensitive
+/// values masked, and anything else as text.
+fn render_body(
+    headers: &http::HeaderMap,
+    body: &[u8],
+    sensitive: &[&str],
+    max_body: usize,
+) -> String {
+    let rendered = if let Ok(mut json) = serde_json::from_slice::<Value>(body) {
+        redact_json(&mut json, sensitive);
+        serde_json::to_string_pretty(&json).unwrap_or_default()
+    } else if is_form(headers) {
+        format!(
+            "{:?}",
+            redact_pairs(&String::from_utf8_lossy(body), sensitive)
+        )
+    } else {
+        String::from_utf8_lossy(body).into_owned()
+    };
+    truncate(rendered, max_body)
+}
+
+fn truncate(mut text: String, max: usize) -> String {
+    if text.len() <= max {
+        return text;
+    }
+    let len = text.len();
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(&format!("... ({len} bytes)"));
+    text
+}
@@ -0,0 +1,180 @@
+//! Integrity digests of request bodies, carried in `Content-Digest` or `Content-MD5` headers.
+
+use std::{future::Future, ops::Deref, pin::Pin};
+
+use actix_web::{error::ErrorBadRequest, web::Bytes, HttpRequest};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::JsonBody;
+
+/// The algorithm used to digest a request body, chosen with `RequestBuilder::content_digest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    /// A SHA-256 digest sent in a `Content-Digest` header.
+    Sha256,
+    /// An MD5 digest sent in a `Content-MD5` header.
+    Md5,
+}
+
+impl DigestAlgorithm {
+    /// The header that carries a digest of `body`.
+    pub(crate) fn header(self, body: &[u8]) -> (http::HeaderName, http::HeaderValue) {
+        let (name, value) = match self {
+            DigestAlgorithm::Sha256 => ("content-digest", sha256_field(body)),
+            DigestAlgorithm::Md5 => ("content-md5", STANDARD.encode(md5::compute(body).0)),
+        };
+        (
+            http::HeaderName::from_static(name),
+            http::HeaderValue::try_from(value).expect("base64 is a valid header value"),
+        )
+    }
+}
+
+fn sha256_field(body: &[u8]) -> String {
+    format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(body)))
+}
+
+/// Check `body` against every digest header on `req`, failing if there are none.
+fn verify(req: &HttpRequest, body: &[u8]) -> Result<(), actix_web::Error> {
+    let mut verified = false;
+
+    if let Some(value) = req.headers().get("Content-Digest") {
+        let value = value
+            .to_str()
+            .map_err(|_| ErrorBadRequest("Malformed Content-Digest header"))?;
+        let expected = sha256_field(body);
+        let sha256 = value
+            .split(',')
+            .map(str::trim)
+            .find(|field| field.starts_with("sha-256="));
+        match sha256 {
+            Some(field) if field == expected => verified = true,
+            Some(_) => return Err(ErrorBadRequest("Content-Digest doesn't match the body")),
+            None => {}
+        }
+    }
+
+    if let Some(value) = req.headers().get("Content-MD5") {
+        if value.as_bytes() != STANDARD.encode(md5::compute(body).0).as_bytes() {
+            return Err(ErrorBadRequest("Content-MD5 doesn't match the body"));
+        }
+        verified = true;
+    }
+
+    if verified {
+        Ok(())
+    } else {
+        Err(ErrorBadRequest("Missing a supported body digest"))
+    }
+}
+
+/// A JSON body extractor that verifies the request's `Content-Digest` or `Content-MD5` header
+/// against the raw body before deserializing it.
+pub struct VerifiedJson<T>(pub T);
+
+impl<T: DeserializeOwned + 'static> actix_web::FromRequest for VerifiedJson<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let req = req.clone();
+        let body = Bytes::from_request(&req, payload);
+        Box::pin(async move {
+            let body = body.await?;
+            verify(&req, &body)?;
+            serde_json::from_slice(&body)
+                .map(VerifiedJson)
+                .map_err(ErrorBadRequest)
+        })
+    }
+}
+
+impl<T: DeserializeOwned, Query> crate::FromRequest<Query, JsonBody<T>> for VerifiedJson<T> {}
+
+impl<T> Deref for VerifiedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::TestRequest, FromRequest as _};
+
+    use super::*;
+
+    const BODY: &[u8] = br#"{"id":1}"#;
+
+    async fn extract(request: TestRequest) -> Result<u32, actix_web::Error> {
+        let (req, mut payload) = request.to_http_parts();
+        let VerifiedJson(body) =
+            VerifiedJson::<serde_json::Value>::from_request(&req, &mut payload).await?;
+        Ok(body["id"].as_u64().unwrap() as u32)
+    }
+
+    /// The header carrying `algorithm`'s digest of `body`, as actix takes it.
+    fn header(algorithm: DigestAlgorithm, body: &[u8]) -> (String, String) {
+        let (name, value) = algorithm.header(body);
+        (name.to_string(), value.to_str().unwrap().to_owned())
+    }
+
+    fn signed(algorithm: DigestAlgorithm) -> TestRequest {
+        TestRequest::post()
+            .insert_header(header(algorithm, BODY))
+            .set_payload(BODY)
+    }
+
+    #[test]
+    fn digests_bodies() {
+        let (name, value) = DigestAlgorithm::Sha256.header(b"");
+        assert_eq!(name, "content-digest");
+        assert_eq!(
+            value,
+            "sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:"
+        );
+        let (name, value) = DigestAlgorithm::Md5.header(b"");
+        assert_eq!(name, "content-md5");
+        assert_eq!(value, "1B2M2Y8AsgTpgAmY7PhCfg==");
+    }
+
+    #[actix_web::test]
+    async fn accepts_bodies_matching_their_digest() {
+        assert_eq!(extract(signed(DigestAlgorithm::Sha256)).await.unwrap(), 1);
+        assert_eq!(extract(signed(DigestAlgorithm::Md5)).await.unwrap(), 1);
+        let request = TestRequest::post()
+            .insert_header((
+                "Content-Digest",
+                format!("sha-512=:abc=:, {}", sha256_field(BODY)),
+            ))
+            .set_payload(BODY);
+        assert_eq!(extract(request).await.unwrap(), 1);
+    }
+
+    #[actix_web::test]
+    async fn rejects_bodies_not_matching_their_digest() {
+        for algorithm in [DigestAlgorithm::Sha256, DigestAlgorithm::Md5] {
+            let request = signed(algorithm).set_payload(&br#"{"id":2}"#[..]);
+            assert!(extract(request).await.is_err(), "{algorithm:?}");
+        }
+        let both = TestRequest::post()
+            .insert_header(header(DigestAlgorithm::Sha256, BODY))
+            .insert_header(header(DigestAlgorithm::Md5, b"other"))
+            .set_payload(BODY);
+        assert!(extract(both).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn rejects_bodies_without_a_supported_digest() {
+        assert!(extract(TestRequest::post().set_payload(BODY))
+            .await
+            .is_err());
+        let request = TestRequest::post()
+            .insert_header(("Content-Digest", "sha-512=:abc=:"))
+            .set_payload(BODY);
+        assert!(extract(request).await.is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Hooks for fetching typed routes from [Dioxus](dioxus) components.
+
+use std::{error::Error, marker::PhantomData, rc::Rc};
+
+use dioxus::{
+    dioxus_core::prelude::{spawn, use_hook},
+    hooks::{use_resource, use_signal, Resource},
+    signals::{ReadOnlySignal, Signal, Writable},
+};
+
+use crate::{
+    join::{fetch_json, FetchError},
+    ApplyToRequestBody, ApplyToRequestHead, ClientTransport, JsonResponse, RequestBuilder, Route,
+    TransportResponse,
+};
+
+/// What a route's JSON response decodes into.
+pub type JsonOutput<R> = <<R as Route>::ResponseBody as JsonResponse>::Output;
+
+/// Fetch `R` with the request built by `request`, and fetch it again whenever a signal that
+/// `request` reads changes.
+///
+/// The resource's value is `None` until the first response has been decoded.
+pub fn use_typed_resource<R, T>(
+    transport: T,
+    request: impl Fn() -> RequestBuilder<R, R::Query, R::RequestBody> + 'static,
+) -> Resource<Result<JsonOutput<R>, FetchError>>
+where
+    R: Route + 'static,
+    R::ResponseBody: JsonResponse,
+    JsonOutput<R>: 'static,
+    <R::Query as ApplyToRequestHead>::Error: Error + 'static,
+    <R::RequestBody as ApplyToRequestBody>::Error: Error + 'static,
+    T: ClientTransport + Clone + 'static,
+    T::Error: Error + 'static,
+    <T::Response as TransportResponse>::Error: Error + 'static,
+{
+    use_resource(move || {
+        let request = request();
+        let transport = transport.clone();
+        async move { fetch_json(request, &transport).await }
+    })
+}
+
+/// The state of the last request sent with a [TypedRequest].
+#[derive(Debug)]
+pub enum RequestState<T> {
+    Idle,
+    Loading,
+    Done(Result<T, FetchError>),
+}
+
+/// A handle, from [use_typed_request], for sending requests to `R` when something happens,
+/// e.g. a form being submitted.
+pub struct TypedRequest<R: Route, T>
+where
+    R::ResponseBody: JsonResponse,
+    JsonOutput<R>: 'static,
+{
+    _marker: PhantomData<R>,
+    transport: Rc<T>,
+    state: Signal<RequestState<JsonOutput<R>>>,
+}
+
+impl<R: Route, T> Clone for TypedRequest<R, T>
+where
+    R::ResponseBody: JsonResponse,
+    JsonOutput<R>: 'static,
+{
+    fn clone(&self) -> Self {
+        TypedRequest {
+            _marker: PhantomData,
+            transport: Rc::clone(&self.transport),
+            state: self.state,
+        }
+    }
+}
+
+/// Create a [TypedRequest] for sending requests to `R` with `transport`.
+pub fn use_typed_request<R, T: 'static>(transport: T) -> TypedRequest<R, T>
+where
+    R: Route,
+    R::ResponseBody: JsonResponse,
+    JsonOutput<R>: 'static,
+{
+    let transport = use_hook(|| Rc::new(transport));
+    let state = use_signal(|| RequestState::Idle);
+    TypedRequest {
+        _marker: PhantomData,
+        transport,
+        state,
+    }
+}
+
+impl<R, T> TypedRequest<R, T>
+where
+    R: Route + 'static,
+    R::ResponseBody: JsonResponse,
+    JsonOutput<R>: 'static,
+    <R::Query as ApplyToRequestHead>::Error: Error + 'static,
+    <R::RequestBody as ApplyToRequestBody>::Error: Error + 'static,
+    T: ClientTransport + 'static,
+    T::Error: Error + 'static,
+    <T::Response as TransportResponse>::Error: Error + 'static,
+{
+    /// Send `request` in the background, updating [TypedRequest::state] as it goes.
+    pub fn send(&self, request: RequestBuilder<R, R::Query, R::RequestBody>) {
+        let transport = Rc::clone(&self.transport);
+        let mut state = self.state;
+        state.set(RequestState::Loading);
+        spawn(async move {
+            let result = fetch_json(request, &*transport).await;
+            state.set(RequestState::Done(result));
+        });
+    }
+}
+
+impl<R: Route, T> TypedRequest<R, T>
+where
+    R::ResponseBody: JsonResponse,
+    JsonOutput<R>: 'static,
+{
+    pub fn state(&self) -> ReadOnlySignal<RequestState<JsonOutput<R>>> {
+        self.state.into()
+    }
+
+    /// Forget the last request's result.
+    pub fn reset(&self) {
+        let mut state = self.state;
+        state.set(RequestState::Idle);
+    }
+}
@@ -0,0 +1,74 @@
+//! Response bodies wrapped in a `{ "data": ..., "meta": ... }` envelope, so that handlers and
+//! callers can work with the inner types.
+
+use std::marker::PhantomData;
+
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    BodyError, ContentType, IntoResponse, JsonBody, JsonResponse, NoCheckResponse, Response,
+    TransportResponse,
+};
+
+/// A type that indicates that the response guarantees that its body will be `Body` wrapped in
+/// an [Envelope] alongside metadata of type `Meta`, e.g. pagination details.
+pub struct Enveloped<Body, Meta>(PhantomData<(Body, Meta)>);
+
+impl<Body: ContentType, Meta> ContentType for Enveloped<Body, Meta> {
+    fn matches(media_type: &str) -> bool {
+        Body::matches(media_type)
+    }
+}
+
+/// An enveloped body, and the responder that sends one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope<T, Meta> {
+    pub data: T,
+    pub meta: Meta,
+}
+
+/// [Response::json] decodes the envelope's data, dropping its metadata.
+impl<T: DeserializeOwned, Meta: DeserializeOwned> JsonResponse for Enveloped<JsonBody<T>, Meta> {
+    type Output = T;
+
+    fn decode(body: &[u8]) -> Result<Self::Output, serde_json::Error> {
+        serde_json::from_slice::<Envelope<T, Meta>>(body).map(|envelope| envelope.data)
+    }
+}
+
+impl<
+        T: DeserializeOwned,
+        Meta: DeserializeOwned,
+        Route: crate::Route<ResponseBody = Enveloped<JsonBody<T>, Meta>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    /// Decode the body, keeping its metadata too.
+    pub async fn enveloped(
+        self,
+    ) -> Result<Envelope<T, Meta>, BodyError<serde_json::Error, R::Error>> {
+        let body = self.body().await.map_err(BodyError::Transport)?;
+        serde_json::from_slice(&body).map_err(BodyError::Format)
+    }
+}
+
+impl<T: Serialize, Meta: Serialize> actix_web::Responder for Envelope<T, Meta> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        HttpResponse::Ok().json(self)
+    }
+}
+
+impl<T: Serialize, Meta: Serialize> IntoResponse<Enveloped<JsonBody<T>, Meta>>
+    for Envelope<T, Meta>
+{
+}
+
+impl<R, Meta, T: IntoResponse<Enveloped<JsonBody<R>, Meta>>, E>
+    IntoResponse<Enveloped<JsonBody<R>, Meta>> for Result<T, E>
+{
+}
+
+impl<Body, Meta, T> IntoResponse<Enveloped<Body, Meta>> for NoCheckResponse<T> {}
@@ -0,0 +1,86 @@
+//! An object safe view of [Route]s, for code that works with a runtime list of routes, e.g.
+//! registries, docs endpoints or generic middleware, while requests stay statically typed.
+//!
+//! Every route is an [ErasedRoute], so `Box::new(routes::GetOrder)` is a
+//! `Box<dyn ErasedRoute>`, and [RouteCatalog::erased](crate::RouteCatalog::erased) gives one
+//! for each route in a catalog.
+
+use crate::Route;
+
+/// The parts of a [Route] that don't depend on its types.
+pub trait ErasedRoute {
+    fn name(&self) -> &'static str;
+    fn method(&self) -> http::Method;
+    fn uri(&self) -> &'static str;
+    fn media_type(&self) -> Option<&'static str>;
+    fn required_scopes(&self) -> &'static [&'static str];
+    fn feature_flag(&self) -> Option<&'static str>;
+    fn alias(&self) -> Option<&'static str>;
+    fn sensitive_fields(&self) -> &'static [&'static str];
+
+    /// The name of the route's query type, e.g. for documentation.
+    fn query_type(&self) -> &'static str;
+    fn request_body_type(&self) -> &'static str;
+    fn response_body_type(&self) -> &'static str;
+}
+
+impl<R: Route> ErasedRoute for R {
+    fn name(&self) -> &'static str {
+        R::NAME
+    }
+
+    fn method(&self) -> http::Method {
+        R::METHOD
+    }
+
+    fn uri(&self) -> &'static str {
+        R::URI
+    }
+
+    fn media_type(&self) -> Option<&'static str> {
+        R::MEDIA_TYPE
+    }
+
+    fn required_scopes(&self) -> &'static [&'static str] {
+        R::REQUIRED_SCOPES
+    }
+
+    fn feature_flag(&self) -> Option<&'static str> {
+        R::FEATURE_FLAG
+    }
+
+    fn alias(&self) -> Option<&'static str> {
+        R::ALIAS
+    }
+
+    fn sensitive_fields(&self) -> &'static [&'static str] {
+        R::SENSITIVE_FIELDS
+    }
+
+    fn query_type(&self) -> &'static str {
+        std::any::type_name::<R::Query>()
+    }
+
+    fn request_body_type(&self) -> &'static str {
+        std::any::type_name::<R::RequestBody>()
+    }
+
+    fn response_body_type(&self) -> &'static str {
+        std::any::type_name::<R::ResponseBody>()
+    }
+}
+
+/// An [ErasedRoute] whose schema fingerprint is known, for routes whose types implement
+/// [DescribeSchema](crate::schema::DescribeSchema).
+#[cfg(feature = "schema")]
+pub trait ErasedRouteSchema: ErasedRoute {
+    /// See [RouteSchema::fingerprint](crate::schema::RouteSchema::fingerprint).
+    fn fingerprint(&self) -> String;
+}
+
+#[cfg(feature = "schema")]
+impl<R: crate::schema::RouteSchema> ErasedRouteSchema for R {
+    fn fingerprint(&self) -> String {
+        R::fingerprint()
+    }
+}
@@ -0,0 +1,127 @@
+//! Example values for a route's query and bodies, declared once and used in its Postman
+//! collection and to serve the route before its handler is written.
+//!
+//! ```
+//! # use actix_web::App;
+//! # use serde::Serialize;
+//! # use typed_routing::{examples::{Examples, RouteExamples}, JsonBody, Method, NoBody, Query, Route, Router};
+//! # #[derive(Serialize)]
+//! # struct OrderQuery { expand: bool }
+//! # #[derive(Serialize)]
+//! # enum OrderStatus { Shipped }
+//! # #[derive(Serialize)]
+//! # struct Order { id: u64, status: OrderStatus }
+//! # struct GetOrder;
+//! # impl Route for GetOrder {
+//! #     type Query = Query<OrderQuery>;
+//! #     type RequestBody = NoBody;
+//! #     type ResponseBody = JsonBody<Order>;
+//! #     const METHOD: Method = Method::GET;
+//! #     const NAME: &'static str = "GetOrder";
+//! #     const URI_PART: &'static str = "/orders/{id}";
+//! #     const URI: &'static str = "/orders/{id}";
+//! # }
+//! impl RouteExamples for GetOrder {
+//!     fn examples() -> Examples {
+//!         Examples::new()
+//!             .query(&OrderQuery { expand: true })
+//!             .response_body(&Order { id: 7, status: OrderStatus::Shipped })
+//!     }
+//! }
+//!
+//! let app = App::new().example_handler::<GetOrder>();
+//! ```
+
+use http::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{compat::ToActix, JsonBody, NoBody, Route};
+
+/// Implemented by routes that have [Examples].
+pub trait RouteExamples: Route {
+    fn examples() -> Examples;
+}
+
+/// A route's example values, as JSON.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Examples {
+    pub query: Option<Value>,
+    pub request_body: Option<Value>,
+    pub response_body: Option<Value>,
+    /// The status the example response is sent with. Defaults to `200 OK`.
+    pub status: StatusCode,
+}
+
+impl Examples {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Panics
+    ///
+    /// If `query` can't be represented as JSON, e.g. a map with non-string keys.
+    pub fn query(mut self, query: &impl Serialize) -> Self {
+        self.query = Some(to_value(query));
+        self
+    }
+
+    /// # Panics
+    ///
+    /// If `body` can't be represented as JSON, e.g. a map with non-string keys.
+    pub fn request_body(mut self, body: &impl Serialize) -> Self {
+        self.request_body = Some(to_value(body));
+        self
+    }
+
+    /// # Panics
+    ///
+    /// If `body` can't be represented as JSON, e.g. a map with non-string keys.
+    pub fn response_body(mut self, body: &impl Serialize) -> Self {
+        self.response_body = Some(to_value(body));
+        self
+    }
+
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+fn to_value(example: &impl Serialize) -> Value {
+    serde_json::to_value(example).expect("examples should be representable as JSON")
+}
+
+/// Implemented by response body types that an example response can be sent as.
+pub trait ExampleResponse {
+    /// The response for `examples`, with `media_type` as its content type if it's given.
+    fn respond(examples: &Examples, media_type: Option<&str>) -> actix_web::HttpResponse;
+}
+
+impl ExampleResponse for NoBody {
+    fn respond(examples: &Examples, _media_type: Option<&str>) -> actix_web::HttpResponse {
+        actix_web::HttpResponse::new(examples.status.to_actix())
+    }
+}
+
+/// Routes without an example response body respond with `501 Not Implemented`.
+impl<T> ExampleResponse for JsonBody<T> {
+    fn respond(examples: &Examples, media_type: Option<&str>) -> actix_web::HttpResponse {
+        let Some(body) = &examples.response_body else {
+            return actix_web::HttpResponse::NotImplemented().finish();
+        };
+        actix_web::HttpResponse::build(examples.status.to_actix())
+            .content_type(media_type.unwrap_or("application/json"))
+            .body(body.to_string())
+    }
+}
+
+/// A route that responds to every request with `R`'s example response, ignoring the request's
+/// query and body.
+pub(crate) fn example_route<R>() -> actix_web::Route
+where
+    R: RouteExamples,
+    R::ResponseBody: ExampleResponse,
+{
+    actix_web::web::route().to(|| async { R::ResponseBody::respond(&R::examples(), R::MEDIA_TYPE) })
+}
@@ -0,0 +1,57 @@
+//! Decoding large JSON responses with [simd_json] on native targets, for routes whose payloads
+//! make serde_json the bottleneck.
+//!
+//! Servers send [FastJsonBody] responses as ordinary JSON, e.g. with [actix_web::web::Json].
+//! simd-json doesn't support WASM, so there responses are decoded with serde_json as usual.
+
+use std::error::Error;
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::{BodyError, ContentType, IntoResponse, NoCheckResponse, Response, TransportResponse};
+
+/// A type that indicates that the response's body will be JSON that deserializes into a `T`,
+/// like [JsonBody](crate::JsonBody), and should be decoded with [Response::fast_json].
+pub struct FastJsonBody<T>(T);
+
+impl<T> ContentType for FastJsonBody<T> {
+    fn matches(media_type: &str) -> bool {
+        media_type == "application/json"
+    }
+}
+
+impl<T: Serialize> IntoResponse<FastJsonBody<T>> for actix_web::web::Json<T> {}
+
+impl<R, T: IntoResponse<FastJsonBody<R>>, E> IntoResponse<FastJsonBody<R>> for Result<T, E> {}
+
+impl<R, T> IntoResponse<FastJsonBody<R>> for NoCheckResponse<T> {}
+
+/// The error returned when a [FastJsonBody] can't be decoded.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct FastJsonError(Box<dyn Error + Send + Sync>);
+
+impl<
+        T: DeserializeOwned,
+        Route: crate::Route<ResponseBody = FastJsonBody<T>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    pub async fn fast_json(self) -> Result<T, BodyError<FastJsonError, R::Error>> {
+        let body = self.body().await.map_err(BodyError::Transport)?;
+        decode(&body).map_err(BodyError::Format)
+    }
+}
+
+/// simd-json parses in place, so the body is copied into a buffer it can modify.
+#[cfg(not(target_arch = "wasm32"))]
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, FastJsonError> {
+    let mut buffer = body.to_vec();
+    simd_json::serde::from_slice(&mut buffer).map_err(|err| FastJsonError(Box::new(err)))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, FastJsonError> {
+    serde_json::from_slice(body).map_err(|err| FastJsonError(Box::new(err)))
+}
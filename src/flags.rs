@@ -0,0 +1,196 @@
+//! Feature flags and kill switches for routes marked with the `flag` option.
+//!
+//! Add [FeatureFlags] to the app's data to decide which flags are on, and register the [Flags]
+//! module so clients can request [Features] to hide what's switched off.
+
+use std::{
+    collections::BTreeMap,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    web::Data,
+    HttpResponse,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{JsonBody, Module, NoBody, NoQuery, Route, RouteCatalog, Router};
+
+/// What happens to the routes behind a flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagState {
+    Enabled,
+    /// The routes respond `404 Not Found`, as if they didn't exist, e.g. for unreleased features.
+    Hidden,
+    /// The routes respond `503 Service Unavailable`, e.g. for a feature that has been switched
+    /// off while it's broken.
+    Unavailable,
+}
+
+/// Decides the state of each feature flag, e.g. from configuration or a flag service.
+pub trait FlagProvider {
+    fn state(&self, flag: &str) -> FlagState;
+}
+
+impl<F: Fn(&str) -> FlagState> FlagProvider for F {
+    fn state(&self, flag: &str) -> FlagState {
+        self(flag)
+    }
+}
+
+/// App data holding the [FlagProvider] that flagged routes are checked against. Without it,
+/// every flag is on.
+#[derive(Clone)]
+pub struct FeatureFlags(Arc<dyn FlagProvider + Send + Sync>);
+
+impl FeatureFlags {
+    pub fn new(provider: impl FlagProvider + Send + Sync + 'static) -> Self {
+        FeatureFlags(Arc::new(provider))
+    }
+
+    pub fn state(&self, flag: &str) -> FlagState {
+        self.0.state(flag)
+    }
+}
+
+fn flag_state(req: &actix_web::HttpRequest, flag: &str) -> FlagState {
+    req.app_data::<FeatureFlags>()
+        .or_else(|| {
+            req.app_data::<Data<FeatureFlags>>()
+                .map(|data| data.get_ref())
+        })
+        .map_or(FlagState::Enabled, |flags| flags.state(flag))
+}
+
+/// Middleware on a flagged route's resource that turns requests away while its flag is off.
+pub(crate) struct FlagGuard(pub Option<&'static str>);
+
+impl<S, B: 'static> Transform<S, ServiceRequest> for FlagGuard
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = FlagGuardMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(FlagGuardMiddleware {
+            flag: self.0,
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub(crate) struct FlagGuardMiddleware<S> {
+    flag: Option<&'static str>,
+    service: Rc<S>,
+}
+
+impl<S, B: 'static> Service<ServiceRequest> for FlagGuardMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let state = match self.flag {
+            Some(flag) => flag_state(req.request(), flag),
+            None => FlagState::Enabled,
+        };
+        let response = match state {
+            FlagState::Enabled => {
+                let service = Rc::clone(&self.service);
+                return Box::pin(async move {
+                    service
+                        .call(req)
+                        .await
+                        .map(ServiceResponse::map_into_left_body)
+                });
+            }
+            FlagState::Hidden => HttpResponse::NotFound().finish(),
+            FlagState::Unavailable => HttpResponse::ServiceUnavailable().finish(),
+        };
+        Box::pin(ready(Ok(req.into_response(response).map_into_right_body())))
+    }
+}
+
+/// `GET /features`, which reports whether each flag guarding a route is on.
+pub struct Features;
+
+impl Route for Features {
+    type Query = NoQuery;
+    type RequestBody = NoBody;
+    type ResponseBody = JsonBody<FeatureStates>;
+    const METHOD: http::Method = http::Method::GET;
+    const NAME: &'static str = "Features";
+    const URI_PART: &'static str = "/features";
+    const URI: &'static str = "/features";
+}
+
+/// The response body of [Features]: whether each flag is on, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeatureStates(pub BTreeMap<String, bool>);
+
+impl FeatureStates {
+    /// Whether `flag` is on. Flags the server didn't report are assumed to be.
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.0.get(flag).copied().unwrap_or(true)
+    }
+
+    /// Whether the server is serving `R`, e.g. to decide whether to show a link to it.
+    pub fn allows<R: Route>(&self) -> bool {
+        R::FEATURE_FLAG.is_none_or(|flag| self.is_enabled(flag))
+    }
+}
+
+/// A module that serves [Features] for the flags used by the catalogs added to it.
+#[derive(Debug, Clone, Default)]
+pub struct Flags {
+    flags: Vec<&'static str>,
+}
+
+impl Flags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the flags used by the routes in `Catalog`.
+    pub fn catalog<Catalog: RouteCatalog>(mut self) -> Self {
+        self.flags
+            .extend(Catalog::ALL.iter().filter_map(|id| id.feature_flag()));
+        self.flags.sort_unstable();
+        self.flags.dedup();
+        self
+    }
+}
+
+impl Module for Flags {
+    fn register<R: Router>(self, router: R) -> R {
+        let flags = Rc::new(self.flags);
+        router.typed_route::<Features, _>(|| {
+            let flags = Rc::clone(&flags);
+            actix_web::web::route().to(move |req: actix_web::HttpRequest| {
+                let states = flags
+                    .iter()
+                    .map(|flag| {
+                        let enabled = flag_state(&req, flag) == FlagState::Enabled;
+                        (flag.to_string(), enabled)
+                    })
+                    .collect();
+                async move { actix_web::web::Json(FeatureStates(states)) }
+            })
+        })
+    }
+}
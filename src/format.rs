@@ -0,0 +1,270 @@
+//! Body formats defined by a single [BodyFormat] impl, which gets them a request and response
+//! body marker, [FormatBody], and a server extractor and responder, [Formatted].
+//!
+//! The YAML, XML and bincode features are built this way, and other formats can be added
+//! outside the crate the same way.
+//!
+//! Query strings work the same way with [QueryFormat], chosen with the second parameter of
+//! [Query](crate::Query), and extracted on the server with [FormattedQuery].
+//!
+//! ```ignore
+//! pub struct Cbor;
+//!
+//! impl BodyFormat for Cbor {
+//!     const CONTENT_TYPE: &'static str = "application/cbor";
+//!     type Error = CborError;
+//!     // ...
+//! }
+//!
+//! routes! {
+//!     // ...
+//!     route(Method::POST, "/readings" => type AddReading (query: NoQuery, body: FormatBody<Cbor, Reading>) -> NoBody)
+//! }
+//! ```
+
+use std::{future::Future, marker::PhantomData, ops::Deref, pin::Pin};
+
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorUnsupportedMediaType},
+    web::Bytes,
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    ApplyToRequestBody, BodyError, ContentType, FromRequest, IntoBody, IntoResponse, NoBody,
+    NoCheckResponse, Query, RequestBuilder, RequestHead, Response, TransportResponse,
+};
+
+/// A serde format that bodies can be sent in.
+pub trait BodyFormat: 'static {
+    /// The content type bodies are sent with.
+    const CONTENT_TYPE: &'static str;
+
+    type Error: std::error::Error + 'static;
+
+    /// Whether a body with the content type `media_type` is in this format. Override this to
+    /// accept alternative content types.
+    fn matches(media_type: &str) -> bool {
+        media_type == Self::CONTENT_TYPE
+    }
+
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Self::Error>;
+
+    fn deserialize<T: DeserializeOwned>(body: &[u8]) -> Result<T, Self::Error>;
+}
+
+/// A type that indicates that the request or response guarantees that its body will be in the
+/// format `F`, and successfully deserializes into a `T`.
+pub struct FormatBody<F, T>(T, PhantomData<fn() -> F>);
+
+impl<F: BodyFormat, T> ContentType for FormatBody<F, T> {
+    fn matches(media_type: &str) -> bool {
+        F::matches(media_type)
+    }
+}
+
+impl<F, T, Route: crate::Route<RequestBody = FormatBody<F, T>>, Query>
+    RequestBuilder<Route, Query, NoBody>
+{
+    pub fn body(self, body: T) -> RequestBuilder<Route, Query, FormatBody<F, T>> {
+        self.with_body(FormatBody(body, PhantomData))
+    }
+}
+
+impl<F, T> IntoBody<FormatBody<F, T>> for T {
+    fn into_body(self) -> FormatBody<F, T> {
+        FormatBody(self, PhantomData)
+    }
+}
+
+impl<F: BodyFormat, T: Serialize> ApplyToRequestBody for FormatBody<F, T> {
+    type Error = F::Error;
+
+    fn apply(self, head: &mut RequestHead) -> Result<Bytes, Self::Error> {
+        let body = F::serialize(&self.0)?;
+        head.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static(F::CONTENT_TYPE),
+        );
+        Ok(body.into())
+    }
+}
+
+impl<
+        F: BodyFormat,
+        T: DeserializeOwned,
+        Route: crate::Route<ResponseBody = FormatBody<F, T>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    /// Read the body and deserialize it with the route's format.
+    pub async fn decode(self) -> Result<T, BodyError<F::Error, R::Error>> {
+        let body = self.body().await.map_err(BodyError::Transport)?;
+        F::deserialize(&body).map_err(BodyError::Format)
+    }
+}
+
+/// Extract a body in the format `F` from a request, rejecting other content types.
+pub(crate) fn extract<F: BodyFormat, T: DeserializeOwned + 'static>(
+    req: &HttpRequest,
+    payload: &mut actix_web::dev::Payload,
+) -> Pin<Box<dyn Future<Output = Result<T, actix_web::Error>>>> {
+    let content_type_matches = F::matches(req.content_type());
+    let body = <Bytes as actix_web::FromRequest>::from_request(req, payload);
+    Box::pin(async move {
+        if !content_type_matches {
+            return Err(ErrorUnsupportedMediaType(format!(
+                "Expected a {} body",
+                F::CONTENT_TYPE
+            )));
+        }
+        let body = body.await?;
+        F::deserialize(&body).map_err(ErrorBadRequest)
+    })
+}
+
+/// Respond with `value` in the format `F`.
+pub(crate) fn respond<F: BodyFormat, T: Serialize>(value: &T) -> HttpResponse {
+    match F::serialize(value) {
+        Ok(body) => HttpResponse::Ok().content_type(F::CONTENT_TYPE).body(body),
+        Err(err) => HttpResponse::from_error(ErrorInternalServerError(err)),
+    }
+}
+
+/// An extractor and responder for bodies in the format `F`, the counterpart of [FormatBody].
+pub struct Formatted<F, T>(pub T, pub PhantomData<fn() -> F>);
+
+impl<F, T> Formatted<F, T> {
+    pub fn new(value: T) -> Self {
+        Formatted(value, PhantomData)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<F: BodyFormat, T: DeserializeOwned + 'static> actix_web::FromRequest for Formatted<F, T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let body = extract::<F, T>(req, payload);
+        Box::pin(async move { body.await.map(Formatted::new) })
+    }
+}
+
+impl<F: BodyFormat, T: Serialize> actix_web::Responder for Formatted<F, T> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        respond::<F, T>(&self.0)
+    }
+}
+
+impl<F: BodyFormat, T: DeserializeOwned, Query> FromRequest<Query, FormatBody<F, T>>
+    for Formatted<F, T>
+{
+}
+
+impl<F: BodyFormat, T: Serialize> IntoResponse<FormatBody<F, T>> for Formatted<F, T> {}
+
+impl<F, R, T: IntoResponse<FormatBody<F, R>>, E> IntoResponse<FormatBody<F, R>> for Result<T, E> {}
+
+impl<F, R, T> IntoResponse<FormatBody<F, R>> for NoCheckResponse<T> {}
+
+impl<F, T> Deref for Formatted<F, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A serde format that query strings can be encoded in.
+pub trait QueryFormat: 'static {
+    /// The error when serializing a query.
+    type Error: std::error::Error + 'static;
+
+    /// The error when deserializing a query.
+    type DecodeError: std::error::Error + 'static;
+
+    fn serialize<T: Serialize>(value: &T) -> Result<String, Self::Error>;
+
+    fn deserialize<T: DeserializeOwned>(query: &str) -> Result<T, Self::DecodeError>;
+}
+
+/// The default [QueryFormat], using [serde_urlencoded]. It only supports flat structs, so
+/// nested values and sequences need another format, like [Qs].
+pub struct UrlEncoded;
+
+impl QueryFormat for UrlEncoded {
+    type Error = serde_urlencoded::ser::Error;
+
+    type DecodeError = serde_urlencoded::de::Error;
+
+    fn serialize<T: Serialize>(value: &T) -> Result<String, Self::Error> {
+        serde_urlencoded::to_string(value)
+    }
+
+    fn deserialize<T: DeserializeOwned>(query: &str) -> Result<T, Self::DecodeError> {
+        serde_urlencoded::from_str(query)
+    }
+}
+
+/// A [QueryFormat] using [serde_qs], which supports nested values and sequences in the
+/// bracketed style, e.g. `filter[status]=open&ids[0]=1&ids[1]=2`.
+#[cfg(feature = "serde_qs")]
+pub struct Qs;
+
+#[cfg(feature = "serde_qs")]
+impl QueryFormat for Qs {
+    type Error = serde_qs::Error;
+
+    type DecodeError = serde_qs::Error;
+
+    fn serialize<T: Serialize>(value: &T) -> Result<String, Self::Error> {
+        serde_qs::to_string(value)
+    }
+
+    fn deserialize<T: DeserializeOwned>(query: &str) -> Result<T, Self::DecodeError> {
+        serde_qs::from_str(query)
+    }
+}
+
+/// An extractor for query strings in the format `F`, the counterpart of [Query] for formats
+/// other than [UrlEncoded], which can use [actix_web::web::Query].
+pub struct FormattedQuery<F, T>(pub T, pub PhantomData<fn() -> F>);
+
+impl<F, T> FormattedQuery<F, T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<F: QueryFormat, T: DeserializeOwned> actix_web::FromRequest for FormattedQuery<F, T> {
+    type Error = actix_web::Error;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        std::future::ready(
+            F::deserialize(req.query_string())
+                .map(|query| FormattedQuery(query, PhantomData))
+                .map_err(ErrorBadRequest),
+        )
+    }
+}
+
+impl<F: QueryFormat, T: DeserializeOwned, Body> FromRequest<Query<T, F>, Body>
+    for FormattedQuery<F, T>
+{
+}
+
+impl<F, T> Deref for FormattedQuery<F, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
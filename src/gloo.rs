@@ -0,0 +1,136 @@
+//! A [ClientTransport] for browsers, built on [gloo_net]'s `fetch` bindings.
+
+use bytes::Bytes;
+use futures_util::Stream;
+use wasm_bindgen::JsCast;
+
+use crate::{ClientTransport, TransportResponse};
+
+/// Sends requests with the browser's `fetch`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gloo;
+
+impl ClientTransport for Gloo {
+    type Response = GlooResponse;
+    type Error = gloo_net::Error;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let (parts, body) = request.into_parts();
+        let method = gloo_net::http::Method::from_bytes(parts.method.as_str().as_bytes())
+            .map_err(|err| gloo_net::Error::GlooError(err.to_string()))?;
+        // `RequestBuilder::header` replaces earlier values, so repeated headers are appended
+        // to a `Headers` of their own.
+        let headers = gloo_net::http::Headers::new();
+        for (name, value) in &parts.headers {
+            let value = value
+                .to_str()
+                .map_err(|err| gloo_net::Error::GlooError(err.to_string()))?;
+            headers.append(name.as_str(), value);
+        }
+        let builder = gloo_net::http::RequestBuilder::new(&parts.uri.to_string())
+            .method(method)
+            .headers(headers);
+        let request = if body.is_empty() {
+            builder.build()?
+        } else {
+            builder.body(js_sys::Uint8Array::from(&body[..]))?
+        };
+
+        let response = request.send().await?;
+        let headers = response
+            .headers()
+            .entries()
+            .filter_map(|(name, value)| {
+                Some((
+                    http::HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    http::HeaderValue::try_from(value).ok()?,
+                ))
+            })
+            .collect();
+        Ok(GlooResponse { response, headers })
+    }
+}
+
+/// A response received by [Gloo].
+pub struct GlooResponse {
+    response: gloo_net::http::Response,
+    headers: http::HeaderMap,
+}
+
+impl GlooResponse {
+    pub fn into_inner(self) -> gloo_net::http::Response {
+        self.response
+    }
+}
+
+impl TransportResponse for GlooResponse {
+    type Error = gloo_net::Error;
+
+    fn status(&self) -> http::StatusCode {
+        http::StatusCode::from_u16(self.response.status())
+            .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn headers(&self) -> &http::HeaderMap {
+        &self.headers
+    }
+
+    async fn bytes(self) -> Result<Bytes, Self::Error> {
+        self.response.binary().await.map(Bytes::from)
+    }
+
+    /// The body as the browser receives it, read from the response's `ReadableStream`.
+    fn chunks(self) -> impl Stream<Item = Result<Bytes, Self::Error>> {
+        let reader = self.response.body().map(|body| {
+            BodyReader(
+                body.get_reader()
+                    .unchecked_into::<web_sys::ReadableStreamDefaultReader>(),
+            )
+        });
+        futures_util::stream::unfold(reader, |reader| async move {
+            let reader = reader?;
+            match reader.read().await {
+                Ok(Some(chunk)) => Some((Ok(chunk), Some(reader))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+}
+
+/// A reader of a response body that cancels the rest of it when dropped, e.g. once a
+/// [ResponseLimit](crate::limit::ResponseLimit) has seen too much, so the browser stops
+/// receiving it.
+struct BodyReader(web_sys::ReadableStreamDefaultReader);
+
+impl BodyReader {
+    /// The next chunk of the body, or `None` once it has all been read.
+    async fn read(&self) -> Result<Option<Bytes>, gloo_net::Error> {
+        let result = wasm_bindgen_futures::JsFuture::from(self.0.read())
+            .await
+            .map_err(js_error)?;
+        let done = js_sys::Reflect::get(&result, &"done".into()).map_err(js_error)?;
+        if done.is_truthy() {
+            return Ok(None);
+        }
+        let value = js_sys::Reflect::get(&result, &"value".into()).map_err(js_error)?;
+        Ok(Some(Bytes::from(
+            value.unchecked_into::<js_sys::Uint8Array>().to_vec(),
+        )))
+    }
+}
+
+impl Drop for BodyReader {
+    fn drop(&mut self) {
+        // Cancelling a stream that has been read to the end does nothing.
+        let _ = self.0.cancel();
+    }
+}
+
+fn js_error(value: wasm_bindgen::JsValue) -> gloo_net::Error {
+    let message = match value.dyn_ref::<js_sys::Error>() {
+        Some(error) => error.message().into(),
+        None => format!("{value:?}"),
+    };
+    gloo_net::Error::GlooError(message)
+}
@@ -0,0 +1,155 @@
+//! Liveness and readiness routes, for load balancers, orchestrators and smoke tests.
+//!
+//! Register the [Health] module on the server, and request [Healthz] or [Readyz] with a
+//! [RequestBuilder](crate::RequestBuilder) to check a deployment with the same types.
+
+use std::{collections::BTreeMap, future::Future, pin::Pin, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{JsonBody, Module, NoBody, NoQuery, Route, Router};
+
+/// `GET /healthz`, which reports whether the process is alive.
+pub struct Healthz;
+
+impl Route for Healthz {
+    type Query = NoQuery;
+    type RequestBody = NoBody;
+    type ResponseBody = JsonBody<HealthReport>;
+    const METHOD: http::Method = http::Method::GET;
+    const NAME: &'static str = "Healthz";
+    const URI_PART: &'static str = "/healthz";
+    const URI: &'static str = "/healthz";
+}
+
+/// `GET /readyz`, which reports whether the server is ready to handle requests, e.g. whether
+/// it can reach its database.
+pub struct Readyz;
+
+impl Route for Readyz {
+    type Query = NoQuery;
+    type RequestBody = NoBody;
+    type ResponseBody = JsonBody<HealthReport>;
+    const METHOD: http::Method = http::Method::GET;
+    const NAME: &'static str = "Readyz";
+    const URI_PART: &'static str = "/readyz";
+    const URI: &'static str = "/readyz";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Pass,
+    Fail,
+}
+
+/// The outcome of one check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub status: HealthStatus,
+    /// Why the check failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// The response body of [Healthz] and [Readyz]. It is sent with `503 Service Unavailable` if
+/// any check failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    /// The result of each check, by name.
+    pub checks: BTreeMap<String, CheckResult>,
+}
+
+type Check = Rc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), String>>>>>;
+
+/// A module that serves [Healthz] and [Readyz], running the checks added to it on each
+/// request.
+#[derive(Clone, Default)]
+pub struct Health {
+    liveness: Vec<(String, Check)>,
+    readiness: Vec<(String, Check)>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a check to [Healthz]. Failing it should mean the process needs restarting.
+    pub fn liveness_check<F, Fut>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<(), String>> + 'static,
+    {
+        self.liveness.push((name.to_owned(), boxed(check)));
+        self
+    }
+
+    /// Add a check to [Readyz]. Failing it should mean traffic is sent elsewhere for now.
+    pub fn readiness_check<F, Fut>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<(), String>> + 'static,
+    {
+        self.readiness.push((name.to_owned(), boxed(check)));
+        self
+    }
+}
+
+fn boxed<F, Fut>(check: F) -> Check
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), String>> + 'static,
+{
+    Rc::new(move || Box::pin(check()))
+}
+
+async fn run(checks: &[(String, Check)]) -> HealthReport {
+    let mut status = HealthStatus::Pass;
+    let mut results = BTreeMap::new();
+    for (name, check) in checks {
+        let result = match check().await {
+            Ok(()) => CheckResult {
+                status: HealthStatus::Pass,
+                message: None,
+            },
+            Err(message) => {
+                status = HealthStatus::Fail;
+                CheckResult {
+                    status: HealthStatus::Fail,
+                    message: Some(message),
+                }
+            }
+        };
+        results.insert(name.clone(), result);
+    }
+    HealthReport {
+        status,
+        checks: results,
+    }
+}
+
+fn handler(checks: Rc<Vec<(String, Check)>>) -> actix_web::Route {
+    actix_web::web::route().to(move || {
+        let checks = Rc::clone(&checks);
+        async move {
+            let report = run(&checks).await;
+            let mut response = match report.status {
+                HealthStatus::Pass => actix_web::HttpResponse::Ok(),
+                HealthStatus::Fail => actix_web::HttpResponse::ServiceUnavailable(),
+            };
+            response.json(report)
+        }
+    })
+}
+
+impl Module for Health {
+    fn register<R: Router>(self, router: R) -> R {
+        let liveness = Rc::new(self.liveness);
+        let readiness = Rc::new(self.readiness);
+        router
+            .typed_route_with_head::<Healthz, _>(|| handler(Rc::clone(&liveness)))
+            .typed_route_with_head::<Readyz, _>(|| handler(Rc::clone(&readiness)))
+    }
+}
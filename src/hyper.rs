@@ -0,0 +1,278 @@
+//! A [ClientTransport] for native callers, built on [hyper_util]'s pooling client, and one for
+//! servers listening on a unix socket.
+
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use http_body_util::{BodyExt, BodyStream, Full};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+};
+use thiserror::Error;
+
+use crate::{ClientTransport, TransportResponse};
+
+/// Sends requests to a single server over a pool of HTTP/1 connections.
+///
+/// Cloning a `Hyper` is cheap and shares its connection pool.
+#[derive(Debug, Clone)]
+pub struct Hyper {
+    client: Client<HttpConnector, Full<Bytes>>,
+    base: String,
+}
+
+impl Hyper {
+    /// A transport that sends requests to `base`, e.g. `http://orders.internal:8080`, with the
+    /// default pool configuration.
+    pub fn new(base: http::Uri) -> Self {
+        Self::builder(base).build()
+    }
+
+    pub fn builder(base: http::Uri) -> HyperBuilder {
+        HyperBuilder {
+            base,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            connect_timeout: None,
+            http2_prior_knowledge: false,
+            http2_initial_stream_window_size: None,
+            http2_keep_alive_interval: None,
+        }
+    }
+}
+
+/// Configures the connection pool of a [Hyper] transport.
+pub struct HyperBuilder {
+    base: http::Uri,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    connect_timeout: Option<Duration>,
+    http2_prior_knowledge: bool,
+    http2_initial_stream_window_size: Option<u32>,
+    http2_keep_alive_interval: Option<Duration>,
+}
+
+impl HyperBuilder {
+    /// Close connections that have been idle for longer than `timeout`.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Keep at most `max` idle connections open to the server.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Give up on connecting to the server after `timeout`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Speak HTTP/2 to the server straight away, without upgrading from HTTP/1, e.g. for
+    /// traffic inside a service mesh.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2_prior_knowledge = true;
+        self
+    }
+
+    /// The HTTP/2 flow control window of each stream, in bytes.
+    pub fn http2_initial_stream_window_size(mut self, size: u32) -> Self {
+        self.http2_initial_stream_window_size = Some(size);
+        self
+    }
+
+    /// Ping HTTP/2 connections every `interval` to keep them from being dropped while idle.
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    pub fn build(self) -> Hyper {
+        let mut connector = HttpConnector::new();
+        connector.set_connect_timeout(self.connect_timeout);
+
+        let mut builder = Client::builder(TokioExecutor::new());
+        builder.pool_timer(TokioTimer::new());
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max);
+        }
+        builder.http2_only(self.http2_prior_knowledge);
+        builder.timer(TokioTimer::new());
+        if let Some(size) = self.http2_initial_stream_window_size {
+            builder.http2_initial_stream_window_size(size);
+        }
+        if let Some(interval) = self.http2_keep_alive_interval {
+            builder.http2_keep_alive_interval(interval);
+        }
+
+        Hyper {
+            client: builder.build(connector),
+            base: self.base.to_string().trim_end_matches('/').to_owned(),
+        }
+    }
+}
+
+/// The error returned when a [Hyper] transport fails to send a request.
+#[derive(Debug, Error)]
+pub enum HyperError {
+    #[error("Failed to join the request's URI onto the base URI")]
+    Uri(#[source] http::uri::InvalidUri),
+    #[error("Failed to send the request")]
+    Send(#[source] hyper_util::client::legacy::Error),
+    /// The request failed at the HTTP/2 layer, e.g. its stream was reset by the server.
+    #[error("HTTP/2 error while sending the request")]
+    Http2(#[source] hyper_util::client::legacy::Error),
+}
+
+impl HyperError {
+    /// Why the server or connection ended the request's HTTP/2 stream, for [HyperError::Http2].
+    pub fn http2_reason(&self) -> Option<h2::Reason> {
+        match self {
+            HyperError::Http2(err) => h2_error(err)?.reason(),
+            _ => None,
+        }
+    }
+
+    fn from_send(err: hyper_util::client::legacy::Error) -> Self {
+        if h2_error(&err).is_some() {
+            HyperError::Http2(err)
+        } else {
+            HyperError::Send(err)
+        }
+    }
+}
+
+fn h2_error<'a>(err: &'a (dyn std::error::Error + 'static)) -> Option<&'a h2::Error> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<h2::Error>() {
+            return Some(err);
+        }
+        source = err.source();
+    }
+    None
+}
+
+impl ClientTransport for Hyper {
+    type Response = HyperResponse;
+    type Error = HyperError;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let (mut parts, body) = request.into_parts();
+        // Absolute URIs, e.g. webhook destinations, are sent as they are.
+        if parts.uri.scheme().is_none() {
+            parts.uri = format!("{}{}", self.base, parts.uri)
+                .parse()
+                .map_err(HyperError::Uri)?;
+        }
+        let request = http::Request::from_parts(parts, Full::new(body));
+
+        self.client
+            .request(request)
+            .await
+            .map(HyperResponse)
+            .map_err(HyperError::from_send)
+    }
+}
+
+/// A response received by [Hyper].
+pub struct HyperResponse(http::Response<hyper::body::Incoming>);
+
+impl HyperResponse {
+    pub fn into_inner(self) -> http::Response<hyper::body::Incoming> {
+        self.0
+    }
+}
+
+impl TransportResponse for HyperResponse {
+    type Error = hyper::Error;
+
+    fn status(&self) -> http::StatusCode {
+        self.0.status()
+    }
+
+    fn headers(&self) -> &http::HeaderMap {
+        self.0.headers()
+    }
+
+    async fn bytes(self) -> Result<Bytes, Self::Error> {
+        Ok(self.0.into_body().collect().await?.to_bytes())
+    }
+
+    fn chunks(self) -> impl futures_util::Stream<Item = Result<Bytes, Self::Error>> {
+        BodyStream::new(self.0.into_body())
+            .try_filter_map(|frame| futures_util::future::ready(Ok(frame.into_data().ok())))
+    }
+}
+
+/// Sends requests over a unix domain socket, e.g. to a sidecar, or to a test server bound with
+/// `HttpServer::bind_uds` so tests don't need a free port.
+///
+/// Each request gets a connection of its own.
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct Unix {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Unix {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Unix { path: path.into() }
+    }
+}
+
+/// The error returned when a [Unix] transport fails to send a request.
+#[cfg(unix)]
+#[derive(Debug, Error)]
+pub enum UnixError {
+    #[error("Failed to connect to the socket")]
+    Connect(#[source] std::io::Error),
+    #[error("Failed to send the request")]
+    Send(#[source] hyper::Error),
+}
+
+#[cfg(unix)]
+impl ClientTransport for Unix {
+    type Response = HyperResponse;
+    type Error = UnixError;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let stream = tokio::net::UnixStream::connect(&self.path)
+            .await
+            .map_err(UnixError::Connect)?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(UnixError::Send)?;
+        tokio::spawn(connection);
+
+        let (mut parts, body) = request.into_parts();
+        if let Some(path_and_query) = parts.uri.path_and_query() {
+            parts.uri = path_and_query
+                .as_str()
+                .parse()
+                .expect("a URI's path is a valid URI");
+        }
+        parts
+            .headers
+            .entry(http::header::HOST)
+            .or_insert(http::HeaderValue::from_static("localhost"));
+        let request = http::Request::from_parts(parts, Full::new(body));
+
+        sender
+            .send_request(request)
+            .await
+            .map(HyperResponse)
+            .map_err(UnixError::Send)
+    }
+}
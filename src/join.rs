@@ -0,0 +1,132 @@
+//! Sending several typed requests at once and decoding their JSON responses, e.g. to load
+//! everything a dashboard shows with [join_routes](crate::join_routes).
+
+use std::error::Error;
+
+use thiserror::Error;
+
+use crate::{
+    maintenance::Maintenance, ApplyToRequestBody, ApplyToRequestHead, ClientTransport,
+    JsonResponse, RequestBuilder, Route, TransportResponse,
+};
+
+/// Why one of the requests sent by [fetch_json] failed.
+#[derive(Debug, Error)]
+pub enum FetchErrorKind {
+    #[error("Failed to build the request")]
+    Build(#[source] Box<dyn Error>),
+    #[error("Failed to send the request")]
+    Transport(#[source] Box<dyn Error>),
+    #[error(transparent)]
+    Maintenance(Maintenance),
+    #[error("The request failed with status {0}")]
+    Status(http::StatusCode),
+    #[error("Failed to read the response")]
+    Body(#[source] Box<dyn Error>),
+}
+
+/// The error returned by [fetch_json], naming the route whose request failed.
+#[derive(Debug, Error)]
+#[error("The request to {route} failed")]
+pub struct FetchError {
+    pub route: &'static str,
+    #[source]
+    pub kind: FetchErrorKind,
+}
+
+/// Build and send `request` with `transport`, and decode its JSON response if it succeeded.
+pub async fn fetch_json<R, T>(
+    request: RequestBuilder<R, R::Query, R::RequestBody>,
+    transport: &T,
+) -> Result<<R::ResponseBody as JsonResponse>::Output, FetchError>
+where
+    R: Route,
+    R::ResponseBody: JsonResponse,
+    <R::Query as ApplyToRequestHead>::Error: Error + 'static,
+    <R::RequestBody as ApplyToRequestBody>::Error: Error + 'static,
+    T: ClientTransport,
+    T::Error: Error + 'static,
+    <T::Response as TransportResponse>::Error: Error + 'static,
+{
+    let error = |kind| FetchError {
+        route: R::NAME,
+        kind,
+    };
+    let request = request
+        .build()
+        .map_err(|err| error(FetchErrorKind::Build(Box::new(err))))?;
+    let response = request
+        .send_with(transport)
+        .await
+        .map_err(|err| error(FetchErrorKind::Transport(Box::new(err))))?;
+    if let Some(maintenance) = response.maintenance() {
+        return Err(error(FetchErrorKind::Maintenance(maintenance)));
+    }
+    if !response.ok() {
+        return Err(error(FetchErrorKind::Status(response.status())));
+    }
+    response
+        .json()
+        .await
+        .map_err(|err| error(FetchErrorKind::Body(Box::new(err))))
+}
+
+/// Send each of the request builders with the transport concurrently, with [fetch_json], and
+/// wait for all of them, giving a tuple of their results.
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// # use typed_routing::{join_routes, ClientTransport, JsonBody, Method, NoBody, NoQuery, Query, RequestBuilder, Route};
+/// # #[derive(Serialize, Deserialize)]
+/// # struct UserQuery { id: u64 }
+/// # struct GetUser;
+/// # impl Route for GetUser {
+/// #     type Query = Query<UserQuery>;
+/// #     type RequestBody = NoBody;
+/// #     type ResponseBody = JsonBody<String>;
+/// #     const METHOD: Method = Method::GET;
+/// #     const NAME: &'static str = "GetUser";
+/// #     const URI_PART: &'static str = "/user";
+/// #     const URI: &'static str = "/user";
+/// # }
+/// # struct ListOrders;
+/// # impl Route for ListOrders {
+/// #     type Query = NoQuery;
+/// #     type RequestBody = NoBody;
+/// #     type ResponseBody = JsonBody<Vec<u64>>;
+/// #     const METHOD: Method = Method::GET;
+/// #     const NAME: &'static str = "ListOrders";
+/// #     const URI_PART: &'static str = "/orders";
+/// #     const URI: &'static str = "/orders";
+/// # }
+/// # async fn load<T: ClientTransport>(transport: T, id: u64)
+/// # where
+/// #     T::Error: std::error::Error + 'static,
+/// #     <T::Response as typed_routing::TransportResponse>::Error: std::error::Error + 'static,
+/// # {
+/// let user = RequestBuilder::<GetUser, _, _>::new().query(UserQuery { id });
+/// let orders = RequestBuilder::<ListOrders, _, _>::new();
+/// let (user, orders) = join_routes!(&transport; user, orders).await;
+/// # }
+/// ```
+#[macro_export]
+macro_rules! join_routes {
+    ($transport:expr; $($request:expr),+ $(,)?) => {
+        async {
+            let transport = $transport;
+            $crate::futures_util::join!($($crate::join::fetch_json($request, transport)),+)
+        }
+    };
+}
+
+/// Like [join_routes], but fails with the first error instead, without waiting for the other
+/// requests.
+#[macro_export]
+macro_rules! try_join_routes {
+    ($transport:expr; $($request:expr),+ $(,)?) => {
+        async {
+            let transport = $transport;
+            $crate::futures_util::try_join!($($crate::join::fetch_json($request, transport)),+)
+        }
+    };
+}
@@ -0,0 +1,247 @@
+//! [JSON:API](https://jsonapi.org) documents, sent as `application/vnd.api+json`.
+
+use std::{collections::BTreeMap, future::Future, ops::Deref, pin::Pin};
+
+use actix_web::{
+    error::{ErrorBadRequest, ErrorUnsupportedMediaType},
+    web::Bytes,
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    ApplyToRequestBody, BodyError, ContentType, FromRequest, IntoBody, IntoResponse, NoBody,
+    NoCheckResponse, RequestBuilder, RequestHead, Response, TransportResponse,
+};
+
+/// The content type JSON:API documents are sent with.
+pub const CONTENT_TYPE: &str = "application/vnd.api+json";
+
+/// Identifies a resource by its type and ID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResourceIdentifier {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+/// The resources a [Relationship] points at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Linkage {
+    One(ResourceIdentifier),
+    Many(Vec<ResourceIdentifier>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Relationship {
+    /// `None` for an empty to-one relationship.
+    pub data: Option<Linkage>,
+}
+
+/// A resource object, with attributes of type `A`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resource<A> {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// `None` for a resource the client is asking the server to create.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub attributes: A,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub relationships: BTreeMap<String, Relationship>,
+}
+
+impl<A> Resource<A> {
+    pub fn new(kind: &str, id: Option<String>, attributes: A) -> Self {
+        Resource {
+            kind: kind.to_owned(),
+            id,
+            attributes,
+            relationships: BTreeMap::new(),
+        }
+    }
+
+    pub fn relationship(mut self, name: &str, data: Option<Linkage>) -> Self {
+        self.relationships
+            .insert(name.to_owned(), Relationship { data });
+        self
+    }
+
+    pub fn identifier(&self) -> Option<ResourceIdentifier> {
+        Some(ResourceIdentifier {
+            kind: self.kind.clone(),
+            id: self.id.clone()?,
+        })
+    }
+}
+
+/// A top-level document whose primary data is `D`, either a [Resource] or a `Vec` of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document<D> {
+    pub data: D,
+    /// Resources related to the primary data, of any type.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub included: Vec<Resource<serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
+impl<D> Document<D> {
+    pub fn new(data: D) -> Self {
+        Document {
+            data,
+            included: Vec::new(),
+            meta: None,
+        }
+    }
+
+    /// Add a related resource to `included`.
+    pub fn include<A: Serialize>(
+        mut self,
+        resource: Resource<A>,
+    ) -> Result<Self, serde_json::Error> {
+        self.included.push(Resource {
+            kind: resource.kind,
+            id: resource.id,
+            attributes: serde_json::to_value(resource.attributes)?,
+            relationships: resource.relationships,
+        });
+        Ok(self)
+    }
+
+    /// The included resources of type `kind`, with their attributes decoded as `A`.
+    pub fn included_of<A: DeserializeOwned>(
+        &self,
+        kind: &str,
+    ) -> Result<Vec<Resource<A>>, serde_json::Error> {
+        self.included
+            .iter()
+            .filter(|resource| resource.kind == kind)
+            .map(|resource| {
+                Ok(Resource {
+                    kind: resource.kind.clone(),
+                    id: resource.id.clone(),
+                    attributes: A::deserialize(&resource.attributes)?,
+                    relationships: resource.relationships.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// The included resource that `identifier` points at, with its attributes decoded as `A`.
+    pub fn resolve<A: DeserializeOwned>(
+        &self,
+        identifier: &ResourceIdentifier,
+    ) -> Option<Result<Resource<A>, serde_json::Error>> {
+        let resource = self.included.iter().find(|resource| {
+            resource.kind == identifier.kind && resource.id.as_deref() == Some(&identifier.id)
+        })?;
+        Some(
+            A::deserialize(&resource.attributes).map(|attributes| Resource {
+                kind: resource.kind.clone(),
+                id: resource.id.clone(),
+                attributes,
+                relationships: resource.relationships.clone(),
+            }),
+        )
+    }
+}
+
+/// A type that indicates that the request or response guarantees that its body will be a
+/// JSON:API document whose primary data successfully deserializes into a `D`.
+pub struct JsonApiBody<D>(Document<D>);
+
+impl<D> ContentType for JsonApiBody<D> {
+    fn matches(media_type: &str) -> bool {
+        media_type == CONTENT_TYPE
+    }
+}
+
+impl<D, Route: crate::Route<RequestBody = JsonApiBody<D>>, Query>
+    RequestBuilder<Route, Query, NoBody>
+{
+    pub fn jsonapi(self, document: Document<D>) -> RequestBuilder<Route, Query, JsonApiBody<D>> {
+        self.with_body(JsonApiBody(document))
+    }
+}
+
+impl<D> IntoBody<JsonApiBody<D>> for Document<D> {
+    fn into_body(self) -> JsonApiBody<D> {
+        JsonApiBody(self)
+    }
+}
+
+impl<D: Serialize> ApplyToRequestBody for JsonApiBody<D> {
+    type Error = serde_json::Error;
+
+    fn apply(self, head: &mut RequestHead) -> Result<Bytes, Self::Error> {
+        let body = serde_json::to_vec(&self.0)?;
+        head.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static(CONTENT_TYPE),
+        );
+        Ok(body.into())
+    }
+}
+
+impl<
+        D: DeserializeOwned,
+        Route: crate::Route<ResponseBody = JsonApiBody<D>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    pub async fn jsonapi(self) -> Result<Document<D>, BodyError<serde_json::Error, R::Error>> {
+        let body = self.body().await.map_err(BodyError::Transport)?;
+        serde_json::from_slice(&body).map_err(BodyError::Format)
+    }
+}
+
+/// An extractor and responder for JSON:API documents, the counterpart of [JsonApiBody].
+pub struct JsonApi<D>(pub Document<D>);
+
+impl<D: DeserializeOwned + 'static> actix_web::FromRequest for JsonApi<D> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let content_type_matches = JsonApiBody::<D>::matches(req.content_type());
+        let body = Bytes::from_request(req, payload);
+        Box::pin(async move {
+            if !content_type_matches {
+                return Err(ErrorUnsupportedMediaType("Expected a JSON:API document"));
+            }
+            let body = body.await?;
+            serde_json::from_slice(&body)
+                .map(JsonApi)
+                .map_err(ErrorBadRequest)
+        })
+    }
+}
+
+impl<D: Serialize> actix_web::Responder for JsonApi<D> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        match serde_json::to_vec(&self.0) {
+            Ok(body) => HttpResponse::Ok().content_type(CONTENT_TYPE).body(body),
+            Err(err) => HttpResponse::from_error(actix_web::error::ErrorInternalServerError(err)),
+        }
+    }
+}
+
+impl<D: DeserializeOwned, Query> FromRequest<Query, JsonApiBody<D>> for JsonApi<D> {}
+
+impl<D: Serialize> IntoResponse<JsonApiBody<D>> for JsonApi<D> {}
+
+impl<R, T: IntoResponse<JsonApiBody<R>>, E> IntoResponse<JsonApiBody<R>> for Result<T, E> {}
+
+impl<R, T> IntoResponse<JsonApiBody<R>> for NoCheckResponse<T> {}
+
+impl<D> Deref for JsonApi<D> {
+    type Target = Document<D>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
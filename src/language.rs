@@ -0,0 +1,194 @@
+//! Language negotiation with the `Accept-Language` header.
+
+use std::{
+    fmt::{self, Display},
+    future::{ready, Ready},
+    ops::Deref,
+    str::FromStr,
+};
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{ClientTransport, FromRequest};
+
+/// A language tag such as `en-GB`, or the `*` wildcard.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LanguageTag(String);
+
+impl LanguageTag {
+    /// The primary language subtag, e.g. `en` for `en-GB`.
+    pub fn primary(&self) -> &str {
+        self.0.split('-').next().unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The error returned when parsing an invalid [LanguageTag].
+#[derive(Debug, Error)]
+#[error("Invalid language tag {0:?}")]
+pub struct ParseLanguageTagError(pub String);
+
+impl FromStr for LanguageTag {
+    type Err = ParseLanguageTagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = s == "*"
+            || (!s.is_empty()
+                && s.split('-').all(|subtag| {
+                    (1..=8).contains(&subtag.len())
+                        && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+                }));
+        if valid {
+            Ok(LanguageTag(s.to_owned()))
+        } else {
+            Err(ParseLanguageTagError(s.to_owned()))
+        }
+    }
+}
+
+impl Display for LanguageTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Build an `Accept-Language` value from `languages` in order of preference, weighting each
+/// one a little lower than the last.
+fn header_value<S: AsRef<str>>(languages: &[S]) -> String {
+    languages
+        .iter()
+        .enumerate()
+        .map(|(i, language)| match i {
+            0 => language.as_ref().to_owned(),
+            i => {
+                let weight = 10usize.saturating_sub(i).max(1);
+                format!("{};q=0.{weight}", language.as_ref())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A [ClientTransport] that adds an `Accept-Language` header to every request that doesn't
+/// already have one.
+#[derive(Debug, Clone)]
+pub struct WithAcceptLanguage<T> {
+    inner: T,
+    value: Option<http::HeaderValue>,
+}
+
+impl<T> WithAcceptLanguage<T> {
+    /// Send `languages`, most preferred first.
+    ///
+    /// Languages that aren't valid header values are skipped.
+    pub fn new<S: AsRef<str>>(inner: T, languages: &[S]) -> Self {
+        let languages: Vec<&str> = languages
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|language| http::HeaderValue::from_str(language).is_ok())
+            .collect();
+        let value = match languages.as_slice() {
+            [] => None,
+            languages => http::HeaderValue::try_from(header_value(languages)).ok(),
+        };
+        WithAcceptLanguage { inner, value }
+    }
+
+    /// Send the user's preferred languages, as reported by `navigator.languages`.
+    #[cfg(feature = "gloo")]
+    pub fn from_navigator(inner: T) -> Self {
+        Self::new(inner, &navigator_languages())
+    }
+}
+
+#[cfg(feature = "gloo")]
+fn navigator_languages() -> Vec<String> {
+    let languages = js_sys::Reflect::get(&js_sys::global(), &"navigator".into())
+        .and_then(|navigator| js_sys::Reflect::get(&navigator, &"languages".into()));
+    match languages {
+        Ok(languages) if js_sys::Array::is_array(&languages) => js_sys::Array::from(&languages)
+            .iter()
+            .filter_map(|language| language.as_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for WithAcceptLanguage<T> {
+    type Response = T::Response;
+    type Error = T::Error;
+
+    async fn send(&self, mut request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        if let Some(value) = &self.value {
+            request
+                .headers_mut()
+                .entry(http::header::ACCEPT_LANGUAGE)
+                .or_insert_with(|| value.clone());
+        }
+        self.inner.send(request).await
+    }
+}
+
+/// An extractor for the languages a request's `Accept-Language` header accepts, most preferred
+/// first.
+///
+/// Languages with a weight of `0` and malformed entries are left out, and a missing header gives
+/// an empty list.
+pub struct AcceptedLanguages(pub Vec<LanguageTag>);
+
+impl FromStr for AcceptedLanguages {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weighted: Vec<(LanguageTag, f32)> = s
+            .split(',')
+            .filter_map(|entry| {
+                let mut params = entry.split(';').map(str::trim);
+                let tag = params.next()?.parse().ok()?;
+                let weight = match params.find_map(|param| param.strip_prefix("q=")) {
+                    Some(weight) => weight.parse().ok()?,
+                    None => 1.0,
+                };
+                Some((tag, weight))
+            })
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        weighted.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        Ok(AcceptedLanguages(
+            weighted.into_iter().map(|(tag, _)| tag).collect(),
+        ))
+    }
+}
+
+impl actix_web::FromRequest for AcceptedLanguages {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let languages = req
+            .headers()
+            .get(actix_web::http::header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        ready(Ok(languages
+            .parse()
+            .unwrap_or(AcceptedLanguages(Vec::new()))))
+    }
+}
+
+impl<Query, Body> FromRequest<Query, Body> for AcceptedLanguages {}
+
+impl Deref for AcceptedLanguages {
+    type Target = [LanguageTag];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
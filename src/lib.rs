@@ -1,13 +1,105 @@
 use std::{
     convert::Infallible,
+    future::{ready, Future, Ready},
     marker::PhantomData,
-    ops::{Deref, DerefMut},
+    ops::{Bound, Deref, DerefMut, RangeBounds, RangeInclusive},
+    str::FromStr,
 };
 
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use compat::ToActix;
+
+#[doc(hidden)]
+pub use actix_web;
+#[doc(hidden)]
+pub use futures_util;
+pub use http;
+pub use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+
+pub mod audit;
+#[cfg(feature = "bincode")]
+pub mod bincode;
+pub mod broadcast;
+#[cfg(feature = "schema")]
+pub mod catalog;
+pub mod chaos;
+pub mod circuit;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod client;
+pub mod clock;
+pub mod compat;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "debug-log")]
+pub mod debug_log;
+#[cfg(feature = "digest")]
+pub mod digest;
+#[cfg(feature = "dioxus")]
+pub mod dioxus;
+pub mod envelope;
+pub mod erased;
+pub mod examples;
+#[cfg(feature = "simd-json")]
+pub mod fast_json;
+pub mod flags;
+pub mod format;
+#[cfg(feature = "gloo")]
+pub mod gloo;
+pub mod health;
+#[cfg(feature = "hyper")]
+pub mod hyper;
+pub mod join;
+#[cfg(feature = "jsonapi")]
+pub mod jsonapi;
+pub mod language;
+pub mod limit;
+pub mod links;
+pub mod maintenance;
+#[cfg(feature = "mock-server")]
+pub mod mock_server;
+#[cfg(feature = "oauth")]
+pub mod oauth;
+pub mod poll;
+pub mod postman;
+pub mod problem;
+pub mod propagate;
+pub mod redact;
+pub mod redirect;
+pub mod registration;
+pub mod request_id;
+pub mod schedule;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod session;
+pub mod shutdown;
+#[cfg(feature = "spa")]
+pub mod spa;
+pub mod sse;
+pub mod status;
+pub mod stream;
+pub mod typed;
+#[cfg(feature = "wasm-test")]
+pub mod wasm_test;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+#[cfg(feature = "wiremock")]
+pub mod wiremock;
+#[cfg(feature = "xml")]
+pub mod xml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
 /// A marker trait that indicates that an extractor is compatible with a particular request
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't extract a request with query `{Query}` and body `{Body}`",
+    label = "this extractor doesn't match the query or body declared for the route",
+    note = "extractors of the query or body have to match the route's declaration, e.g. `web::Json<T>` needs `body: JsonBody<T>` and `web::Query<T>` needs `query: Query<T>`",
+    note = "extractors that read neither, e.g. `HttpRequest`, need a `FromRequest<Query, Body>` impl for every query and body"
+)]
 pub trait FromRequest<Query, Body> {}
 
 impl<'de, T: Serialize + Deserialize<'de>, Query> FromRequest<Query, JsonBody<T>>
@@ -15,21 +107,93 @@ impl<'de, T: Serialize + Deserialize<'de>, Query> FromRequest<Query, JsonBody<T>
 {
 }
 
+impl<'de, T: Serialize + Deserialize<'de>, Query> FromRequest<Query, FormBody<T>>
+    for actix_web::web::Form<T>
+{
+}
+
 impl<'de, T: Serialize + Deserialize<'de>, Body> FromRequest<Query<T>, Body>
     for actix_web::web::Query<T>
 {
 }
 
+/// Implement [FromRequest] for a type made of other extractors, for any query and body its
+/// parts are all compatible with, e.g. an extractor that bundles several together:
+///
+/// ```
+/// // Extracted by extracting both of its parts, in an actix `FromRequest` impl.
+/// struct Context<A, B>(A, B);
+///
+/// typed_routing::impl_from_request! { Context<A, B> }
+/// ```
+///
+/// Tuples of up to 16 extractors, as many as actix takes, are already covered. Handlers that
+/// need more can nest tuples, since a tuple of extractors is an extractor too.
+#[macro_export]
 macro_rules! impl_from_request {
+    ($type:ident < $($i:ident),* $(,)? >) => {
+        impl<Query, Body $(,$i)*> $crate::FromRequest<Query, Body> for $type<$($i),*>
+        where
+            $($i: $crate::FromRequest<Query, Body>),*
+        {}
+    };
     ($($i:ident)*) => {
-        impl<Query, Body $(,$i)*> FromRequest<Query, Body> for ($($i,)*)
+        impl<Query, Body $(,$i)*> $crate::FromRequest<Query, Body> for ($($i,)*)
         where
-            $($i: FromRequest<Query, Body>),*
+            $($i: $crate::FromRequest<Query, Body>),*
 
          {}
     };
 }
 
+/// Declare which requests your own extractor is compatible with, instead of writing its
+/// [FromRequest] impl by hand:
+///
+/// ```
+/// # use typed_routing::{JsonBody, Query};
+/// # struct Auth;
+/// # trait Validate {}
+/// # struct Validated<T>(T);
+/// # struct Filters<T>(T);
+/// # struct Signed<Q, B>(Q, B);
+/// // An extractor that reads neither the query nor the body, e.g. from headers.
+/// typed_routing::compatible_extractor! { Auth => any }
+/// // An extractor that reads the body, for bodies of types it can decode.
+/// typed_routing::compatible_extractor! { impl[T: Validate] Validated<T> => body: JsonBody<T> }
+/// // An extractor that reads the query, whatever the body.
+/// typed_routing::compatible_extractor! { impl[T] Filters<T> => query: Query<T> }
+/// // An extractor that reads both.
+/// typed_routing::compatible_extractor! { impl[Q, B] Signed<Q, B> => query: Query<Q>, body: JsonBody<B> }
+/// ```
+///
+/// The generics of the impl go in `impl[...]`, and a `where` clause can follow.
+#[macro_export]
+macro_rules! compatible_extractor {
+    (impl [$($generics:tt)*] $type:ty => any $(where $($bounds:tt)+)?) => {
+        impl<__Query, __Body, $($generics)*> $crate::FromRequest<__Query, __Body> for $type
+        $(where $($bounds)+)?
+        {}
+    };
+    (impl [$($generics:tt)*] $type:ty => query: $query:ty, body: $body:ty $(where $($bounds:tt)+)?) => {
+        impl<$($generics)*> $crate::FromRequest<$query, $body> for $type
+        $(where $($bounds)+)?
+        {}
+    };
+    (impl [$($generics:tt)*] $type:ty => query: $query:ty $(where $($bounds:tt)+)?) => {
+        impl<__Body, $($generics)*> $crate::FromRequest<$query, __Body> for $type
+        $(where $($bounds)+)?
+        {}
+    };
+    (impl [$($generics:tt)*] $type:ty => body: $body:ty $(where $($bounds:tt)+)?) => {
+        impl<__Query, $($generics)*> $crate::FromRequest<__Query, $body> for $type
+        $(where $($bounds)+)?
+        {}
+    };
+    ($type:ty => $($rest:tt)*) => {
+        $crate::compatible_extractor! { impl[] $type => $($rest)* }
+    };
+}
+
 impl_from_request! {}
 impl_from_request! { A }
 impl_from_request! { A B }
@@ -49,20 +213,28 @@ impl_from_request! { A B C D E F G H I J K L M N O }
 impl_from_request! { A B C D E F G H I J K L M N O P }
 
 /// A marker trait that indicates that a return type is compatible with a particular response
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be returned for a response body declared as `{Body}`",
+    label = "this return type doesn't match the response body declared for the route",
+    note = "return a type implementing `IntoResponse<{Body}>`, or a `Result` of one"
+)]
 pub trait IntoResponse<Body> {}
 
 impl<T> IntoResponse<NoBody> for T {}
 
 impl<R, T: IntoResponse<JsonBody<R>>, E> IntoResponse<JsonBody<R>> for Result<T, E> {}
 
+impl<R, T> IntoResponse<JsonBody<R>> for NoCheckResponse<T> {}
+
 /// A type that indicates that the request makes no guarantees about its query string.
 pub struct NoQuery;
 
 /// A type that indicates that the request guarantees that its query string will successfully
 /// deserialize into a `T`.
 ///
-/// This will use [serde_urlencoded] to serialize to and deserialize from the query string.
-pub struct Query<T>(T);
+/// The query string is encoded with `F`, [serde_urlencoded] by default. See
+/// [QueryFormat](format::QueryFormat) for other encodings.
+pub struct Query<T, F = format::UrlEncoded>(T, PhantomData<fn() -> F>);
 
 /// A type that indicates that the request or response makes no guarantees about its body,
 /// or if it even has one.
@@ -72,12 +244,239 @@ pub struct NoBody;
 /// that successfully deserializes into a `T` when using `serde_json`'s deserializer.
 pub struct JsonBody<T>(T);
 
+/// A type that indicates that the request guarantees that its body will be a URL encoded form
+/// that successfully deserializes into a `T`.
+///
+/// Like [Query], this uses [serde_urlencoded].
+pub struct FormBody<T>(T);
+
+/// Implemented by response body types to say which content types their format is sent
+/// with, so that [Response::strict] can check responses before decoding them.
+pub trait ContentType {
+    /// Whether a body sent as `media_type` (without parameters such as `charset`) is in this
+    /// format.
+    fn matches(media_type: &str) -> bool;
+}
+
+impl ContentType for NoBody {
+    fn matches(_media_type: &str) -> bool {
+        true
+    }
+}
+
+impl<T> ContentType for JsonBody<T> {
+    fn matches(media_type: &str) -> bool {
+        media_type == "application/json" || media_type.ends_with("+json")
+    }
+}
+
+impl<T> ContentType for FormBody<T> {
+    fn matches(media_type: &str) -> bool {
+        media_type == "application/x-www-form-urlencoded"
+    }
+}
+
 /// An extractor wrapper that opts-out of checking if the inner extractor is compatible with the request.
 /// Useful if you want to use a third-party extractor that doesn't implement [FromRequest].
 ///
 /// For your own extractors you should favour implementing [FromRequest] on it over using this.
+///
+/// It extracts exactly as `T` does, so wrapping an extractor only changes type checking.
 pub struct NoCheck<T>(pub T);
 
+impl<T: actix_web::FromRequest> actix_web::FromRequest for NoCheck<T> {
+    type Error = T::Error;
+    type Future = futures_util::future::MapOk<T::Future, fn(T) -> Self>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        futures_util::TryFutureExt::map_ok(T::from_request(req, payload), NoCheck)
+    }
+}
+
+impl<T, Query, Body> FromRequest<Query, Body> for NoCheck<T> {}
+
+/// A return type wrapper that opts-out of checking if the inner type is compatible with the
+/// route's response body, the counterpart of [NoCheck]. It responds exactly as `T` does.
+///
+/// Every response body type in this crate accepts it. Response body types defined elsewhere
+/// need their own `impl<T, ...> IntoResponse<TheirBody<...>> for NoCheckResponse<T>`.
+pub struct NoCheckResponse<T>(pub T);
+
+impl<T: actix_web::Responder> actix_web::Responder for NoCheckResponse<T> {
+    type Body = T::Body;
+
+    fn respond_to(self, req: &actix_web::HttpRequest) -> actix_web::HttpResponse<Self::Body> {
+        self.0.respond_to(req)
+    }
+}
+
+/// An extractor for the `{tenant}` segment of a [TenantScoped] route's URI.
+pub struct Tenant<T>(pub T);
+
+impl<T: FromStr> actix_web::FromRequest for Tenant<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        ready(
+            match req.match_info().get(TENANT_SEGMENT).map(T::from_str) {
+                Some(Ok(tenant)) => Ok(Tenant(tenant)),
+                Some(Err(_)) | None => Err(actix_web::error::ErrorNotFound("Unknown tenant")),
+            },
+        )
+    }
+}
+
+impl<T, Query, Body> FromRequest<Query, Body> for Tenant<T> {}
+
+/// An extractor that rejects requests with `415 Unsupported Media Type` if the route declares
+/// a [Route::MEDIA_TYPE] and the request body wasn't sent with it.
+pub struct RequireMediaType<Route> {
+    _marker: PhantomData<fn() -> Route>,
+}
+
+impl<Route: self::Route> actix_web::FromRequest for RequireMediaType<Route> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let content_type = req
+            .headers()
+            .get(actix_web::http::header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .map(media_type_essence);
+        ready(match Route::MEDIA_TYPE {
+            Some(expected) if content_type != Some(expected) => Err(
+                actix_web::error::ErrorUnsupportedMediaType(format!("Expected {expected}")),
+            ),
+            _ => Ok(RequireMediaType {
+                _marker: PhantomData,
+            }),
+        })
+    }
+}
+
+impl<Route, Query, Body> FromRequest<Query, Body> for RequireMediaType<Route> {}
+
+/// An extractor for the values of a route's [Route::REQUIRED_COOKIES], which rejects requests
+/// that are missing any of them with `400 Bad Request`.
+pub struct RouteCookies<Route> {
+    _marker: PhantomData<fn() -> Route>,
+    values: Vec<String>,
+}
+
+impl<Route: self::Route> RouteCookies<Route> {
+    /// The value of the required cookie `name`, or `None` if the route doesn't require it.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let index = Route::REQUIRED_COOKIES.iter().position(|&n| n == name)?;
+        Some(&self.values[index])
+    }
+
+    /// The values of the required cookies, in the order the route declares them.
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+
+    /// The required cookies as a `T` with a field for each, named like the cookie.
+    pub fn parse<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_urlencoded::de::Error> {
+        let pairs: Vec<(&str, &str)> = Route::REQUIRED_COOKIES
+            .iter()
+            .copied()
+            .zip(self.values.iter().map(String::as_str))
+            .collect();
+        let encoded =
+            serde_urlencoded::to_string(pairs).expect("string pairs can always be encoded");
+        serde_urlencoded::from_str(&encoded)
+    }
+}
+
+/// An extractor for a route's [Route::REQUIRED_COOKIES] as a `T` with a field for each, e.g.
+/// `struct Session { session_id: String }`. Requests missing any of them, or whose values don't
+/// fit `T`, are rejected with `400 Bad Request`.
+pub struct TypedCookies<Route, T> {
+    _marker: PhantomData<fn() -> Route>,
+    cookies: T,
+}
+
+impl<Route, T> TypedCookies<Route, T> {
+    pub fn into_inner(self) -> T {
+        self.cookies
+    }
+}
+
+impl<Route: self::Route, T: serde::de::DeserializeOwned> actix_web::FromRequest
+    for TypedCookies<Route, T>
+{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let cookies = RouteCookies::<Route>::from_request(req, payload)
+            .into_inner()
+            .and_then(|cookies| cookies.parse().map_err(actix_web::error::ErrorBadRequest));
+        ready(cookies.map(|cookies| TypedCookies {
+            _marker: PhantomData,
+            cookies,
+        }))
+    }
+}
+
+impl<Route, T, Query, Body> FromRequest<Query, Body> for TypedCookies<Route, T> {}
+
+impl<Route: self::Route> actix_web::FromRequest for RouteCookies<Route> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let values = Route::REQUIRED_COOKIES
+            .iter()
+            .map(|&name| match req.cookie(name) {
+                Some(cookie) => Ok(cookie.value().to_owned()),
+                None => Err(actix_web::error::ErrorBadRequest(format!(
+                    "Missing cookie {name}"
+                ))),
+            })
+            .collect::<Result<_, _>>();
+        ready(values.map(|values| RouteCookies {
+            _marker: PhantomData,
+            values,
+        }))
+    }
+}
+
+impl<Route, Query, Body> FromRequest<Query, Body> for RouteCookies<Route> {}
+
+impl<Route, T> Deref for TypedCookies<Route, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cookies
+    }
+}
+
+impl<T> Deref for Tenant<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl<T> Deref for NoCheck<T> {
     type Target = T;
 
@@ -92,64 +491,378 @@ impl<T> DerefMut for NoCheck<T> {
     }
 }
 
-trait ApplyToRequestHead {
+/// The parts of a request, other than its body, that a [RequestBuilder] assembles before the
+/// request is handed to a [ClientTransport].
+#[derive(Debug, Clone)]
+pub struct RequestHead {
+    pub method: http::Method,
+    /// The request's path, without a query string.
+    pub path: String,
+    /// The request's URL encoded query string, without a leading `?`.
+    pub query: String,
+    pub headers: http::HeaderMap,
+}
+
+impl RequestHead {
+    fn new(method: http::Method, path: &str) -> Self {
+        RequestHead {
+            method,
+            path: path.to_owned(),
+            query: String::new(),
+            headers: http::HeaderMap::new(),
+        }
+    }
+
+    /// Append already URL encoded `key=value` pairs to the query string.
+    pub fn append_query(&mut self, encoded: &str) {
+        if encoded.is_empty() {
+            return;
+        }
+        if !self.query.is_empty() {
+            self.query.push('&');
+        }
+        self.query.push_str(encoded);
+    }
+
+    /// The request's path and query string.
+    pub fn uri(&self) -> String {
+        if self.query.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{}?{}", self.path, self.query)
+        }
+    }
+}
+
+/// Implemented by a route's query type to add it to the request being built.
+pub trait ApplyToRequestHead {
     type Error;
-    fn apply(
-        self,
-        builder: gloo_net::http::RequestBuilder,
-    ) -> Result<gloo_net::http::RequestBuilder, Self::Error>;
+    fn apply(self, head: &mut RequestHead) -> Result<(), Self::Error>;
 }
 
 impl ApplyToRequestHead for NoQuery {
     type Error = Infallible;
-    fn apply(
-        self,
-        builder: gloo_net::http::RequestBuilder,
-    ) -> Result<gloo_net::http::RequestBuilder, Self::Error> {
-        Ok(builder)
+    fn apply(self, _head: &mut RequestHead) -> Result<(), Self::Error> {
+        Ok(())
     }
 }
 
-impl<T: Serialize> ApplyToRequestHead for Query<T> {
-    type Error = serde_urlencoded::ser::Error;
-    fn apply(
-        self,
-        builder: gloo_net::http::RequestBuilder,
-    ) -> Result<gloo_net::http::RequestBuilder, Self::Error> {
-        let params = serde_urlencoded::to_string(self.0)?;
-        let params = params.split('&').filter_map(|pair| pair.split_once('='));
-        Ok(builder.query(params))
+impl<T: Serialize, F: format::QueryFormat> ApplyToRequestHead for Query<T, F> {
+    type Error = F::Error;
+    fn apply(self, head: &mut RequestHead) -> Result<(), Self::Error> {
+        head.append_query(&F::serialize(&self.0)?);
+        Ok(())
     }
 }
 
-trait ApplyToRequestBody {
+/// Implemented by a route's request body type to serialize it into the request being built.
+pub trait ApplyToRequestBody {
     type Error;
-    fn apply(
-        self,
-        builder: gloo_net::http::RequestBuilder,
-    ) -> Result<gloo_net::http::Request, Self::Error>;
+    /// Serialize the body, setting any headers that describe it, such as `Content-Type`.
+    ///
+    /// An empty body is sent as no body at all.
+    fn apply(self, head: &mut RequestHead) -> Result<Bytes, Self::Error>;
 }
 
 impl ApplyToRequestBody for NoBody {
-    type Error = gloo_net::Error;
-    fn apply(
-        self,
-        builder: gloo_net::http::RequestBuilder,
-    ) -> Result<gloo_net::http::Request, Self::Error> {
-        builder.build()
+    type Error = Infallible;
+    fn apply(self, _head: &mut RequestHead) -> Result<Bytes, Self::Error> {
+        Ok(Bytes::new())
     }
 }
+
 impl<T: Serialize> ApplyToRequestBody for JsonBody<T> {
-    type Error = gloo_net::Error;
+    type Error = serde_json::Error;
+
+    fn apply(self, head: &mut RequestHead) -> Result<Bytes, Self::Error> {
+        let body = serde_json::to_vec(&self.0)?;
+        head.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/json"),
+        );
+        Ok(body.into())
+    }
+}
 
-    fn apply(
-        self,
-        builder: gloo_net::http::RequestBuilder,
-    ) -> Result<gloo_net::http::Request, Self::Error> {
-        builder.json(&self.0)
+impl<T: Serialize> ApplyToRequestBody for FormBody<T> {
+    type Error = serde_urlencoded::ser::Error;
+
+    fn apply(self, head: &mut RequestHead) -> Result<Bytes, Self::Error> {
+        let body = serde_urlencoded::to_string(self.0)?;
+        head.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        Ok(body.into())
     }
 }
 
+/// Converts a value into a route's query, so that [Request::with] can take the query's value
+/// directly, or `()` for routes without one.
+pub trait IntoQuery<Query> {
+    fn into_query(self) -> Query;
+}
+
+impl IntoQuery<NoQuery> for () {
+    fn into_query(self) -> NoQuery {
+        NoQuery
+    }
+}
+
+impl<T, F> IntoQuery<Query<T, F>> for T {
+    fn into_query(self) -> Query<T, F> {
+        Query(self, PhantomData)
+    }
+}
+
+/// Converts a value into a route's request body, so that [Request::with] can take the body's
+/// value directly, or `()` for routes without one.
+pub trait IntoBody<Body> {
+    fn into_body(self) -> Body;
+}
+
+impl IntoBody<NoBody> for () {
+    fn into_body(self) -> NoBody {
+        NoBody
+    }
+}
+
+impl<T> IntoBody<JsonBody<T>> for T {
+    fn into_body(self) -> JsonBody<T> {
+        JsonBody(self)
+    }
+}
+
+impl<T> IntoBody<FormBody<T>> for T {
+    fn into_body(self) -> FormBody<T> {
+        FormBody(self)
+    }
+}
+
+pub type JsonErrorHandler =
+    fn(actix_web::error::JsonPayloadError, &actix_web::HttpRequest) -> actix_web::Error;
+
+pub type QueryErrorHandler =
+    fn(actix_web::error::QueryPayloadError, &actix_web::HttpRequest) -> actix_web::Error;
+
+/// A predicate a request has to pass to reach a route's handler, see [Route::GUARDS].
+pub type RouteGuard = fn(&actix_web::guard::GuardContext<'_>) -> bool;
+
+/// How a route's URI should treat a trailing slash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// The URI never ends in a slash (other than the root URI `/`).
+    Trim,
+    /// The URI always ends in a slash.
+    Always,
+    /// The URI doesn't end in a slash, but the server also accepts requests with one.
+    Either,
+}
+
+/// Collapses repeated slashes in `raw` and applies `trailing_slash` to it, writing the
+/// result to the start of the returned buffer alongside its length.
+///
+/// `N` must be at least `raw.len() + 1`. Use [normalize_uri] rather than calling this directly.
+#[doc(hidden)]
+pub const fn normalize_uri<const N: usize>(
+    raw: &str,
+    trailing_slash: TrailingSlash,
+) -> ([u8; N], usize) {
+    let raw = raw.as_bytes();
+    let mut uri = [0; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < raw.len() {
+        if !(raw[i] == b'/' && len > 0 && uri[len - 1] == b'/') {
+            uri[len] = raw[i];
+            len += 1;
+        }
+        i += 1;
+    }
+    match trailing_slash {
+        TrailingSlash::Trim | TrailingSlash::Either => {
+            if len > 1 && uri[len - 1] == b'/' {
+                len -= 1;
+            }
+        }
+        TrailingSlash::Always => {
+            if len == 0 || uri[len - 1] != b'/' {
+                uri[len] = b'/';
+                len += 1;
+            }
+        }
+    }
+    (uri, len)
+}
+
+/// Writes `a` followed by `b` into a buffer, for [concat_uri].
+///
+/// `N` must be `a.len() + b.len()`.
+#[doc(hidden)]
+pub const fn concat_bytes<const N: usize>(a: &str, b: &str) -> [u8; N] {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let mut bytes = [0; N];
+    let mut i = 0;
+    while i < a.len() {
+        bytes[i] = a[i];
+        i += 1;
+    }
+    while i < N {
+        bytes[i] = b[i - a.len()];
+        i += 1;
+    }
+    bytes
+}
+
+/// Fails to compile if `uri` has a `{` without a matching `}`, a placeholder inside another,
+/// an empty `{}`, or the same placeholder twice.
+///
+/// Braces in a placeholder's regex, e.g. the quantifier in `{id:\d{3}}`, only need to balance.
+#[doc(hidden)]
+pub const fn check_placeholders(uri: &str) {
+    let uri = uri.as_bytes();
+    let mut i = 0;
+    // Where the open placeholder's name starts and, once its `:` is reached, ends.
+    let mut open: Option<(usize, Option<usize>)> = None;
+    // How many braces are open in the placeholder's regex.
+    let mut depth = 0;
+    while i < uri.len() {
+        match (uri[i], open) {
+            (b'\\', Some((_, Some(_)))) => i += 1,
+            (b'{', Some((_, Some(_)))) => depth += 1,
+            (b'}', Some((_, Some(_)))) if depth > 0 => depth -= 1,
+            (b'{', Some((_, None))) => panic!("A URI placeholder can't contain another"),
+            (b'{', None) => open = Some((i + 1, None)),
+            (b'}', None) => panic!("A URI has a `}}` without a matching `{{`"),
+            (b':', Some((start, None))) => open = Some((start, Some(i))),
+            (b'}', Some((start, end))) => {
+                let end = match end {
+                    Some(end) => end,
+                    None => i,
+                };
+                if end == start {
+                    panic!("A URI has an empty placeholder");
+                }
+                if placeholder_repeats(uri, start, end) {
+                    panic!("A URI has the same placeholder twice");
+                }
+                open = None;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if open.is_some() {
+        panic!("A URI has a `{{` without a matching `}}`");
+    }
+}
+
+/// Whether the placeholder named `uri[start..end]` appears again after it.
+const fn placeholder_repeats(uri: &[u8], start: usize, end: usize) -> bool {
+    let len = end - start;
+    let mut i = end;
+    while i + len + 1 < uri.len() {
+        let mut j = 0;
+        while j < len && uri[i + 1 + j] == uri[start + j] {
+            j += 1;
+        }
+        if uri[i] == b'{' && j == len && matches!(uri[i + 1 + len], b'}' | b':') {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Concatenates two constant strings at compile time, checking that the resulting URI's
+/// placeholders are well formed.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! concat_uri {
+    ($a:expr, $b:expr) => {{
+        const A: &str = $a;
+        const B: &str = $b;
+        const BYTES: [u8; A.len() + B.len()] = $crate::concat_bytes::<{ A.len() + B.len() }>(A, B);
+        const URI: &str = match ::std::str::from_utf8(&BYTES) {
+            Ok(uri) => uri,
+            Err(_) => panic!("Concatenating a URI produced invalid UTF-8"),
+        };
+        const _: () = $crate::check_placeholders(URI);
+        URI
+    }};
+}
+
+/// Normalizes a constant URI at compile time, collapsing the duplicate slashes that prefix
+/// concatenation tends to produce and applying a [TrailingSlash] policy.
+#[macro_export]
+macro_rules! normalize_uri {
+    ($uri:expr, $trailing_slash:expr) => {{
+        const RAW: &str = $uri;
+        const NORMALIZED: ([u8; RAW.len() + 1], usize) =
+            $crate::normalize_uri::<{ RAW.len() + 1 }>(RAW, $trailing_slash);
+        match ::std::str::from_utf8(NORMALIZED.0.split_at(NORMALIZED.1).0) {
+            Ok(uri) => uri,
+            Err(_) => panic!("Normalizing a URI produced invalid UTF-8"),
+        }
+    }};
+}
+
+const TENANT_SEGMENT: &str = "tenant";
+const TENANT_PLACEHOLDER: &str = "{tenant}";
+
+/// A marker trait for routes whose URI contains a `{tenant}` segment, typically because
+/// the module's scope is something like `"/t/{tenant}"`.
+///
+/// Requests to these routes are built with [RequestBuilder::for_tenant]. The module's
+/// [Module::register] mounts them at their URI with the segment as a path parameter, and
+/// handlers can take a [Tenant] to get at its value.
+pub trait TenantScoped: Route {}
+
+/// Percent-encode everything in `value` but unreserved characters, e.g. so a path segment
+/// can't add segments, a query or a fragment to its URI.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Decode the `%XX` escapes in `value`, leaving any `%` that doesn't start one as it is.
+fn percent_decode(value: &str) -> String {
+    String::from_utf8_lossy(&percent_decode_bytes(value)).into_owned()
+}
+
+/// The bytes `value`'s `%XX` escapes stand for, which needn't be UTF-8.
+fn percent_decode_bytes(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit));
+        match escape
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
 pub trait Route {
     type Query: ApplyToRequestHead;
 
@@ -159,40 +872,141 @@ pub trait Route {
 
     const METHOD: http::Method;
 
+    /// The name of the route's type, e.g. for use as a metric label or in logs.
+    const NAME: &'static str;
+
     const URI_PART: &'static str;
     const URI: &'static str;
+
+    /// The trailing slash policy that was applied to [Route::URI].
+    const TRAILING_SLASH: TrailingSlash = TrailingSlash::Trim;
+
+    /// A custom media type, e.g. `application/vnd.myapp.v2+json`, that the route's request and
+    /// response bodies are sent with instead of their format's usual content type.
+    const MEDIA_TYPE: Option<&'static str> = None;
+
+    /// Whether a `GET` route also responds to `HEAD` requests, with actix dropping the response
+    /// body.
+    const HEAD: bool = false;
+
+    /// The scopes a caller's token must have been granted to use this route. The server answers
+    /// requests whose [GrantedScopes] lack any of them with `403 Forbidden`.
+    const REQUIRED_SCOPES: &'static [&'static str] = &[];
+
+    /// The cookies, e.g. a session id, that a request to this route must carry.
+    const REQUIRED_COOKIES: &'static [&'static str] = &[];
+
+    /// Whether requests to this route are reported to an [audit::AuditSink] by the
+    /// [audit::Audit] middleware.
+    const AUDITED: bool = false;
+
+    /// The largest JSON request body, in bytes, that the route's handler accepts, instead of
+    /// the app's [actix_web::web::JsonConfig].
+    const JSON_LIMIT: Option<usize> = None;
+
+    /// Turns errors extracting the route's JSON request body into responses, instead of the
+    /// app's [actix_web::web::JsonConfig].
+    const JSON_ERROR_HANDLER: Option<JsonErrorHandler> = None;
+
+    /// Turns errors extracting the route's query into responses, instead of the app's
+    /// [actix_web::web::QueryConfig].
+    const QUERY_ERROR_HANDLER: Option<QueryErrorHandler> = None;
+
+    /// Predicates, e.g. on the request's host or headers, that requests have to pass to be
+    /// handled by this route. Requests that fail them are tried against the routes registered
+    /// after it, as with actix guards.
+    const GUARDS: &'static [RouteGuard] = &[];
+
+    /// How a [schedule::Scheduler] queues requests to this route.
+    const PRIORITY: schedule::Priority = schedule::Priority::Normal;
+
+    /// The feature flag that has to be on for the server to serve this route, looked up in the
+    /// app's [flags::FeatureFlags].
+    const FEATURE_FLAG: Option<&'static str> = None;
+
+    /// An old URI the route is also served at while clients migrate away from it. Responses
+    /// there carry a `Deprecation` header, and are behind the same checks, e.g. of
+    /// [Route::REQUIRED_SCOPES], as at the route's URI.
+    const ALIAS: Option<&'static str> = None;
+
+    /// Whether requests to [Route::ALIAS] are redirected to [Route::URI] with a
+    /// `308 Permanent Redirect`, instead of being handled there.
+    const ALIAS_REDIRECT: bool = false;
+
+    /// The names of query, body and path fields holding secrets, e.g. passwords, whose values
+    /// the crate masks wherever it logs or records requests, see [redact].
+    const SENSITIVE_FIELDS: &'static [&'static str] = &[];
+
+    /// Whether a token that was granted `scopes` is allowed to use this route.
+    ///
+    /// Useful on the client to hide actions the current token can't perform.
+    fn is_permitted<S: AsRef<str>>(scopes: &[S]) -> bool {
+        Self::REQUIRED_SCOPES
+            .iter()
+            .all(|required| scopes.iter().any(|scope| scope.as_ref() == *required))
+    }
 }
 
-struct RequestBuilder<Route, Query, Body> {
-    _marker: PhantomData<*const Route>,
+pub struct RequestBuilder<Route, Query, Body> {
+    _marker: PhantomData<fn() -> Route>,
     query: Query,
     body: Body,
-    builder: gloo_net::http::RequestBuilder,
+    head: RequestHead,
+    priority: schedule::Priority,
+    canonical_query: bool,
+    #[cfg(feature = "digest")]
+    digest: Option<digest::DigestAlgorithm>,
 }
 
 impl<Route: self::Route> RequestBuilder<Route, NoQuery, NoBody> {
     pub fn new() -> Self {
-        let builder = gloo_net::http::RequestBuilder::new(Route::URI).method(match Route::METHOD {
-            http::Method::GET => gloo_net::http::Method::GET,
-            http::Method::POST => gloo_net::http::Method::POST,
-            http::Method::PUT => gloo_net::http::Method::PUT,
-            http::Method::DELETE => gloo_net::http::Method::DELETE,
-            http::Method::HEAD => gloo_net::http::Method::HEAD,
-            http::Method::OPTIONS => gloo_net::http::Method::OPTIONS,
-            http::Method::CONNECT => gloo_net::http::Method::CONNECT,
-            http::Method::PATCH => gloo_net::http::Method::PATCH,
-            http::Method::TRACE => gloo_net::http::Method::TRACE,
-            _ => unimplemented!(),
-        });
+        Self::with_uri(Route::URI)
+    }
+
+    fn with_uri(uri: &str) -> Self {
         Self {
             _marker: PhantomData,
             query: NoQuery,
             body: NoBody,
-            builder,
+            head: RequestHead::new(Route::METHOD, uri),
+            priority: Route::PRIORITY,
+            canonical_query: false,
+            #[cfg(feature = "digest")]
+            digest: None,
         }
     }
 }
 
+impl<Route: self::Route> RequestBuilder<Route, NoQuery, NoBody> {
+    /// Start building a request with its query and body already given, for routes that need
+    /// more than [Request::with] does, e.g. extra headers.
+    pub fn new_with(
+        query: impl IntoQuery<Route::Query>,
+        body: impl IntoBody<Route::RequestBody>,
+    ) -> RequestBuilder<Route, Route::Query, Route::RequestBody> {
+        Self::new()
+            .with_query(query.into_query())
+            .with_body(body.into_body())
+    }
+}
+
+impl<Route: self::Route> Default for RequestBuilder<Route, NoQuery, NoBody> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Route: TenantScoped> RequestBuilder<Route, NoQuery, NoBody> {
+    /// Start building a request to `tenant`'s instance of the route.
+    ///
+    /// `tenant` is percent-encoded into the route's `{tenant}` segment, so e.g. a `/` in it
+    /// stays part of the segment. actix leaves `%2F` encoded in path parameters, so a [Tenant]
+    /// holding a `/` gets it as `%2F`.
+    pub fn for_tenant(tenant: &str) -> Self {
+        Self::with_uri(&Route::URI.replace(TENANT_PLACEHOLDER, &percent_encode(tenant)))
+    }
+}
+
 impl<Route: self::Route, Query, Body> RequestBuilder<Route, Query, Body> {
     /// Provide additional query parameters that are not required by the route definition.
     pub fn extra_query<'a, T, V>(mut self, params: T) -> Self
@@ -200,124 +1014,878 @@ impl<Route: self::Route, Query, Body> RequestBuilder<Route, Query, Body> {
         T: IntoIterator<Item = (&'a str, V)>,
         V: AsRef<str>,
     {
-        self.builder = self.builder.query(params);
+        let params: Vec<(&str, V)> = params.into_iter().collect();
+        let pairs: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(name, value)| (*name, value.as_ref()))
+            .collect();
+        let encoded =
+            serde_urlencoded::to_string(pairs).expect("string pairs can always be encoded");
+        self.head.append_query(&encoded);
+        self
+    }
+
+    /// Add a header that is not required by the route definition. Adding a header more than
+    /// once sends all of its values.
+    pub fn extra_header(mut self, name: http::HeaderName, value: http::HeaderValue) -> Self {
+        self.head.headers.append(name, value);
+        self
+    }
+
+    /// Add several headers with [RequestBuilder::extra_header].
+    pub fn extra_headers<T>(mut self, headers: T) -> Self
+    where
+        T: IntoIterator<Item = (http::HeaderName, http::HeaderValue)>,
+    {
+        for (name, value) in headers {
+            self.head.headers.append(name, value);
+        }
+        self
+    }
+
+    /// Request only part of the response body, as a range of byte offsets. Fails for a range
+    /// without any bytes in it, e.g. `5..5`, as a `Range` header can't ask for none.
+    ///
+    /// Check [Response::content_range] to see which part the server actually sent. To resume
+    /// an interrupted download, request the range starting at the number of bytes received.
+    pub fn range<R: RangeBounds<u64>>(mut self, range: R) -> Result<Self, EmptyRange> {
+        let first = match range.start_bound() {
+            Bound::Included(&first) => first,
+            Bound::Excluded(&first) => first.checked_add(1).ok_or(EmptyRange)?,
+            Bound::Unbounded => 0,
+        };
+        let last = match range.end_bound() {
+            Bound::Included(&last) => Some(last),
+            Bound::Excluded(&end) => Some(end.checked_sub(1).ok_or(EmptyRange)?),
+            Bound::Unbounded => None,
+        };
+        let value = match last {
+            Some(last) if last < first => return Err(EmptyRange),
+            Some(last) => format!("bytes={first}-{last}"),
+            None => format!("bytes={first}-"),
+        };
+        let value =
+            http::HeaderValue::try_from(value).expect("byte ranges are valid header values");
+        self.head.headers.insert(http::header::RANGE, value);
+        Ok(self)
+    }
+
+    /// Add a cookie to the request's `Cookie` header.
+    ///
+    /// The name and value are percent-encoded, as actix decodes them, so separators such as
+    /// `;` in them can't add cookies of their own. Browsers attach cookies themselves, so this
+    /// is for native transports.
+    pub fn cookie(mut self, name: &str, value: &str) -> Self {
+        let (name, value) = (percent_encode(name), percent_encode(value));
+        let cookie = match self.head.headers.get(http::header::COOKIE) {
+            Some(existing) => format!("{}; {name}={value}", existing.to_str().unwrap_or_default()),
+            None => format!("{name}={value}"),
+        };
+        if let Ok(cookie) = http::HeaderValue::try_from(cookie) {
+            self.head.headers.insert(http::header::COOKIE, cookie);
+        }
+        self
+    }
+
+    /// Send the query string in its [canonical form](canonicalize_query), e.g. so that
+    /// caches in front of the server see identical queries as the same.
+    pub fn canonical_query(mut self) -> Self {
+        self.canonical_query = true;
         self
     }
+
+    /// Queue the request as `priority` in a [schedule::Scheduler], instead of its route's
+    /// [Route::PRIORITY].
+    pub fn priority(mut self, priority: schedule::Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Attach a digest of the serialized body to the request, for servers (or proxies) that
+    /// check its integrity.
+    #[cfg(feature = "digest")]
+    pub fn content_digest(mut self, algorithm: digest::DigestAlgorithm) -> Self {
+        self.digest = Some(algorithm);
+        self
+    }
+}
+
+impl<T, F, Route: self::Route<Query = Query<T, F>>, Body> RequestBuilder<Route, NoQuery, Body> {
+    pub fn query(self, query: T) -> RequestBuilder<Route, Query<T, F>, Body> {
+        self.with_query(Query(query, PhantomData))
+    }
+
+    /// Build the query from `value` with a fallible conversion, for query types that enforce
+    /// invariants (e.g. bounds on a page size) in their [TryFrom] impl.
+    pub fn query_try<V>(
+        self,
+        value: V,
+    ) -> Result<RequestBuilder<Route, Query<T, F>, Body>, T::Error>
+    where
+        T: TryFrom<V>,
+    {
+        T::try_from(value).map(|query| self.query(query))
+    }
 }
 
-impl<T, Route: self::Route<Query = Query<T>>, Body> RequestBuilder<Route, NoQuery, Body> {
-    pub fn query(self, query: T) -> RequestBuilder<Route, Query<T>, Body> {
+impl<Route, Body> RequestBuilder<Route, NoQuery, Body> {
+    fn with_query<Query>(self, query: Query) -> RequestBuilder<Route, Query, Body> {
         RequestBuilder {
             _marker: self._marker,
-            query: Query(query),
+            query,
             body: self.body,
-            builder: self.builder,
+            head: self.head,
+            priority: self.priority,
+            canonical_query: self.canonical_query,
+            #[cfg(feature = "digest")]
+            digest: self.digest,
         }
     }
 }
 
-impl<T, Route: self::Route<RequestBody = JsonBody<T>>, Query> RequestBuilder<Route, Query, NoBody> {
-    pub fn json(self, json: T) -> RequestBuilder<Route, Query, JsonBody<T>> {
+impl<Route, Query> RequestBuilder<Route, Query, NoBody> {
+    fn with_body<Body>(self, body: Body) -> RequestBuilder<Route, Query, Body> {
         RequestBuilder {
             _marker: self._marker,
             query: self.query,
-            body: JsonBody(json),
-            builder: self.builder,
+            body,
+            head: self.head,
+            priority: self.priority,
+            canonical_query: self.canonical_query,
+            #[cfg(feature = "digest")]
+            digest: self.digest,
+        }
+    }
+}
+
+impl<T, Route: self::Route<RequestBody = JsonBody<T>>, Query> RequestBuilder<Route, Query, NoBody> {
+    pub fn json(self, json: T) -> RequestBuilder<Route, Query, JsonBody<T>> {
+        self.with_body(JsonBody(json))
+    }
+}
+
+impl<T, Route: self::Route<RequestBody = FormBody<T>>, Query> RequestBuilder<Route, Query, NoBody> {
+    pub fn form(self, form: T) -> RequestBuilder<Route, Query, FormBody<T>> {
+        self.with_body(FormBody(form))
+    }
+}
+
+/// An error receiving or decoding a response body.
+#[derive(Debug, Error)]
+pub enum BodyError<FormatError, TransportError> {
+    #[error("Failed to decode the body")]
+    Format(#[source] FormatError),
+    #[error("Failed to receive the body")]
+    Transport(#[source] TransportError),
+}
+
+/// The error returned by [RequestBuilder::range] for a range without any bytes in it.
+#[derive(Debug, Error)]
+#[error("The byte range is empty")]
+pub struct EmptyRange;
+
+/// A parsed `Content-Range` header for a byte range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentRange {
+    /// The byte offsets of the part of the resource that was sent, or `None` if the
+    /// requested range couldn't be satisfied.
+    pub range: Option<RangeInclusive<u64>>,
+    /// The length of the whole resource, if the server knows it.
+    pub complete_length: Option<u64>,
+}
+
+/// The error returned when parsing an invalid [ContentRange].
+#[derive(Debug, Error)]
+#[error("Invalid Content-Range header")]
+pub struct ParseContentRangeError;
+
+impl FromStr for ContentRange {
+    type Err = ParseContentRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (range, complete_length) = s
+            .trim()
+            .strip_prefix("bytes ")
+            .and_then(|s| s.split_once('/'))
+            .ok_or(ParseContentRangeError)?;
+
+        let range = match range {
+            "*" => None,
+            range => {
+                let (first, last) = range.split_once('-').ok_or(ParseContentRangeError)?;
+                let first = first.parse().map_err(|_| ParseContentRangeError)?;
+                let last = last.parse().map_err(|_| ParseContentRangeError)?;
+                Some(first..=last)
+            }
+        };
+        let complete_length = match complete_length {
+            "*" => None,
+            length => Some(length.parse().map_err(|_| ParseContentRangeError)?),
+        };
+
+        match (&range, complete_length) {
+            (None, None) => Err(ParseContentRangeError),
+            (Some(range), _) if range.start() > range.end() => Err(ParseContentRangeError),
+            (Some(range), Some(length)) if *range.end() >= length => Err(ParseContentRangeError),
+            _ => Ok(ContentRange {
+                range,
+                complete_length,
+            }),
         }
     }
 }
 
+/// The error returned by [Response::check_media_type] when a response has the wrong media type.
 #[derive(Debug, Error)]
-enum RequestBuildError<QueryError, BodyError> {
+#[error("Expected a response of type {expected}, but got {got:?}")]
+pub struct UnexpectedMediaType {
+    pub expected: &'static str,
+    pub got: Option<String>,
+    /// The response's [Response::request_id].
+    pub request_id: Option<String>,
+}
+
+/// The error returned by [Response::strict] when a response isn't in the expected format.
+#[derive(Debug, Error)]
+#[error("Response has unexpected content type {got:?}: {body_snippet:?}")]
+pub struct WrongContentType {
+    pub got: Option<String>,
+    /// The first few characters of the response body.
+    pub body_snippet: String,
+    /// The response's [Response::request_id].
+    pub request_id: Option<String>,
+}
+
+const BODY_SNIPPET_LENGTH: usize = 200;
+
+/// Strip any parameters, e.g. `; charset=utf-8`, from a `Content-Type` header value.
+fn media_type_essence(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+
+/// The error returned when a response body is longer than the limit set with
+/// [Response::limit] or a [limit::ResponseLimit].
+#[derive(Debug, Clone, Error)]
+#[error("Response body of {length} bytes exceeds the limit of {max} bytes")]
+pub struct ResponseTooLarge {
+    /// The body's length from its `Content-Length`, or how much of it had been read when it
+    /// passed the limit.
+    pub length: u64,
+    pub max: u64,
+    /// The response's [Response::request_id].
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum RequestBuildError<QueryError, BodyError> {
     #[error("Failed to build query")]
     QueryError(#[source] QueryError),
     #[error("Failed to build body")]
     BodyError(#[source] BodyError),
+    #[error("Failed to assemble the request")]
+    Http(#[source] http::Error),
 }
 
-impl<
-        Query: ApplyToRequestHead,
-        Body: ApplyToRequestBody,
-        Route: self::Route<Query = Query, RequestBody = Body>,
-    > RequestBuilder<Route, Query, Body>
-{
-    fn build(
-        self,
-    ) -> Result<
-        Request<Route>,
-        RequestBuildError<
-            <Route::Query as ApplyToRequestHead>::Error,
-            <Route::RequestBody as ApplyToRequestBody>::Error,
-        >,
-    > {
-        let builder = match self.query.apply(self.builder) {
-            Ok(builder) => builder,
-            Err(query_error) => return Err(RequestBuildError::QueryError(query_error)),
-        };
+/// Implemented by a route's query type for the builder state that provides it, so that building
+/// a request before giving it its query fails with an error saying so.
+#[diagnostic::on_unimplemented(
+    message = "the request for `{Route}` can't be built before it's given its query",
+    label = "the builder has `{State}`, but `{Route}` needs `{Self}`",
+    note = "call `.query(...)` or `.query_try(...)` before `.build()`"
+)]
+pub trait QueryProvided<State, Route> {
+    fn from_builder(state: State) -> Self;
+}
 
-        let request = match self.body.apply(builder) {
-            Ok(request) => request,
-            Err(body_error) => return Err(RequestBuildError::BodyError(body_error)),
-        };
+impl<T, Route> QueryProvided<T, Route> for T {
+    fn from_builder(state: T) -> Self {
+        state
+    }
+}
+
+/// Implemented by a route's request body type for the builder state that provides it, so that
+/// building a request before giving it its body fails with an error saying so.
+#[diagnostic::on_unimplemented(
+    message = "the request for `{Route}` can't be built before it's given its body",
+    label = "the builder has `{State}`, but `{Route}` needs `{Self}`",
+    note = "call the method for the route's body type, e.g. `.json(...)`, `.form(...)` or `.body(...)`, before `.build()`"
+)]
+pub trait BodyProvided<State, Route> {
+    fn from_builder(state: State) -> Self;
+}
+
+impl<T, Route> BodyProvided<T, Route> for T {
+    fn from_builder(state: T) -> Self {
+        state
+    }
+}
+
+// The bounds are on the methods rather than the impl, so that a missing step is reported with
+// the traits' messages instead of as a method whose bounds aren't satisfied.
+impl<Route: self::Route, Query, Body> RequestBuilder<Route, Query, Body> {
+    pub fn build(self) -> Result<Request<Route>, RouteBuildError<Route>>
+    where
+        Route::Query: QueryProvided<Query, Route>,
+        Route::RequestBody: BodyProvided<Body, Route>,
+    {
+        let mut head = self.head;
+        <Route::Query as QueryProvided<Query, Route>>::from_builder(self.query)
+            .apply(&mut head)
+            .map_err(RequestBuildError::QueryError)?;
+        if self.canonical_query {
+            head.query = canonicalize_query(&head.query);
+        }
+        if let Some(media_type) = Route::MEDIA_TYPE {
+            head.headers.insert(
+                http::header::ACCEPT,
+                http::HeaderValue::from_static(media_type),
+            );
+        }
+
+        let body = <Route::RequestBody as BodyProvided<Body, Route>>::from_builder(self.body)
+            .apply(&mut head)
+            .map_err(RequestBuildError::BodyError)?;
+        #[cfg(feature = "digest")]
+        if let Some(algorithm) = self.digest {
+            let (name, value) = algorithm.header(&body);
+            head.headers.insert(name, value);
+        }
+        if let Some(media_type) = Route::MEDIA_TYPE {
+            if head.headers.contains_key(http::header::CONTENT_TYPE) {
+                head.headers.insert(
+                    http::header::CONTENT_TYPE,
+                    http::HeaderValue::from_static(media_type),
+                );
+            }
+        }
+
+        let mut request = http::Request::builder()
+            .method(head.method.clone())
+            .uri(head.uri())
+            .body(body)
+            .map_err(RequestBuildError::Http)?;
+        *request.headers_mut() = head.headers;
+        request.extensions_mut().insert(RouteName(Route::NAME));
+        request
+            .extensions_mut()
+            .insert(SensitiveFields(Route::SENSITIVE_FIELDS));
+        request.extensions_mut().insert(self.priority);
 
         Ok(Request {
             _marker: PhantomData,
             request,
         })
     }
+
+    /// Build the request and render it as a curl command, see [Request::to_curl].
+    pub fn to_curl(self) -> Result<String, RouteBuildError<Route>>
+    where
+        Route::Query: QueryProvided<Query, Route>,
+        Route::RequestBody: BodyProvided<Body, Route>,
+    {
+        self.build().map(|request| request.to_curl())
+    }
 }
 
-struct Request<Route> {
-    _marker: PhantomData<*const Route>,
-    request: gloo_net::http::Request,
+/// The error building a request for `Route` fails with.
+type RouteBuildError<Route> = RequestBuildError<
+    <<Route as self::Route>::Query as ApplyToRequestHead>::Error,
+    <<Route as self::Route>::RequestBody as ApplyToRequestBody>::Error,
+>;
+
+/// The [Route::NAME] of the route a request was built for, stored in the request's extensions so
+/// that transports can tell routes apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteName(pub &'static str);
+
+/// The [Route::SENSITIVE_FIELDS] of the route a request was built for, stored in the request's
+/// extensions alongside its [RouteName].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensitiveFields(pub &'static [&'static str]);
+
+/// An HTTP client that typed requests can be sent with.
+///
+/// The `gloo` feature provides one for browsers, [gloo::Gloo], and the `hyper` feature one for
+/// native callers, [hyper::Hyper].
+pub trait ClientTransport {
+    type Response: TransportResponse;
+    type Error;
+
+    fn send(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>>;
+}
+
+impl<T: ClientTransport> ClientTransport for &T {
+    type Response = T::Response;
+    type Error = T::Error;
+
+    fn send(
+        &self,
+        request: http::Request<Bytes>,
+    ) -> impl Future<Output = Result<Self::Response, Self::Error>> {
+        (**self).send(request)
+    }
+}
+
+/// A response received by a [ClientTransport], before its body has been read.
+pub trait TransportResponse {
+    /// The error returned when reading the body fails.
+    type Error;
+
+    fn status(&self) -> http::StatusCode;
+
+    fn headers(&self) -> &http::HeaderMap;
+
+    fn bytes(self) -> impl Future<Output = Result<Bytes, Self::Error>>;
+
+    /// The body as it arrives, for transports that can stream it. By default it's read whole
+    /// and given as a single chunk.
+    fn chunks(self) -> impl futures_util::Stream<Item = Result<Bytes, Self::Error>>
+    where
+        Self: Sized,
+    {
+        futures_util::stream::once(self.bytes())
+    }
+}
+
+pub struct Request<Route> {
+    _marker: PhantomData<fn() -> Route>,
+    request: http::Request<Bytes>,
+}
+
+/// The error returned by [Request::check_cookies] when a request is missing one of its route's
+/// [Route::REQUIRED_COOKIES].
+#[derive(Debug, Error)]
+#[error("Route {route} requires the cookie {cookie}, but the request doesn't have it")]
+pub struct MissingCookie {
+    pub route: &'static str,
+    pub cookie: &'static str,
 }
 
 impl<Route: self::Route> Request<Route> {
-    pub async fn send(self) -> Result<Response<Route>, gloo_net::Error> {
-        self.request.send().await.map(|response| Response {
+    /// Build a request from its query and body in one step, e.g.
+    /// `Request::<GetOrders>::with(OrdersQuery { page: 2 }, ())`.
+    pub fn with(
+        query: impl IntoQuery<Route::Query>,
+        body: impl IntoBody<Route::RequestBody>,
+    ) -> Result<Self, RouteBuildError<Route>> {
+        RequestBuilder::new_with(query, body).build()
+    }
+
+    /// Fail if the request's `Cookie` header doesn't carry all of the route's
+    /// [Route::REQUIRED_COOKIES], rather than finding out from the server.
+    ///
+    /// Only useful with native transports, as browsers add cookies when the request is sent.
+    pub fn check_cookies(self) -> Result<Self, MissingCookie> {
+        let sent: Vec<&str> = self
+            .request
+            .headers()
+            .get_all(http::header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|cookie| cookie.split_once('='))
+            .map(|(name, _)| name.trim())
+            .collect();
+        match Route::REQUIRED_COOKIES
+            .iter()
+            .find(|required| !sent.contains(required))
+        {
+            Some(cookie) => Err(MissingCookie {
+                route: Route::NAME,
+                cookie,
+            }),
+            None => Ok(self),
+        }
+    }
+
+    /// Send the request with `transport`.
+    pub async fn send_with<T: ClientTransport>(
+        self,
+        transport: &T,
+    ) -> Result<Response<Route, T::Response>, T::Error> {
+        transport.send(self.request).await.map(|response| Response {
             _marker: PhantomData,
             response,
         })
     }
+
+    /// Send the request with the browser's `fetch`.
+    #[cfg(feature = "gloo")]
+    pub async fn send(self) -> Result<Response<Route, gloo::GlooResponse>, gloo_net::Error> {
+        self.send_with(&gloo::Gloo).await
+    }
+
+    /// A key identifying what the request asks for, its method, path and canonical query, e.g.
+    /// for caching responses or deduplicating requests.
+    pub fn cache_key(&self) -> String {
+        canonical_uri(self.request.method(), self.request.uri())
+    }
+
+    /// Render the request as a curl command that can be pasted into a shell, e.g. for a bug
+    /// report.
+    ///
+    /// Requests for relative URIs are sent to `$BASE_URL`, which needs setting to the server
+    /// first. Bodies that aren't UTF-8 are written with bash's `$'...'` quoting.
+    pub fn to_curl(&self) -> String {
+        let uri = self.request.uri();
+        let url = if uri.scheme().is_some() {
+            shell_quote(&uri.to_string())
+        } else {
+            format!("\"$BASE_URL\"{}", shell_quote(&uri.to_string()))
+        };
+        let mut curl = format!("curl -X {} {url}", self.request.method());
+        for (name, value) in self.request.headers() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            curl.push_str(&format!(
+                " \\\n  -H {}",
+                shell_quote(&format!("{name}: {value}"))
+            ));
+        }
+        let body = self.request.body();
+        if !body.is_empty() {
+            let body = match std::str::from_utf8(body) {
+                Ok(body) => format!("--data-raw {}", shell_quote(body)),
+                Err(_) => {
+                    let escaped: String =
+                        body.iter().map(|byte| format!("\\x{byte:02x}")).collect();
+                    format!("--data-binary $'{escaped}'")
+                }
+            };
+            curl.push_str(&format!(" \\\n  {body}"));
+        }
+        curl
+    }
+
+    /// A copy of what will be sent, e.g. for tests to check what a builder produced.
+    pub fn parts(&self) -> RequestParts {
+        RequestParts {
+            method: self.request.method().clone(),
+            uri: self.request.uri().clone(),
+            headers: self.request.headers().clone(),
+            body: self.request.body().clone(),
+        }
+    }
+
+    pub fn into_untyped_request(self) -> http::Request<Bytes> {
+        self.request
+    }
+}
+
+/// The parts of a built [Request], from [Request::parts].
+#[derive(Debug, Clone)]
+pub struct RequestParts {
+    pub method: http::Method,
+    /// The full URI, including the query string.
+    pub uri: http::Uri,
+    pub headers: http::HeaderMap,
+    pub body: Bytes,
+}
+
+/// Quote `s` as a single shell word.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Rewrite a URL encoded query string so that queries with the same pairs, e.g. serialized
+/// from differently ordered maps, come out the same.
+///
+/// Pairs are sorted by key, keeping the order of repeated keys, and re-encoded consistently.
+/// Query strings that can't be decoded, including ones escaping bytes that aren't UTF-8, are
+/// returned unchanged.
+pub fn canonicalize_query(query: &str) -> String {
+    // Decoding replaces bytes that aren't UTF-8, which would give different queries the same
+    // canonical form.
+    if query
+        .split('&')
+        .any(|pair| std::str::from_utf8(&percent_decode_bytes(pair)).is_err())
+    {
+        return query.to_owned();
+    }
+    let Ok(mut pairs) = serde_urlencoded::from_str::<Vec<(String, String)>>(query) else {
+        return query.to_owned();
+    };
+    pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    serde_urlencoded::to_string(pairs).unwrap_or_else(|_| query.to_owned())
+}
+
+/// `method` and `uri`'s path and canonical query, as `METHOD /path?query`.
+pub(crate) fn canonical_uri(method: &http::Method, uri: &http::Uri) -> String {
+    match uri.query().map(canonicalize_query) {
+        Some(query) if !query.is_empty() => format!("{method} {}?{query}", uri.path()),
+        _ => format!("{method} {}", uri.path()),
+    }
+}
+
+pub struct Response<Route, R> {
+    _marker: PhantomData<fn() -> Route>,
+    response: R,
+}
+
+impl<Route: self::Route, R: TransportResponse> Response<Route, R> {
+    pub fn status(&self) -> http::StatusCode {
+        self.response.status()
+    }
+
+    pub fn ok(&self) -> bool {
+        self.response.status().is_success()
+    }
+
+    pub fn headers(&self) -> &http::HeaderMap {
+        self.response.headers()
+    }
+
+    fn header(&self, name: http::header::HeaderName) -> Option<&str> {
+        self.headers().get(name)?.to_str().ok()
+    }
+
+    /// The ID the server handled the request under, from the `X-Request-Id` header. Errors
+    /// about a response carry it too, for correlating the failure with the server's logs.
+    pub fn request_id(&self) -> Option<&str> {
+        self.header(http::header::HeaderName::from_static(
+            request_id::REQUEST_ID_HEADER,
+        ))
+    }
+
+    /// Whether the server advertised support for byte range requests with `Accept-Ranges`.
+    pub fn accepts_ranges(&self) -> bool {
+        self.header(http::header::ACCEPT_RANGES) == Some("bytes")
+    }
+
+    /// The part of the resource this response contains, according to its `Content-Range`
+    /// header, if it has a valid one.
+    pub fn content_range(&self) -> Option<ContentRange> {
+        self.header(http::header::CONTENT_RANGE)?.parse().ok()
+    }
+
+    /// The media type of the body according to the `Content-Type` header, without any
+    /// parameters such as `charset`.
+    pub fn content_type(&self) -> Option<String> {
+        let content_type = self.header(http::header::CONTENT_TYPE)?;
+        Some(media_type_essence(content_type).to_owned())
+    }
+
+    /// Fail if the route declares a [Route::MEDIA_TYPE] and the response was sent with a
+    /// different one.
+    pub fn check_media_type(self) -> Result<Self, UnexpectedMediaType> {
+        match Route::MEDIA_TYPE {
+            Some(expected) => {
+                let got = self.content_type();
+                if got.as_deref() == Some(expected) {
+                    Ok(self)
+                } else {
+                    Err(UnexpectedMediaType {
+                        expected,
+                        got,
+                        request_id: self.request_id().map(str::to_owned),
+                    })
+                }
+            }
+            None => Ok(self),
+        }
+    }
+
+    /// Fail if the response's `Content-Type` doesn't match the route's [Route::MEDIA_TYPE] or,
+    /// if it doesn't declare one, its response body format.
+    ///
+    /// This catches e.g. HTML error pages from proxies before they reach a decoder, and the
+    /// error includes the start of the unexpected body to help tell what it was.
+    pub async fn strict(self) -> Result<Self, WrongContentType>
+    where
+        Route::ResponseBody: ContentType,
+    {
+        let got = self.content_type();
+        let request_id = self.request_id().map(str::to_owned);
+        let matches = match (Route::MEDIA_TYPE, got.as_deref()) {
+            (Some(expected), got) => got == Some(expected),
+            (None, Some(got)) => <Route::ResponseBody as ContentType>::matches(got),
+            (None, None) => <Route::ResponseBody as ContentType>::matches(""),
+        };
+        if matches {
+            return Ok(self);
+        }
+
+        let body_snippet = match self.response.bytes().await {
+            Ok(body) => String::from_utf8_lossy(&body)
+                .chars()
+                .take(BODY_SNIPPET_LENGTH)
+                .collect(),
+            Err(_) => String::new(),
+        };
+        Err(WrongContentType {
+            got,
+            body_snippet,
+            request_id,
+        })
+    }
+
+    /// The length of the body according to the `Content-Length` header, if there is one.
+    pub fn content_length(&self) -> Option<u64> {
+        self.header(http::header::CONTENT_LENGTH)
+            .and_then(|length| length.parse().ok())
+    }
+
+    /// Fail reading the body once more than `max` bytes of it have arrived, so that it's never
+    /// buffered whole, see [limit]. A `Content-Length` over `max` fails at once.
+    pub fn limit(
+        self,
+        max: u64,
+    ) -> Result<Response<Route, limit::LimitedResponse<R>>, ResponseTooLarge> {
+        let response = limit::LimitedResponse::new(self.response, max);
+        match response.content_length() {
+            Some(length) if length > max => Err(response.too_large(length)),
+            _ => Ok(Response {
+                _marker: PhantomData,
+                response,
+            }),
+        }
+    }
+
+    /// Read the whole body without decoding it.
+    async fn body(self) -> Result<Bytes, R::Error> {
+        self.response.bytes().await
+    }
+
+    pub fn into_untyped_response(self) -> R {
+        self.response
+    }
+
+    /// Read the whole body, so that the response can be cloned, e.g. to decode it and also
+    /// keep it raw.
+    pub async fn buffer(self) -> Result<Response<Route, BufferedResponse>, R::Error> {
+        let status = self.response.status();
+        let headers = self.response.headers().clone();
+        let body = self.response.bytes().await?;
+        Ok(Response {
+            _marker: PhantomData,
+            response: BufferedResponse {
+                status,
+                headers,
+                body,
+            },
+        })
+    }
+
+    /// Read the whole body and split the response in two, see [Response::buffer].
+    pub async fn tee(
+        self,
+    ) -> Result<
+        (
+            Response<Route, BufferedResponse>,
+            Response<Route, BufferedResponse>,
+        ),
+        R::Error,
+    > {
+        let response = self.buffer().await?;
+        Ok((response.clone(), response))
+    }
+}
+
+impl<Route> Clone for Response<Route, BufferedResponse> {
+    fn clone(&self) -> Self {
+        Response {
+            _marker: PhantomData,
+            response: self.response.clone(),
+        }
+    }
+}
+
+impl<Route> Response<Route, BufferedResponse> {
+    /// The raw body, which is already read.
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.response.body
+    }
+}
+
+/// A response whose body has been read into memory, from [Response::buffer].
+#[derive(Debug, Clone)]
+pub struct BufferedResponse {
+    status: http::StatusCode,
+    headers: http::HeaderMap,
+    body: Bytes,
+}
+
+impl TransportResponse for BufferedResponse {
+    type Error = Infallible;
+
+    fn status(&self) -> http::StatusCode {
+        self.status
+    }
+
+    fn headers(&self) -> &http::HeaderMap {
+        &self.headers
+    }
+
+    async fn bytes(self) -> Result<Bytes, Self::Error> {
+        Ok(self.body)
+    }
+}
+
+/// Implemented by response body types that [Response::json] can decode.
+pub trait JsonResponse {
+    type Output;
+
+    fn decode(body: &[u8]) -> Result<Self::Output, serde_json::Error>;
+}
+
+impl<T: for<'de> serde::Deserialize<'de>> JsonResponse for JsonBody<T> {
+    type Output = T;
+
+    fn decode(body: &[u8]) -> Result<Self::Output, serde_json::Error> {
+        serde_json::from_slice(body)
+    }
+}
+
+impl<Route: self::Route, R: TransportResponse> Response<Route, R>
+where
+    Route::ResponseBody: JsonResponse,
+{
+    pub async fn json(
+        self,
+    ) -> Result<<Route::ResponseBody as JsonResponse>::Output, BodyError<serde_json::Error, R::Error>>
+    {
+        let body = self.body().await.map_err(BodyError::Transport)?;
+        <Route::ResponseBody as JsonResponse>::decode(&body).map_err(BodyError::Format)
+    }
+}
+
+impl<T, Route: self::Route<ResponseBody = JsonBody<T>>, R: TransportResponse> Response<Route, R> {
+    /// Read the body and keep it, so that it can be deserialized into types that borrow from
+    /// it instead of allocating their strings, e.g. for large lists.
+    pub async fn json_borrowed(self) -> Result<BorrowedJson<Route>, R::Error> {
+        let body = self.body().await?;
+        Ok(BorrowedJson {
+            _marker: PhantomData,
+            body,
+        })
+    }
 }
 
-struct Response<Route> {
-    _marker: PhantomData<*const Route>,
-    response: gloo_net::http::Response,
+/// A JSON response body returned by [Response::json_borrowed], which values deserialized from
+/// it can borrow from.
+pub struct BorrowedJson<Route> {
+    _marker: PhantomData<fn() -> Route>,
+    body: Bytes,
 }
 
-impl<Route: self::Route> Response<Route> {
-    pub fn status(&self) -> u16 {
-        self.response.status()
-    }
-
-    pub fn ok(&self) -> bool {
-        self.response.ok()
-    }
-
-    pub fn headers(&self) -> gloo_net::http::Headers {
-        self.response.headers()
-    }
-
-    pub fn body_used(&self) -> bool {
-        self.response.body_used()
-    }
-
-    pub fn into_untyped_response(self) -> gloo_net::http::Response {
-        self.response
+impl<Route> BorrowedJson<Route> {
+    /// Deserialize the body as `T`, a borrowing version of the route's response type, with
+    /// `&str` or `#[serde(borrow)] Cow<str>` fields pointing into the body.
+    ///
+    /// Strings with escapes in them can't be borrowed, so use `Cow` unless they never do.
+    pub fn get<'a, T: serde::Deserialize<'a>>(&'a self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
     }
-}
 
-impl<T: for<'de> serde::Deserialize<'de>, Route: self::Route<ResponseBody = JsonBody<T>>>
-    Response<Route>
-{
-    pub async fn json(&self) -> Result<T, gloo_net::Error> {
-        self.response.json().await
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.body
     }
 }
 
 pub struct Handled<Route, F> {
-    _marker: PhantomData<*const Route>,
+    _marker: PhantomData<fn() -> Route>,
     handler: F,
 }
 
@@ -334,6 +1902,95 @@ where
     }
 }
 
+impl<Route, F> Handled<Route, F> {
+    /// The actix route that calls the handler, e.g. for a [registration::Handles] impl.
+    pub fn into_route<Args>(self) -> actix_web::Route
+    where
+        F: actix_web::Handler<Args>,
+        Args: actix_web::FromRequest + 'static,
+        F::Output: actix_web::Responder + 'static,
+    {
+        actix_web::web::route().to(self.handler)
+    }
+}
+
+/// The scopes granted to the token that authenticated the current request.
+///
+/// Your authentication middleware should insert this into the request's extensions so that
+/// routes with [Route::REQUIRED_SCOPES] can check it. Requests to those routes without all of
+/// their scopes are answered with `403 Forbidden`.
+pub struct GrantedScopes(pub Vec<String>);
+
+/// Middleware on a route's resource that turns away requests whose [GrantedScopes] don't
+/// include all of its [Route::REQUIRED_SCOPES].
+struct ScopeCheck(&'static [&'static str]);
+
+impl<S, B: 'static> actix_web::dev::Transform<S, actix_web::dev::ServiceRequest> for ScopeCheck
+where
+    S: actix_web::dev::Service<
+            actix_web::dev::ServiceRequest,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<actix_web::body::EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = ScopeCheckMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ScopeCheckMiddleware {
+            required: self.0,
+            service: std::rc::Rc::new(service),
+        }))
+    }
+}
+
+struct ScopeCheckMiddleware<S> {
+    required: &'static [&'static str],
+    service: std::rc::Rc<S>,
+}
+
+impl<S, B: 'static> actix_web::dev::Service<actix_web::dev::ServiceRequest>
+    for ScopeCheckMiddleware<S>
+where
+    S: actix_web::dev::Service<
+            actix_web::dev::ServiceRequest,
+            Response = actix_web::dev::ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<actix_web::body::EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = std::pin::Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: actix_web::dev::ServiceRequest) -> Self::Future {
+        let permitted = {
+            let extensions = actix_web::HttpMessage::extensions(&req);
+            let granted = extensions
+                .get::<GrantedScopes>()
+                .map_or(&[][..], |scopes| &scopes.0);
+            self.required
+                .iter()
+                .all(|required| granted.iter().any(|scope| scope == required))
+        };
+        if permitted {
+            let service = std::rc::Rc::clone(&self.service);
+            return Box::pin(async move {
+                service
+                    .call(req)
+                    .await
+                    .map(actix_web::dev::ServiceResponse::map_into_left_body)
+            });
+        }
+        let response = actix_web::HttpResponse::Forbidden().finish();
+        Box::pin(ready(Ok(req.into_response(response).map_into_right_body())))
+    }
+}
+
 pub trait Router {
     fn app_data<U: 'static>(self, ext: U) -> Self;
     fn configure<F: FnOnce(&mut actix_web::web::ServiceConfig)>(self, f: F) -> Self;
@@ -349,6 +2006,289 @@ pub trait Router {
         U::InitError: std::fmt::Debug;
     fn route(self, path: &str, route: actix_web::Route) -> Self;
     fn service<F: actix_web::dev::HttpServiceFactory + 'static>(self, factory: F) -> Self;
+
+    /// Register the route produced by `route` at the typed route's URI, guarded by its method,
+    /// and by `HEAD` too if the route sets [Route::HEAD].
+    ///
+    /// `route` shouldn't add a method guard of its own, e.g. use `web::route().to(handler)`.
+    /// For routes using [TrailingSlash::Either] it is registered a second time at the URI
+    /// with a trailing slash.
+    fn typed_route<Route: self::Route, F: Fn() -> actix_web::Route>(self, route: F) -> Self
+    where
+        Self: Sized,
+    {
+        register_typed_route::<Self, Route, F>(self, route, Route::HEAD)
+    }
+
+    /// Like [Router::typed_route], but `GET` routes respond to `HEAD` requests whether or not
+    /// they set [Route::HEAD].
+    fn typed_route_with_head<Route: self::Route, F: Fn() -> actix_web::Route>(
+        self,
+        route: F,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        register_typed_route::<Self, Route, F>(self, route, true)
+    }
+
+    /// Register `handler` for its route, like [Router::typed_route].
+    fn typed_handler<Route, P, H>(self, handler: H) -> Self
+    where
+        Self: Sized,
+        Route: self::Route + 'static,
+        Route::Query: typed::ExtractQuery,
+        Route::RequestBody: typed::ExtractBody,
+        P: serde::de::DeserializeOwned + 'static,
+        H: typed::RouteHandler<Route, P>,
+    {
+        let handler = std::rc::Rc::new(handler);
+        self.typed_route::<Route, _>(|| typed::handler_route(std::rc::Rc::clone(&handler)))
+    }
+
+    /// Register a handler for `Route` that responds with its example response, e.g. to serve it
+    /// to clients before its real handler is written.
+    fn example_handler<Route>(self) -> Self
+    where
+        Self: Sized,
+        Route: examples::RouteExamples,
+        Route::ResponseBody: examples::ExampleResponse,
+    {
+        self.typed_route::<Route, _>(examples::example_route::<Route>)
+    }
+
+    /// Register an `OPTIONS` handler at each URI in `Catalog` that reports the methods of the
+    /// routes at that URI in its `Allow` header.
+    fn catalog_options<Catalog: RouteCatalog>(self) -> Self
+    where
+        Self: Sized,
+    {
+        let mut uris: Vec<&'static str> = Catalog::ALL.iter().map(|id| id.uri()).collect();
+        uris.sort_unstable();
+        uris.dedup();
+
+        uris.into_iter().fold(self, |router, uri| {
+            let mut allow: Vec<String> = Catalog::ALL
+                .iter()
+                .filter(|id| id.uri() == uri)
+                .map(|id| id.method().to_string())
+                .collect();
+            allow.push(http::Method::OPTIONS.to_string());
+            let allow = allow.join(", ");
+
+            router.route(
+                uri,
+                actix_web::web::route()
+                    .method(actix_web::http::Method::OPTIONS)
+                    .to(move || {
+                        let allow = allow.clone();
+                        async move {
+                            actix_web::HttpResponse::NoContent()
+                                .insert_header((actix_web::http::header::ALLOW, allow))
+                                .finish()
+                        }
+                    }),
+            )
+        })
+    }
+}
+
+fn register_typed_route<R: Router, Route: self::Route, F: Fn() -> actix_web::Route>(
+    router: R,
+    route: F,
+    with_head: bool,
+) -> R {
+    let router = if Route::JSON_LIMIT.is_some()
+        || Route::JSON_ERROR_HANDLER.is_some()
+        || Route::QUERY_ERROR_HANDLER.is_some()
+        || Route::FEATURE_FLAG.is_some()
+        || !Route::REQUIRED_SCOPES.is_empty()
+        || Route::AUDITED
+    {
+        register_configured_route::<R, Route, _>(router, &route, with_head)
+    } else {
+        register_plain_route::<R, Route, _>(router, &route, with_head)
+    };
+    match Route::ALIAS {
+        Some(alias) => register_alias::<R, Route, _>(router, alias, &route, with_head),
+        None => router,
+    }
+}
+
+fn register_plain_route<R: Router, Route: self::Route, F: Fn() -> actix_web::Route>(
+    router: R,
+    route: F,
+    with_head: bool,
+) -> R {
+    let route = || {
+        let route = if with_head && Route::METHOD == http::Method::GET {
+            route()
+                .guard(actix_web::guard::Any(actix_web::guard::Get()).or(actix_web::guard::Head()))
+        } else {
+            route().method(Route::METHOD.to_actix())
+        };
+        Route::GUARDS.iter().fold(route, |route, &guard| {
+            route.guard(actix_web::guard::fn_guard(guard))
+        })
+    };
+    let router = router.route(Route::URI, route());
+    match Route::TRAILING_SLASH {
+        TrailingSlash::Either if Route::URI != "/" => {
+            router.route(&format!("{}/", Route::URI), route())
+        }
+        _ => router,
+    }
+}
+
+/// Register a route that overrides the app's extractor configuration, is behind a feature
+/// flag, requires scopes or is audited.
+fn register_configured_route<R: Router, Route: self::Route, F: Fn() -> actix_web::Route>(
+    router: R,
+    route: F,
+    with_head: bool,
+) -> R {
+    let resource = |router: R, uri: &str| {
+        register_route_resource::<R, Route>(
+            router,
+            uri,
+            with_head,
+            route(),
+            actix_web::middleware::DefaultHeaders::new(),
+        )
+    };
+    let router = resource(router, Route::URI);
+    match Route::TRAILING_SLASH {
+        TrailingSlash::Either if Route::URI != "/" => resource(router, &format!("{}/", Route::URI)),
+        _ => router,
+    }
+}
+
+/// Register a resource at `uri` serving `route` with the route's configuration and middleware,
+/// so that they apply wherever the route is served, e.g. at its [Route::ALIAS] too. `headers`
+/// are added to its responses.
+///
+/// actix only lets resources hold route-local app data and middleware, so the route gets a
+/// resource of its own. The method guard goes on the resource, so that requests with other methods fall
+/// through to the other resources at the URI.
+fn register_route_resource<R: Router, Route: self::Route>(
+    router: R,
+    uri: &str,
+    with_head: bool,
+    route: actix_web::Route,
+    headers: actix_web::middleware::DefaultHeaders,
+) -> R {
+    router.service(
+        guarded_resource::<Route>(uri)
+            .guard(method_guard::<Route>(with_head))
+            .app_data(json_config::<Route>())
+            .app_data(query_config::<Route>())
+            .route(route)
+            .wrap(ScopeCheck(Route::REQUIRED_SCOPES))
+            .wrap(flags::FlagGuard(Route::FEATURE_FLAG))
+            .wrap(headers)
+            .wrap(audit::AuditMarker(audit::AuditedRoute::of::<Route>())),
+    )
+}
+
+/// A resource at `uri` with the route's [Route::GUARDS].
+fn guarded_resource<Route: self::Route>(uri: &str) -> actix_web::Resource {
+    Route::GUARDS
+        .iter()
+        .fold(actix_web::web::resource(uri), |resource, &guard| {
+            resource.guard(actix_web::guard::fn_guard(guard))
+        })
+}
+
+fn method_guard<Route: self::Route>(with_head: bool) -> actix_web::guard::AnyGuard {
+    if with_head && Route::METHOD == http::Method::GET {
+        actix_web::guard::Any(actix_web::guard::Get()).or(actix_web::guard::Head())
+    } else {
+        actix_web::guard::Any(actix_web::guard::Method(Route::METHOD.to_actix()))
+    }
+}
+
+fn json_config<Route: self::Route>() -> actix_web::web::JsonConfig {
+    let mut config = actix_web::web::JsonConfig::default();
+    if let Some(limit) = Route::JSON_LIMIT {
+        config = config.limit(limit);
+    }
+    if let Some(handler) = Route::JSON_ERROR_HANDLER {
+        config = config.error_handler(handler);
+    }
+    config
+}
+
+fn query_config<Route: self::Route>() -> actix_web::web::QueryConfig {
+    let mut config = actix_web::web::QueryConfig::default();
+    if let Some(handler) = Route::QUERY_ERROR_HANDLER {
+        config = config.error_handler(handler);
+    }
+    config
+}
+
+/// Register a route at its deprecated [Route::ALIAS], either handled there or redirecting to
+/// the route's URI. Either way the alias is behind the same middleware as the route.
+fn register_alias<R: Router, Route: self::Route, F: Fn() -> actix_web::Route>(
+    router: R,
+    alias: &'static str,
+    route: F,
+    with_head: bool,
+) -> R {
+    let route = || {
+        if Route::ALIAS_REDIRECT {
+            actix_web::web::route().to(|req: actix_web::HttpRequest| async move {
+                let mut location = alias_redirect_location::<Route>(&req)?;
+                if !req.query_string().is_empty() {
+                    location = format!("{location}?{}", req.query_string());
+                }
+                Ok::<_, actix_web::Error>(
+                    actix_web::HttpResponse::PermanentRedirect()
+                        .insert_header((actix_web::http::header::LOCATION, location))
+                        .finish(),
+                )
+            })
+        } else {
+            route()
+        }
+    };
+    let resource = |router: R, uri: &str| {
+        register_route_resource::<R, Route>(
+            router,
+            uri,
+            with_head,
+            route(),
+            actix_web::middleware::DefaultHeaders::new().add(("Deprecation", "true")),
+        )
+    };
+    let router = resource(router, alias);
+    match Route::TRAILING_SLASH {
+        TrailingSlash::Either if alias != "/" => resource(router, &format!("{alias}/")),
+        _ => router,
+    }
+}
+
+/// The route's URI with the path segments of a request to its alias filled in.
+///
+/// actix leaves `%`, `/` and `+` percent-encoded in path segments, so they're decoded first to
+/// be encoded only once by [links::fill_uri].
+fn alias_redirect_location<Route: self::Route>(
+    req: &actix_web::HttpRequest,
+) -> Result<String, actix_web::Error> {
+    let segments: Vec<(&str, String)> = req
+        .match_info()
+        .iter()
+        .map(|(name, value)| (name, percent_decode(value)))
+        .collect();
+    links::fill_uri(Route::URI, |name| {
+        segments
+            .iter()
+            .find(|(segment, _)| *segment == name)
+            .map(|(_, value)| value.as_str())
+    })
+    .map_err(|err| match err {
+        links::LinkError::InvalidPathParam { .. } => actix_web::error::ErrorBadRequest(err),
+        err => actix_web::error::ErrorInternalServerError(err),
+    })
 }
 
 impl<
@@ -431,6 +2371,43 @@ impl<
     }
 }
 
+/// The error returned when parsing a `RouteId` generated by [routes] from an unknown name.
+#[derive(Debug, Error)]
+#[error("Unknown route name {0:?}")]
+pub struct ParseRouteIdError(pub String);
+
+/// A catalog of a module's routes, implemented by the `RouteId` enum that [routes] generates.
+pub trait RouteCatalog: Copy + Eq + std::hash::Hash + std::fmt::Display + 'static {
+    /// Every route in the catalog.
+    const ALL: &'static [Self];
+
+    fn name(self) -> &'static str;
+    fn method(self) -> http::Method;
+    fn uri(self) -> &'static str;
+    fn required_scopes(self) -> &'static [&'static str];
+    fn audited(self) -> bool;
+    fn feature_flag(self) -> Option<&'static str>;
+    /// The deprecated URI the route is also served at.
+    fn alias(self) -> Option<&'static str>;
+    fn sensitive_fields(self) -> &'static [&'static str];
+    /// The route as a trait object, for the parts of it that don't depend on its types.
+    fn erased(self) -> &'static dyn erased::ErasedRoute;
+}
+
+/// Build a CORS configuration that allows the methods used by the routes in `Catalog`.
+///
+/// `actix_cors` applies one policy to everything it wraps, so wrap the scope holding the
+/// catalog's routes with it. Origins and headers still need to be configured on the result.
+#[cfg(feature = "cors")]
+pub fn cors<Catalog: RouteCatalog>() -> actix_cors::Cors {
+    let mut methods: Vec<http::Method> = Catalog::ALL.iter().map(|id| id.method()).collect();
+    methods.push(http::Method::OPTIONS);
+    methods.sort_unstable_by(|a, b| a.as_str().cmp(b.as_str()));
+    methods.dedup();
+
+    actix_cors::Cors::default().allowed_methods(methods.iter().map(compat::ToActix::to_actix))
+}
+
 pub trait Module {
     fn register<R: Router>(self, router: R) -> R;
 }
@@ -441,31 +2418,167 @@ macro_rules! routes {
         module: type $module:ident;
         $(
             outer_routes: type $outer_routes_type:ident [
-                $(route($($outer_route:tt)*)),*
+                $(route($method:expr, $uri_part:expr => type $type_name:ident (query: $query_type:ty, body: $body_type:ty $(, $option:ident: $option_value:tt)* $(,)?) -> $response_type:ty)),*
                 $(,)?
             ];
         )?
         scope: $uri:expr;
+        $(trailing_slash: $trailing_slash:ident;)?
         inner_items: type $inner_routes_type:ident [
             $(($($inner_item:tt)*)),*
             $(,)?
         ];
     } => {
         $(
-            outer_routes_typedef!{$outer_routes_type { $($($outer_route)*),*}}
+            outer_routes_typedef!{$outer_routes_type { $($method, $uri_part => type $type_name (query: $query_type, body: $body_type $(, $option: $option_value)*) -> $response_type),*}}
         )?
 
         pub struct $module;
 
-        impl $crate::Module for $module {
+        impl $crate::Module for $module
+        where
+            $($(for<'a> $module: $crate::registration::Handles<$type_name>,)*)?
+        {
             fn register<R: $crate::Router>(self, router: R) -> R {
+                $($(
+                    let router = router.typed_route::<$type_name, _>(
+                        <$module as $crate::registration::Handles<$type_name>>::route,
+                    );
+                )*)?
+                router.catalog_options::<RouteId>()
+            }
+        }
+
+        /// Identifies one of the routes defined in this module.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum RouteId {
+            $($($type_name,)*)?
+        }
+
+        impl RouteId {
+            pub const ALL: &'static [RouteId] = &[$($(RouteId::$type_name,)*)?];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::NAME,)*)?
+                }
+            }
+
+            pub fn method(self) -> $crate::Method {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::METHOD,)*)?
+                }
+            }
+
+            pub fn uri(self) -> &'static str {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::URI,)*)?
+                }
+            }
+
+            pub fn required_scopes(self) -> &'static [&'static str] {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::REQUIRED_SCOPES,)*)?
+                }
+            }
+
+            pub fn audited(self) -> bool {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::AUDITED,)*)?
+                }
+            }
+
+            pub fn feature_flag(self) -> Option<&'static str> {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::FEATURE_FLAG,)*)?
+                }
+            }
+
+            pub fn alias(self) -> Option<&'static str> {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::ALIAS,)*)?
+                }
+            }
+
+            pub fn sensitive_fields(self) -> &'static [&'static str] {
+                match self {
+                    $($(RouteId::$type_name => <$type_name as $crate::Route>::SENSITIVE_FIELDS,)*)?
+                }
+            }
+
+            pub fn erased(self) -> &'static dyn $crate::erased::ErasedRoute {
+                match self {
+                    $($(RouteId::$type_name => &$type_name,)*)?
+                }
+            }
+        }
+
+        impl $crate::RouteCatalog for RouteId {
+            const ALL: &'static [RouteId] = RouteId::ALL;
+
+            fn name(self) -> &'static str {
+                RouteId::name(self)
+            }
+
+            fn method(self) -> $crate::Method {
+                RouteId::method(self)
+            }
+
+            fn uri(self) -> &'static str {
+                RouteId::uri(self)
+            }
+
+            fn required_scopes(self) -> &'static [&'static str] {
+                RouteId::required_scopes(self)
+            }
+
+            fn audited(self) -> bool {
+                RouteId::audited(self)
+            }
+
+            fn feature_flag(self) -> Option<&'static str> {
+                RouteId::feature_flag(self)
+            }
+
+            fn alias(self) -> Option<&'static str> {
+                RouteId::alias(self)
+            }
+
+            fn sensitive_fields(self) -> &'static [&'static str] {
+                RouteId::sensitive_fields(self)
+            }
+
+            fn erased(self) -> &'static dyn $crate::erased::ErasedRoute {
+                RouteId::erased(self)
+            }
+        }
+
+        impl std::fmt::Display for RouteId {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl std::str::FromStr for RouteId {
+            type Err = $crate::ParseRouteIdError;
 
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                RouteId::ALL
+                    .iter()
+                    .copied()
+                    .find(|id| id.name() == s)
+                    .ok_or_else(|| $crate::ParseRouteIdError(s.to_owned()))
             }
         }
 
-        const URI: &'static str = const_str::concat!(super::URI,$uri);
+        const URI: &'static str = $crate::concat_uri!(super::URI, $uri);
+        const TRAILING_SLASH: $crate::TrailingSlash = {
+            let trailing_slash = $crate::TrailingSlash::Trim;
+            $(let trailing_slash = $crate::TrailingSlash::$trailing_slash;)?
+            trailing_slash
+        };
         $($(
-            define_route_type!($($outer_route)*);
+            define_route_type!($method, $uri_part => type $type_name (query: $query_type, body: $body_type $(, $option: $option_value)*) -> $response_type);
         )*)?
     };
 }
@@ -478,22 +2591,75 @@ macro_rules! define_item {
 
 #[macro_export]
 macro_rules! define_route_type {
-    ($method:expr, $uri_part:expr => type $type_name:ident (query: $query_type:ty, body: $body_type:ty $(,)?) -> $response_type:ty) => {
+    ($method:expr, $uri_part:expr => type $type_name:ident (query: $query_type:ty, body: $body_type:ty $(, $option:ident: $option_value:tt)* $(,)?) -> $response_type:ty) => {
         pub struct $type_name;
         impl crate::Route for $type_name {
             type Query = $query_type;
             type RequestBody = $body_type;
             type ResponseBody = $response_type;
-            const METHOD: http::Method = $method;
+            const METHOD: $crate::Method = $method;
+            const NAME: &'static str = stringify!($type_name);
             const URI_PART: &'static str = $uri_part;
-            const URI: &'static str = const_str::concat!(super::URI, $uri_part);
+            const URI: &'static str =
+                $crate::normalize_uri!($crate::concat_uri!(super::URI, $uri_part), TRAILING_SLASH);
+            const TRAILING_SLASH: $crate::TrailingSlash = TRAILING_SLASH;
+            $($crate::route_option!($option: $option_value);)*
         }
     };
 }
 
+/// Maps the options that follow a route's query and body types in [routes] and
+/// [define_route_type] onto the matching [Route] items.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! route_option {
+    (head: $head:literal) => {
+        const HEAD: bool = $head;
+    };
+    (scopes: [$($scope:expr),* $(,)?]) => {
+        const REQUIRED_SCOPES: &'static [&'static str] = &[$($scope),*];
+    };
+    (media_type: $media_type:literal) => {
+        const MEDIA_TYPE: Option<&'static str> = Some($media_type);
+    };
+    (cookies: [$($cookie:expr),* $(,)?]) => {
+        const REQUIRED_COOKIES: &'static [&'static str] = &[$($cookie),*];
+    };
+    (audited: $audited:literal) => {
+        const AUDITED: bool = $audited;
+    };
+    (json_limit: $limit:literal) => {
+        const JSON_LIMIT: Option<usize> = Some($limit);
+    };
+    (json_error: $handler:path) => {
+        const JSON_ERROR_HANDLER: Option<$crate::JsonErrorHandler> = Some($handler);
+    };
+    (query_error: $handler:path) => {
+        const QUERY_ERROR_HANDLER: Option<$crate::QueryErrorHandler> = Some($handler);
+    };
+    (flag: $flag:literal) => {
+        const FEATURE_FLAG: Option<&'static str> = Some($flag);
+    };
+    (alias: $alias:literal) => {
+        const ALIAS: Option<&'static str> = Some($alias);
+    };
+    (alias_redirect: $redirect:literal) => {
+        const ALIAS_REDIRECT: bool = $redirect;
+    };
+    (sensitive: [$($field:expr),* $(,)?]) => {
+        const SENSITIVE_FIELDS: &'static [&'static str] = &[$($field),*];
+    };
+    (priority: $priority:ident) => {
+        const PRIORITY: $crate::schedule::Priority = $crate::schedule::Priority::$priority;
+    };
+    (guards: [$($guard:expr),* $(,)?]) => {
+        const GUARDS: &'static [$crate::RouteGuard] = &[$($guard),*];
+    };
+}
+
 
 macro_rules! outer_routes_typedef {
-    ($outer_routes_type:ident {$($method:expr, $uri_part:expr => type $type_name:ident (query: $query_type:ty, body: $body_type:ty $(,)?) -> $response_type:ty),*}) => {
+    ($outer_routes_type:ident {$($method:expr, $uri_part:expr => type $type_name:ident (query: $query_type:ty, body: $body_type:ty $(, $option:ident: $option_value:tt)* $(,)?) -> $response_type:ty),*}) => {
         
     };
 }
@@ -510,13 +2676,14 @@ mod x {
     routes! {
         module: type Module;
         outer_routes: type ModuleOuter [
-            route(Method::POST, "/abc" => type Abc (query: NoQuery, body: JsonBody<Vec<u8>>) -> JsonBody<(String, u8)>)
+            route(Method::POST, "/abc" => type Abc (query: NoQuery, body: JsonBody<Vec<u8>>, scopes: ["abc:write"], media_type: "application/vnd.abc+json") -> JsonBody<(String, u8)>)
         ];
         scope: "/xyz";
         inner_items: type ModuleInner [];
     }
 }
 
+#[cfg(feature = "gloo")]
 async fn f() {
     let x = Default::default();
     let r = RequestBuilder::<x::Abc, _, _>::new()
@@ -530,3 +2697,369 @@ async fn f() {
         .await
         .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{dev::Service as _, HttpMessage as _};
+
+    use super::*;
+
+    const URI: &str = "";
+
+    // routes! leaves the module's URI unused when it has no inner items, and shadows its
+    // default trailing slash policy with the one given.
+    #[allow(dead_code, unused_variables)]
+    mod api {
+        use crate::{registration::Handles, JsonBody, Method, NoBody, NoCheckResponse, NoQuery};
+
+        routes! {
+            module: type Api;
+            outer_routes: type Outer [
+                route(Method::GET, "/orders/{id}" => type GetOrder (query: NoQuery, body: NoBody, audited: true, sensitive: ["id"], alias: "/order/{id}") -> JsonBody<String>),
+                route(Method::DELETE, "/orders/{id}" => type DeleteOrder (query: NoQuery, body: NoBody, audited: true, scopes: ["orders:delete"]) -> JsonBody<String>),
+                route(Method::GET, "/health" => type Health (query: NoQuery, body: NoBody) -> JsonBody<String>),
+            ];
+            scope: "";
+            trailing_slash: Either;
+            inner_items: type Inner [];
+        }
+
+        fn ok() -> actix_web::Route {
+            actix_web::web::route()
+                .to(|| async { NoCheckResponse(actix_web::HttpResponse::Ok().finish()) })
+        }
+
+        impl Handles<GetOrder> for Api {
+            fn route() -> actix_web::Route {
+                ok()
+            }
+        }
+
+        impl Handles<DeleteOrder> for Api {
+            fn route() -> actix_web::Route {
+                ok()
+            }
+        }
+
+        impl Handles<Health> for Api {
+            fn route() -> actix_web::Route {
+                ok()
+            }
+        }
+    }
+
+    /// A route that isn't `Send` or `Sync` itself.
+    struct NotSendRoute(PhantomData<*const ()>);
+
+    impl Route for NotSendRoute {
+        type Query = NoQuery;
+        type RequestBody = NoBody;
+        type ResponseBody = NoBody;
+        const METHOD: http::Method = http::Method::GET;
+        const NAME: &'static str = "NotSendRoute";
+        const URI_PART: &'static str = "/";
+        const URI: &'static str = "/";
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    // Native transports send from multithreaded runtimes, e.g. inside `tokio::spawn`, so the
+    // typed wrappers have to stay `Send` and `Sync` whatever their route is.
+
+    #[test]
+    fn request_builders_are_send_and_sync() {
+        assert_send_sync::<RequestBuilder<NotSendRoute, NoQuery, NoBody>>();
+    }
+
+    #[test]
+    fn requests_are_send_and_sync() {
+        assert_send_sync::<Request<NotSendRoute>>();
+    }
+
+    #[test]
+    fn responses_are_send_and_sync() {
+        assert_send_sync::<Response<NotSendRoute, BufferedResponse>>();
+    }
+
+    #[test]
+    fn accepts_well_formed_placeholders() {
+        check_placeholders("/orders/{id}/items/{item}");
+        check_placeholders(r"/items/{id:\d{3}}");
+        check_placeholders(r"/items/{id:[0-9]{1,3}}/{name:\{[a-z]+\}}");
+        check_placeholders("/static/{tail:.*}");
+    }
+
+    #[test]
+    #[should_panic(expected = "without a matching `}`")]
+    fn rejects_unclosed_placeholders() {
+        check_placeholders("/orders/{id");
+    }
+
+    #[test]
+    #[should_panic(expected = "without a matching `}`")]
+    fn rejects_unbalanced_braces_in_regexes() {
+        check_placeholders(r"/items/{id:\d{3}");
+    }
+
+    #[test]
+    #[should_panic(expected = "without a matching `{`")]
+    fn rejects_unopened_placeholders() {
+        check_placeholders("/orders/id}");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't contain another")]
+    fn rejects_nested_placeholders() {
+        check_placeholders("/orders/{id{item}}");
+    }
+
+    #[test]
+    #[should_panic(expected = "empty placeholder")]
+    fn rejects_empty_placeholders() {
+        check_placeholders("/orders/{}");
+    }
+
+    #[test]
+    #[should_panic(expected = "same placeholder twice")]
+    fn rejects_repeated_placeholders() {
+        check_placeholders(r"/orders/{id}/copies/{id:\d+}");
+    }
+
+    #[test]
+    fn canonicalizes_queries() {
+        assert_eq!(canonicalize_query("b=2&a=1"), "a=1&b=2");
+        assert_eq!(canonicalize_query("a=x&b=2&a=y"), "a=x&a=y&b=2");
+        assert_eq!(
+            canonicalize_query("q=caf%C3%A9+au+lait&p=%7e"),
+            canonicalize_query("p=~&q=caf\u{e9}%20au%20lait")
+        );
+        assert_eq!(canonicalize_query(""), "");
+        assert_eq!(canonicalize_query("b=%ff&a=1"), "b=%ff&a=1");
+        assert_eq!(canonicalize_query("b=%EF%BF%BD&a=1"), "a=1&b=%EF%BF%BD");
+    }
+
+    #[test]
+    fn keys_requests_by_method_path_and_canonical_query() {
+        let uri = |uri: &str| uri.parse::<http::Uri>().unwrap();
+        assert_eq!(
+            canonical_uri(&http::Method::GET, &uri("/orders?status=open&page=2")),
+            canonical_uri(&http::Method::GET, &uri("/orders?page=2&status=open"))
+        );
+        assert_eq!(
+            canonical_uri(&http::Method::GET, &uri("https://example.com/orders?")),
+            "GET /orders"
+        );
+        assert_ne!(
+            canonical_uri(&http::Method::GET, &uri("/orders?page=2")),
+            canonical_uri(&http::Method::DELETE, &uri("/orders?page=2"))
+        );
+    }
+
+    struct Download;
+
+    impl Route for Download {
+        type Query = NoQuery;
+        type RequestBody = NoBody;
+        type ResponseBody = NoBody;
+        const METHOD: http::Method = http::Method::GET;
+        const NAME: &'static str = "Download";
+        const URI_PART: &'static str = "/download";
+        const URI: &'static str = "/download";
+    }
+
+    fn range_header(range: impl RangeBounds<u64>) -> Result<String, EmptyRange> {
+        let request = RequestBuilder::<Download, _, _>::new()
+            .range(range)?
+            .build()
+            .unwrap()
+            .into_untyped_request();
+        Ok(request.headers()[http::header::RANGE]
+            .to_str()
+            .unwrap()
+            .to_owned())
+    }
+
+    #[test]
+    fn requests_byte_ranges() {
+        assert_eq!(range_header(0..10).unwrap(), "bytes=0-9");
+        assert_eq!(range_header(5..=5).unwrap(), "bytes=5-5");
+        assert_eq!(range_header(100..).unwrap(), "bytes=100-");
+        assert_eq!(range_header(..10).unwrap(), "bytes=0-9");
+        assert_eq!(
+            range_header((Bound::Excluded(4), Bound::Unbounded)).unwrap(),
+            "bytes=5-"
+        );
+        assert_eq!(
+            range_header(0..=u64::MAX).unwrap(),
+            format!("bytes=0-{}", u64::MAX)
+        );
+    }
+
+    #[test]
+    fn rejects_empty_byte_ranges() {
+        assert!(range_header(5..5).is_err());
+        assert!(range_header(..0).is_err());
+        assert!(range_header((Bound::Included(6), Bound::Included(5))).is_err());
+        assert!(range_header((Bound::Excluded(u64::MAX), Bound::Unbounded)).is_err());
+    }
+
+    #[test]
+    fn parses_content_ranges() {
+        assert_eq!(
+            "bytes 0-9/100".parse::<ContentRange>().unwrap(),
+            ContentRange {
+                range: Some(0..=9),
+                complete_length: Some(100),
+            }
+        );
+        assert_eq!(
+            "bytes 0-9/*".parse::<ContentRange>().unwrap(),
+            ContentRange {
+                range: Some(0..=9),
+                complete_length: None,
+            }
+        );
+        assert_eq!(
+            "bytes */100".parse::<ContentRange>().unwrap(),
+            ContentRange {
+                range: None,
+                complete_length: Some(100),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_content_ranges() {
+        for header in [
+            "bytes */*",
+            "bytes 9-0/100",
+            "bytes 0-100/100",
+            "bytes 0-9",
+            "items 0-9/100",
+            "bytes -9/100",
+        ] {
+            assert!(header.parse::<ContentRange>().is_err(), "{header}");
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Events(std::rc::Rc<std::cell::RefCell<Vec<audit::AuditEvent<api::RouteId>>>>);
+
+    impl audit::AuditSink<api::RouteId> for Events {
+        fn record(&self, event: audit::AuditEvent<api::RouteId>) {
+            self.0.borrow_mut().push(event);
+        }
+    }
+
+    /// The route and status of each event recorded for `requests`, with the API under an `/api`
+    /// scope.
+    async fn audit(requests: &[(actix_web::http::Method, &str)]) -> Vec<(api::RouteId, u16)> {
+        let events = Events::default();
+        let app = actix_web::test::init_service(
+            actix_web::App::new()
+                .wrap(audit::Audit::<api::RouteId, _>::new(events.clone()))
+                .service(api::Api.register(actix_web::web::scope("/api"))),
+        )
+        .await;
+        for (method, uri) in requests {
+            let req = actix_web::test::TestRequest::default()
+                .method(method.clone())
+                .uri(uri)
+                .to_request();
+            actix_web::test::call_service(&app, req).await;
+        }
+        let events = events.0.borrow();
+        events
+            .iter()
+            .map(|event| (event.route, event.status.as_u16()))
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn audits_routes_however_theyre_reached() {
+        use actix_web::http::Method;
+
+        let events = audit(&[
+            (Method::GET, "/api/orders/1"),
+            (Method::GET, "/api/orders/1/"),
+            (Method::GET, "/api/order/1"),
+            (Method::GET, "/api/health"),
+        ])
+        .await;
+        assert_eq!(
+            events,
+            [
+                (api::RouteId::GetOrder, 200),
+                (api::RouteId::GetOrder, 200),
+                (api::RouteId::GetOrder, 200),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn audits_rejected_requests() {
+        let events = audit(&[(actix_web::http::Method::DELETE, "/api/orders/1")]).await;
+        assert_eq!(events, [(api::RouteId::DeleteOrder, 403)]);
+    }
+
+    #[actix_web::test]
+    async fn audits_requests_that_fail_with_an_error() {
+        let events = Events::default();
+        let app = actix_web::test::init_service(
+            actix_web::App::new()
+                .wrap(audit::Audit::<api::RouteId, _>::new(events.clone()))
+                .service(api::Api.register(actix_web::web::scope("/api")).wrap_fn(
+                    |req, service| {
+                        let response = service.call(req);
+                        async move {
+                            response.await?;
+                            Err::<actix_web::dev::ServiceResponse, _>(
+                                actix_web::error::ErrorBadGateway("upstream failed"),
+                            )
+                        }
+                    },
+                )),
+        )
+        .await;
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/orders/1")
+            .to_request();
+        let _ = actix_web::test::try_call_service(&app, req).await;
+
+        let events = events.0.borrow();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, http::StatusCode::BAD_GATEWAY);
+    }
+
+    #[actix_web::test]
+    async fn masks_sensitive_path_params_and_reports_the_principal() {
+        let events = Events::default();
+        let app = actix_web::test::init_service(
+            actix_web::App::new()
+                .wrap(audit::Audit::<api::RouteId, _>::new(events.clone()))
+                .service(api::Api.register(actix_web::web::scope("/api"))),
+        )
+        .await;
+        let req = actix_web::test::TestRequest::delete()
+            .uri("/api/orders/1")
+            .to_request();
+        req.extensions_mut()
+            .insert(GrantedScopes(vec!["orders:delete".to_owned()]));
+        req.extensions_mut()
+            .insert(audit::Principal("alice".to_owned()));
+        actix_web::test::call_service(&app, req).await;
+        let req = actix_web::test::TestRequest::get()
+            .uri("/api/orders/1")
+            .to_request();
+        actix_web::test::call_service(&app, req).await;
+
+        let events = events.0.borrow();
+        assert_eq!(events[0].principal.as_deref(), Some("alice"));
+        assert_eq!(events[0].path_params, [("id".to_owned(), "1".to_owned())]);
+        assert_eq!(events[1].principal, None);
+        assert_eq!(
+            events[1].path_params,
+            [("id".to_owned(), redact::MASK.to_owned())]
+        );
+    }
+}
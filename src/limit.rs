@@ -0,0 +1,144 @@
+//! Capping how much of a response body a client reads, so that a server, or anything between it
+//! and the client, can't exhaust the client's memory with a huge body.
+//!
+//! ```
+//! # #[cfg(feature = "hyper")]
+//! # fn transport(base_url: typed_routing::http::Uri) {
+//! # use typed_routing::{hyper::Hyper, limit::ResponseLimit};
+//! let transport = ResponseLimit::new(Hyper::new(base_url), 10 * 1024 * 1024);
+//! # }
+//! ```
+//!
+//! Bodies are counted as they're read, so responses without a `Content-Length`, e.g. chunked
+//! ones, are cut off too, and the rest of the body isn't received. Transports that don't
+//! override [TransportResponse::chunks] give their body whole, so they've read it by the time
+//! it's counted, and only a `Content-Length` over the limit stops them reading it.
+
+use bytes::{Bytes, BytesMut};
+use futures_util::{future::Either, Stream, StreamExt};
+use thiserror::Error;
+
+use crate::{request_id::REQUEST_ID_HEADER, ClientTransport, ResponseTooLarge, TransportResponse};
+
+/// A [ClientTransport] that fails reading any response body longer than its limit.
+/// [Response::limit](crate::Response::limit) sets one for a single response instead.
+#[derive(Debug, Clone)]
+pub struct ResponseLimit<T> {
+    inner: T,
+    max: u64,
+}
+
+impl<T> ResponseLimit<T> {
+    pub fn new(inner: T, max: u64) -> Self {
+        ResponseLimit { inner, max }
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for ResponseLimit<T> {
+    type Response = LimitedResponse<T::Response>;
+    type Error = T::Error;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let response = self.inner.send(request).await?;
+        Ok(LimitedResponse::new(response, self.max))
+    }
+}
+
+/// The error reading a [LimitedResponse]'s body.
+#[derive(Debug, Error)]
+pub enum LimitError<TransportError> {
+    #[error(transparent)]
+    TooLarge(ResponseTooLarge),
+    #[error("Failed to receive the body")]
+    Transport(#[source] TransportError),
+}
+
+/// A response whose body is read up to a limit of `max` bytes.
+pub struct LimitedResponse<R> {
+    response: R,
+    max: u64,
+}
+
+impl<R: TransportResponse> LimitedResponse<R> {
+    pub(crate) fn new(response: R, max: u64) -> Self {
+        LimitedResponse { response, max }
+    }
+
+    /// The body's length according to the `Content-Length` header, if there is one.
+    pub(crate) fn content_length(&self) -> Option<u64> {
+        self.response
+            .headers()
+            .get(http::header::CONTENT_LENGTH)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    pub(crate) fn too_large(&self, length: u64) -> ResponseTooLarge {
+        ResponseTooLarge {
+            length,
+            max: self.max,
+            request_id: self
+                .response
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|id| id.to_str().ok())
+                .map(str::to_owned),
+        }
+    }
+}
+
+impl<R: TransportResponse> TransportResponse for LimitedResponse<R> {
+    type Error = LimitError<R::Error>;
+
+    fn status(&self) -> http::StatusCode {
+        self.response.status()
+    }
+
+    fn headers(&self) -> &http::HeaderMap {
+        self.response.headers()
+    }
+
+    async fn bytes(self) -> Result<Bytes, Self::Error> {
+        let mut chunks = std::pin::pin!(self.chunks());
+        let mut body = BytesMut::new();
+        while let Some(chunk) = chunks.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body.freeze())
+    }
+
+    fn chunks(self) -> impl Stream<Item = Result<Bytes, Self::Error>> {
+        if let Some(length) = self.content_length().filter(|&length| length > self.max) {
+            let err = LimitError::TooLarge(self.too_large(length));
+            return Either::Left(futures_util::stream::once(async { Err(err) }));
+        }
+
+        // The error for a body that passes the limit, made up front as the response is
+        // consumed by reading it.
+        let too_large = self.too_large(0);
+        let max = self.max;
+        let mut read = 0;
+        let mut done = false;
+        Either::Right(self.response.chunks().scan((), move |_, chunk| {
+            let item = match chunk {
+                _ if done => None,
+                Ok(chunk) => {
+                    read += chunk.len() as u64;
+                    if read > max {
+                        done = true;
+                        Some(Err(LimitError::TooLarge(ResponseTooLarge {
+                            length: read,
+                            ..too_large.clone()
+                        })))
+                    } else {
+                        Some(Ok(chunk))
+                    }
+                }
+                Err(err) => Some(Err(LimitError::Transport(err))),
+            };
+            async move { item }
+        }))
+    }
+}
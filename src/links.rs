@@ -0,0 +1,185 @@
+//! Hyperlinks between typed routes, embedded in response bodies.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{NoBody, NoQuery, RequestBuilder, Route};
+
+/// A link to a route, at a URI with its path segments filled in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub href: String,
+    pub method: String,
+}
+
+/// The error returned when building or following a [Link] fails.
+#[derive(Debug, Error)]
+pub enum LinkError {
+    #[error("No value for the path segment {{{0}}}")]
+    MissingPathParam(String),
+    #[error("The path segment {{{name}}} can't be {value:?}")]
+    InvalidPathParam { name: String, value: String },
+    #[error("No link with relation {0}")]
+    Missing(String),
+    #[error("Link {rel} doesn't point at route {route}")]
+    WrongRoute { rel: String, route: &'static str },
+}
+
+/// Links from a resource to related routes, keyed by relation, e.g. `self` or `next`.
+///
+/// Serializes as a map from relation to [Link], to embed in response bodies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Links(pub BTreeMap<String, Link>);
+
+impl Links {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a link to `Route`, filling in its URI's `{name}` segments from `params`.
+    pub fn link<Route: self::Route>(
+        mut self,
+        rel: &str,
+        params: &[(&str, &str)],
+    ) -> Result<Self, LinkError> {
+        let href = fill_uri(Route::URI, |name| {
+            params
+                .iter()
+                .find(|(param, _)| *param == name)
+                .map(|(_, value)| *value)
+        })?;
+        self.0.insert(
+            rel.to_owned(),
+            Link {
+                href,
+                method: Route::METHOD.to_string(),
+            },
+        );
+        Ok(self)
+    }
+
+    pub fn get(&self, rel: &str) -> Option<&Link> {
+        self.0.get(rel)
+    }
+
+    /// Start building a request that follows the link `rel`, checking that it points at
+    /// `Route`.
+    pub fn follow<Route: self::Route>(
+        &self,
+        rel: &str,
+    ) -> Result<RequestBuilder<Route, NoQuery, NoBody>, LinkError> {
+        let link = self
+            .get(rel)
+            .ok_or_else(|| LinkError::Missing(rel.to_owned()))?;
+        if link.method != Route::METHOD.as_str() || !matches_uri(Route::URI, &link.href) {
+            return Err(LinkError::WrongRoute {
+                rel: rel.to_owned(),
+                route: Route::NAME,
+            });
+        }
+        Ok(RequestBuilder::with_uri(&link.href))
+    }
+}
+
+/// Fill in the `{name}` segments of a route's URI with `value(name)`, percent-encoded so that
+/// a value can't add segments, a query or a fragment to it.
+///
+/// Empty values, `.` and `..` are rejected, as clients would drop or resolve the segment even
+/// if it were encoded.
+pub(crate) fn fill_uri<'a>(
+    pattern: &'a str,
+    value: impl Fn(&str) -> Option<&'a str>,
+) -> Result<String, LinkError> {
+    pattern
+        .split('/')
+        .map(|segment| match placeholder(segment) {
+            Some(name) => match value(name) {
+                Some(value @ ("" | "." | "..")) => Err(LinkError::InvalidPathParam {
+                    name: name.to_owned(),
+                    value: value.to_owned(),
+                }),
+                Some(value) => Ok(crate::percent_encode(value)),
+                None => Err(LinkError::MissingPathParam(name.to_owned())),
+            },
+            None => Ok(segment.to_owned()),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|segments| segments.join("/"))
+}
+
+/// The name of a `{name}` or `{name:regex}` path segment.
+pub(crate) fn placeholder(segment: &str) -> Option<&str> {
+    let placeholder = segment.strip_prefix('{')?.strip_suffix('}')?;
+    Some(
+        placeholder
+            .split_once(':')
+            .map_or(placeholder, |(name, _)| name),
+    )
+}
+
+/// Whether `href` is an instance of the route URI `pattern`.
+pub(crate) fn matches_uri(pattern: &str, href: &str) -> bool {
+    let path = href.split(['?', '#']).next().unwrap_or_default();
+    let mut patterns = pattern.split('/');
+    let mut segments = path.split('/');
+    loop {
+        match (patterns.next(), segments.next()) {
+            (None, None) => return true,
+            (Some(pattern), Some(segment)) => {
+                let matches = match placeholder(pattern) {
+                    Some(_) => !segment.is_empty(),
+                    None => pattern == segment,
+                };
+                if !matches {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(pattern: &'static str, id: &'static str) -> Result<String, LinkError> {
+        fill_uri(pattern, |name| (name == "id").then_some(id))
+    }
+
+    #[test]
+    fn encodes_values() {
+        assert_eq!(fill("/items/{id}", "a b").unwrap(), "/items/a%20b");
+        assert_eq!(
+            fill("/items/{id}/parts", "../x?y#z%").unwrap(),
+            "/items/..%2Fx%3Fy%23z%25/parts"
+        );
+        assert_eq!(fill("/items/{id}", "//host").unwrap(), "/items/%2F%2Fhost");
+    }
+
+    #[test]
+    fn rejects_dot_and_empty_values() {
+        for id in ["", ".", ".."] {
+            assert!(matches!(
+                fill("/items/{id}", id),
+                Err(LinkError::InvalidPathParam { .. })
+            ));
+        }
+        assert!(matches!(
+            fill_uri("/items/{id}", |_| None),
+            Err(LinkError::MissingPathParam(name)) if name == "id"
+        ));
+    }
+
+    #[test]
+    fn fills_placeholders_with_regexes() {
+        assert_eq!(placeholder("{id:\\d{3}}"), Some("id"));
+        assert_eq!(placeholder("{id}"), Some("id"));
+        assert_eq!(placeholder("items"), None);
+        assert_eq!(fill("/items/{id:\\d+}", "123").unwrap(), "/items/123");
+        assert!(matches_uri("/items/{id:\\d+}", "/items/123?x=1"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Maintenance mode: answering every request with a `503 Service Unavailable` the client can
+//! recognise, while the server is being worked on.
+
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    ResponseError,
+};
+
+use crate::{problem::ProblemDetails, Response, TransportResponse};
+
+/// The header that marks a `503` as a maintenance response rather than some other outage.
+pub const MAINTENANCE_HEADER: &str = "x-maintenance";
+
+/// The problem type maintenance responses are sent with.
+pub const MAINTENANCE_KIND: &str = "urn:typed-routing:maintenance";
+
+#[derive(Debug, Clone, Default)]
+struct Window {
+    retry_after: Option<Duration>,
+    message: Option<String>,
+}
+
+/// Middleware that, while switched on, answers every request to what it wraps with a
+/// maintenance response. Wrap the app, or only the scopes of the modules being worked on.
+///
+/// Clones share the same switch, so keep one to turn maintenance mode on and off at runtime.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceMode(Arc<Mutex<Option<Window>>>);
+
+impl MaintenanceMode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start answering requests with maintenance responses, telling clients to come back after
+    /// `retry_after` if it's known.
+    pub fn enable(&self, retry_after: Option<Duration>, message: Option<&str>) {
+        *self.0.lock().unwrap() = Some(Window {
+            retry_after,
+            message: message.map(str::to_owned),
+        });
+    }
+
+    pub fn disable(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+}
+
+fn maintenance_response(window: Window) -> actix_web::HttpResponse {
+    let mut problem = ProblemDetails::new(http::StatusCode::SERVICE_UNAVAILABLE)
+        .kind(MAINTENANCE_KIND, "Down for maintenance");
+    if let Some(message) = &window.message {
+        problem = problem.detail(message);
+    }
+    let mut response = problem.error_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        actix_web::http::header::HeaderName::from_static(MAINTENANCE_HEADER),
+        actix_web::http::header::HeaderValue::from_static("1"),
+    );
+    if let Some(retry_after) = window.retry_after {
+        headers.insert(
+            actix_web::http::header::RETRY_AFTER,
+            actix_web::http::header::HeaderValue::from(retry_after.as_secs()),
+        );
+    }
+    response
+}
+
+impl<S, B: 'static> Transform<S, ServiceRequest> for MaintenanceMode
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = MaintenanceMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MaintenanceMiddleware {
+            mode: self.clone(),
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct MaintenanceMiddleware<S> {
+    mode: MaintenanceMode,
+    service: Rc<S>,
+}
+
+impl<S, B: 'static> Service<ServiceRequest> for MaintenanceMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let window = self.mode.0.lock().unwrap().clone();
+        match window {
+            Some(window) => {
+                let response = maintenance_response(window);
+                Box::pin(ready(Ok(req.into_response(response).map_into_right_body())))
+            }
+            None => {
+                let service = Rc::clone(&self.service);
+                Box::pin(async move {
+                    service
+                        .call(req)
+                        .await
+                        .map(ServiceResponse::map_into_left_body)
+                })
+            }
+        }
+    }
+}
+
+/// A maintenance response, recognised by [Response::maintenance].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Maintenance {
+    /// How long the server asked clients to wait before trying again.
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for Maintenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("The server is down for maintenance")?;
+        if let Some(retry_after) = self.retry_after {
+            write!(f, ", retry after {}s", retry_after.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Maintenance {}
+
+impl<Route: crate::Route, R: TransportResponse> Response<Route, R> {
+    /// Whether this is a maintenance response from a server in [MaintenanceMode].
+    ///
+    /// Only `Retry-After` values given in seconds are understood.
+    pub fn maintenance(&self) -> Option<Maintenance> {
+        if self.status() != http::StatusCode::SERVICE_UNAVAILABLE
+            || !self.headers().contains_key(MAINTENANCE_HEADER)
+        {
+            return None;
+        }
+        let retry_after = self
+            .headers()
+            .get(http::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|seconds| seconds.parse().ok())
+            .map(Duration::from_secs);
+        Some(Maintenance { retry_after })
+    }
+}
@@ -0,0 +1,116 @@
+//! A server that answers every route of an API with a canned response, so that frontends can
+//! be developed against the typed API before its handlers exist.
+//!
+//! ```no_run
+//! # use serde::Serialize;
+//! # use typed_routing::{examples::{Examples, RouteExamples}, mock_server::MockServer, JsonBody, Method, NoBody, NoQuery, Route};
+//! # #[derive(Default, Serialize)]
+//! # struct Order { id: u64 }
+//! # struct GetOrder;
+//! # impl Route for GetOrder {
+//! #     type Query = NoQuery;
+//! #     type RequestBody = NoBody;
+//! #     type ResponseBody = JsonBody<Order>;
+//! #     const METHOD: Method = Method::GET;
+//! #     const NAME: &'static str = "GetOrder";
+//! #     const URI_PART: &'static str = "/orders/{id}";
+//! #     const URI: &'static str = "/orders/{id}";
+//! # }
+//! # impl RouteExamples for GetOrder {
+//! #     fn examples() -> Examples {
+//! #         Examples::new().response_body(&Order { id: 7 })
+//! #     }
+//! # }
+//! # struct ListOrders;
+//! # impl Route for ListOrders {
+//! #     type Query = NoQuery;
+//! #     type RequestBody = NoBody;
+//! #     type ResponseBody = JsonBody<Vec<Order>>;
+//! #     const METHOD: Method = Method::GET;
+//! #     const NAME: &'static str = "ListOrders";
+//! #     const URI_PART: &'static str = "/orders";
+//! #     const URI: &'static str = "/orders";
+//! # }
+//! #[actix_web::main]
+//! async fn main() -> std::io::Result<()> {
+//!     MockServer::new()
+//!         .route::<GetOrder>()
+//!         .default_route::<ListOrders>()
+//!         .serve(("127.0.0.1", 8080))?
+//!         .await
+//! }
+//! ```
+//!
+//! It's also a [Module], to mock an API alongside other services in an app.
+
+use actix_web::{web, App, HttpServer, Scope};
+
+use crate::{
+    examples::{ExampleResponse, Examples, RouteExamples},
+    postman::Example,
+    Module, Route, Router,
+};
+
+/// A set of routes served with canned responses.
+#[derive(Clone, Default)]
+pub struct MockServer {
+    routes: Vec<fn(Scope) -> Scope>,
+}
+
+impl MockServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `R`'s example response from its [RouteExamples].
+    pub fn route<R>(mut self) -> Self
+    where
+        R: RouteExamples + 'static,
+        R::ResponseBody: ExampleResponse,
+    {
+        self.routes.push(|scope| scope.example_handler::<R>());
+        self
+    }
+
+    /// Serve `R`'s response body's [Default] value, with a `200 OK` status, for routes without
+    /// [RouteExamples].
+    pub fn default_route<R>(mut self) -> Self
+    where
+        R: Route + 'static,
+        R::ResponseBody: Example + ExampleResponse,
+    {
+        self.routes.push(|scope| {
+            scope.typed_route::<R, _>(|| {
+                web::route().to(|| async {
+                    let examples = Examples {
+                        response_body: R::ResponseBody::example(),
+                        ..Examples::default()
+                    };
+                    R::ResponseBody::respond(&examples, R::MEDIA_TYPE)
+                })
+            })
+        });
+        self
+    }
+
+    /// Start serving the routes at `addr`, with a worker per CPU core. The server runs until
+    /// the returned future is dropped or it's stopped with a signal.
+    pub fn serve(
+        self,
+        addr: impl std::net::ToSocketAddrs,
+    ) -> std::io::Result<actix_web::dev::Server> {
+        Ok(HttpServer::new(move || self.clone().register(App::new()))
+            .bind(addr)?
+            .run())
+    }
+}
+
+impl Module for MockServer {
+    fn register<R: Router>(self, router: R) -> R {
+        let scope = self
+            .routes
+            .into_iter()
+            .fold(web::scope(""), |scope, register| register(scope));
+        router.service(scope)
+    }
+}
@@ -0,0 +1,309 @@
+//! The OAuth 2.0 authorization code flow with PKCE, as used by OpenID Connect providers.
+//!
+//! A provider's endpoints are declared like any other routes, using the types in this module:
+//!
+//! ```ignore
+//! route(Method::GET, "/authorize" => type Authorize (query: Query<oauth::AuthorizeParams>, body: NoBody) -> NoBody),
+//! route(Method::POST, "/token" => type ExchangeCode (query: NoQuery, body: FormBody<oauth::CodeExchange>) -> JsonBody<oauth::TokenResponse>),
+//! route(Method::POST, "/token" => type RefreshToken (query: NoQuery, body: FormBody<oauth::TokenRefresh>) -> JsonBody<oauth::TokenResponse>)
+//! ```
+
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{session::Session, Query};
+
+/// A PKCE code verifier, kept by the client between sending the user to the authorization
+/// endpoint and exchanging the code it gets back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pkce {
+    verifier: String,
+}
+
+impl Pkce {
+    /// A new verifier made of 32 random bytes.
+    pub fn generate() -> Result<Self, getrandom::Error> {
+        Ok(Pkce {
+            verifier: random_string()?,
+        })
+    }
+
+    /// A verifier that was saved earlier, e.g. across the redirect to the provider.
+    pub fn from_verifier(verifier: String) -> Self {
+        Pkce { verifier }
+    }
+
+    pub fn verifier(&self) -> &str {
+        &self.verifier
+    }
+
+    /// The `S256` code challenge for this verifier.
+    pub fn challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.verifier.as_bytes()))
+    }
+}
+
+/// 32 random bytes, encoded to be safe in URLs.
+fn random_string() -> Result<String, getrandom::Error> {
+    let mut bytes = [0; 32];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// The query of the provider's authorization endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeParams {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    pub state: String,
+    pub code_challenge: String,
+    pub code_challenge_method: String,
+}
+
+impl AuthorizeParams {
+    pub fn new(client_id: &str, redirect_uri: &str, state: &str, pkce: &Pkce) -> Self {
+        AuthorizeParams {
+            response_type: "code".to_owned(),
+            client_id: client_id.to_owned(),
+            redirect_uri: redirect_uri.to_owned(),
+            scope: None,
+            state: state.to_owned(),
+            code_challenge: pkce.challenge(),
+            code_challenge_method: "S256".to_owned(),
+        }
+    }
+
+    /// Request `scopes`, e.g. `openid` and `profile` for OpenID Connect.
+    pub fn scopes(mut self, scopes: &[&str]) -> Self {
+        self.scope = Some(scopes.join(" "));
+        self
+    }
+}
+
+/// The URL to send the user to, to authorize the client at the provider at `base`.
+pub fn authorize_url<Route: crate::Route<Query = Query<AuthorizeParams>>>(
+    base: &str,
+    params: &AuthorizeParams,
+) -> Result<String, serde_urlencoded::ser::Error> {
+    let query = serde_urlencoded::to_string(params)?;
+    Ok(format!(
+        "{}{}?{query}",
+        base.trim_end_matches('/'),
+        Route::URI
+    ))
+}
+
+/// The query the provider redirects back to the client's redirect URI with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorizeCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+impl AuthorizeCallback {
+    /// The authorization code, if the user authorized the client and `state` matches the one
+    /// the flow was started with.
+    pub fn into_code(self, expected_state: &str) -> Result<String, AuthorizeError> {
+        if let Some(error) = self.error {
+            return Err(AuthorizeError::Denied {
+                error,
+                description: self.error_description,
+            });
+        }
+        if self.state.as_deref() != Some(expected_state) {
+            return Err(AuthorizeError::StateMismatch);
+        }
+        self.code.ok_or(AuthorizeError::MissingCode)
+    }
+}
+
+/// An error completing the redirect to and from the authorization endpoint.
+#[derive(Debug, Error)]
+pub enum AuthorizeError {
+    #[error("Authorization failed with {error}: {description:?}")]
+    Denied {
+        error: String,
+        description: Option<String>,
+    },
+    #[error("Authorization callback has the wrong state")]
+    StateMismatch,
+    #[error("Authorization callback is missing the code")]
+    MissingCode,
+    #[error("Failed to generate a random value")]
+    Random(#[source] getrandom::Error),
+    #[error("Failed to build the authorization URL")]
+    Url(#[source] serde_urlencoded::ser::Error),
+    #[error("Browser API call failed: {0}")]
+    Browser(String),
+}
+
+/// The form sent to the token endpoint to exchange an authorization code for tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeExchange {
+    pub grant_type: String,
+    pub code: String,
+    pub redirect_uri: String,
+    pub client_id: String,
+    pub code_verifier: String,
+}
+
+impl CodeExchange {
+    pub fn new(code: String, redirect_uri: &str, client_id: &str, pkce: &Pkce) -> Self {
+        CodeExchange {
+            grant_type: "authorization_code".to_owned(),
+            code,
+            redirect_uri: redirect_uri.to_owned(),
+            client_id: client_id.to_owned(),
+            code_verifier: pkce.verifier.clone(),
+        }
+    }
+}
+
+/// The form sent to the token endpoint to get a new access token with a refresh token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenRefresh {
+    pub grant_type: String,
+    pub refresh_token: String,
+    pub client_id: String,
+}
+
+impl TokenRefresh {
+    pub fn new(refresh_token: String, client_id: &str) -> Self {
+        TokenRefresh {
+            grant_type: "refresh_token".to_owned(),
+            refresh_token,
+            client_id: client_id.to_owned(),
+        }
+    }
+}
+
+/// The token endpoint's response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: Option<u64>,
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+    /// The OpenID Connect ID token, if the `openid` scope was requested.
+    pub id_token: Option<String>,
+}
+
+impl TokenResponse {
+    /// A session that sends the access token, for use with a
+    /// [SessionClient](crate::session::SessionClient).
+    pub fn session(&self) -> Session {
+        Session::Bearer(self.access_token.clone())
+    }
+}
+
+/// Where a client keeps its tokens between requests, e.g. in memory or browser storage.
+pub trait TokenStore {
+    fn load(&self) -> Option<TokenResponse>;
+    fn store(&self, tokens: &TokenResponse);
+    fn clear(&self);
+}
+
+/// A [TokenStore] that keeps tokens for the lifetime of the process or page.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore(Mutex<Option<TokenResponse>>);
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> Option<TokenResponse> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn store(&self, tokens: &TokenResponse) {
+        *self.0.lock().unwrap() = Some(tokens.clone());
+    }
+
+    fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+/// Helpers for the redirect to and from the provider in a browser, keeping the state and PKCE
+/// verifier in `sessionStorage` across it.
+#[cfg(feature = "gloo")]
+pub mod browser {
+    use js_sys::{
+        wasm_bindgen::{JsCast, JsValue},
+        Array, Function, Reflect,
+    };
+
+    use super::{
+        authorize_url, random_string, AuthorizeCallback, AuthorizeError, AuthorizeParams, Pkce,
+    };
+    use crate::Query;
+
+    const STATE_KEY: &str = "typed-routing.oauth.state";
+    const VERIFIER_KEY: &str = "typed-routing.oauth.verifier";
+
+    fn browser_error(value: JsValue) -> AuthorizeError {
+        AuthorizeError::Browser(format!("{value:?}"))
+    }
+
+    fn get(target: &JsValue, property: &str) -> Result<JsValue, AuthorizeError> {
+        Reflect::get(target, &property.into()).map_err(browser_error)
+    }
+
+    fn call(target: &JsValue, method: &str, args: &[&str]) -> Result<JsValue, AuthorizeError> {
+        let function: Function = get(target, method)?.dyn_into().map_err(browser_error)?;
+        let args: Array = args.iter().map(|&arg| JsValue::from(arg)).collect();
+        function.apply(target, &args).map_err(browser_error)
+    }
+
+    fn session_storage() -> Result<JsValue, AuthorizeError> {
+        get(&js_sys::global(), "sessionStorage")
+    }
+
+    /// Send the user to the provider's authorization endpoint at `base`.
+    pub fn start_authorization<Route: crate::Route<Query = Query<AuthorizeParams>>>(
+        base: &str,
+        client_id: &str,
+        redirect_uri: &str,
+        scopes: &[&str],
+    ) -> Result<(), AuthorizeError> {
+        let pkce = Pkce::generate().map_err(AuthorizeError::Random)?;
+        let state = random_string().map_err(AuthorizeError::Random)?;
+        let params = AuthorizeParams::new(client_id, redirect_uri, &state, &pkce).scopes(scopes);
+        let url = authorize_url::<Route>(base, &params).map_err(AuthorizeError::Url)?;
+
+        let storage = session_storage()?;
+        call(&storage, "setItem", &[STATE_KEY, &state])?;
+        call(&storage, "setItem", &[VERIFIER_KEY, pkce.verifier()])?;
+        let location = get(&js_sys::global(), "location")?;
+        call(&location, "assign", &[&url])?;
+        Ok(())
+    }
+
+    /// On the redirect URI's page, get the authorization code and the verifier to exchange it
+    /// with, using [CodeExchange](super::CodeExchange).
+    pub fn finish_authorization() -> Result<(String, Pkce), AuthorizeError> {
+        let storage = session_storage()?;
+        let state = call(&storage, "getItem", &[STATE_KEY])?.as_string();
+        let verifier = call(&storage, "getItem", &[VERIFIER_KEY])?.as_string();
+        call(&storage, "removeItem", &[STATE_KEY])?;
+        call(&storage, "removeItem", &[VERIFIER_KEY])?;
+        let (Some(state), Some(verifier)) = (state, verifier) else {
+            return Err(AuthorizeError::StateMismatch);
+        };
+
+        let location = get(&js_sys::global(), "location")?;
+        let search = get(&location, "search")?.as_string().unwrap_or_default();
+        let callback: AuthorizeCallback =
+            serde_urlencoded::from_str(search.trim_start_matches('?'))
+                .map_err(|err| AuthorizeError::Browser(err.to_string()))?;
+        let code = callback.into_code(&state)?;
+        Ok((code, Pkce::from_verifier(verifier)))
+    }
+}
@@ -0,0 +1,208 @@
+//! Long polling a typed `GET` route that carries a cursor in its query.
+//!
+//! The client repeatedly requests the route with the cursor from its last response, and the
+//! server holds each request open until there is something past that cursor.
+
+use std::{convert::Infallible, future::Future, marker::PhantomData, sync::Arc, time::Duration};
+
+use futures_util::Stream;
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::{
+    BodyError, ClientTransport, JsonResponse, NoBody, Query, RequestBuildError, RequestBuilder,
+    Route, TransportResponse,
+};
+
+/// Implemented by a long-polled route's query, to ask for what comes after a cursor.
+pub trait PollQuery {
+    type Cursor: Clone;
+
+    fn after(cursor: Self::Cursor) -> Self;
+}
+
+/// Implemented by a long-polled route's response, to say where the next poll should start.
+pub trait PollResponse<Cursor> {
+    fn cursor(&self) -> Cursor;
+}
+
+/// An error from one poll of a [Poller].
+#[derive(Debug, thiserror::Error)]
+pub enum PollError<TransportError, BodyTransportError> {
+    #[error("Failed to build the poll request")]
+    Build(#[source] RequestBuildError<serde_urlencoded::ser::Error, Infallible>),
+    #[error("Failed to send the poll request")]
+    Transport(#[source] TransportError),
+    #[error("Poll failed with status {0}")]
+    Status(http::StatusCode),
+    #[error("Failed to read the poll response")]
+    Body(#[source] BodyError<serde_json::Error, BodyTransportError>),
+}
+
+type PollResult<T, Transport> = Result<
+    T,
+    PollError<
+        <Transport as ClientTransport>::Error,
+        <<Transport as ClientTransport>::Response as TransportResponse>::Error,
+    >,
+>;
+
+/// Polls `Route` with `T`, waiting between polls with `S`, e.g. `tokio::time::sleep` or
+/// `gloo_timers::future::sleep`.
+///
+/// Use [Poller::next] as an async iterator, or [Poller::into_stream].
+pub struct Poller<'a, Route, Q: PollQuery, T, S> {
+    _marker: PhantomData<fn() -> Route>,
+    transport: &'a T,
+    sleep: S,
+    cursor: Q::Cursor,
+    interval: Duration,
+    max_backoff: Duration,
+    backoff: Option<Duration>,
+    first: bool,
+}
+
+/// Start long polling `Route` from `cursor`, waiting `interval` between successful polls.
+pub fn poll<Route, Q, T, S, Fut>(
+    transport: &T,
+    cursor: Q::Cursor,
+    interval: Duration,
+    sleep: S,
+) -> Poller<'_, Route, Q, T, S>
+where
+    Route: self::Route<Query = Query<Q>, RequestBody = NoBody>,
+    Q: PollQuery + Serialize,
+    T: ClientTransport,
+    S: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    Poller {
+        _marker: PhantomData,
+        transport,
+        sleep,
+        cursor,
+        interval,
+        max_backoff: Duration::from_secs(60),
+        backoff: None,
+        first: true,
+    }
+}
+
+impl<'a, Route, Q, T, S, Fut> Poller<'a, Route, Q, T, S>
+where
+    Route: self::Route<Query = Query<Q>, RequestBody = NoBody>,
+    Route::ResponseBody: JsonResponse,
+    <Route::ResponseBody as JsonResponse>::Output: PollResponse<Q::Cursor>,
+    Q: PollQuery + Serialize,
+    T: ClientTransport,
+    S: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    /// The longest to wait after repeated failures, doubling from the interval. Defaults to a
+    /// minute.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Wait for the interval, or the backoff after an error, then poll once.
+    pub async fn next(&mut self) -> PollResult<<Route::ResponseBody as JsonResponse>::Output, T> {
+        if !self.first {
+            (self.sleep)(self.backoff.unwrap_or(self.interval)).await;
+        }
+        self.first = false;
+
+        let result = self.poll_once().await;
+        match &result {
+            Ok(output) => {
+                self.cursor = output.cursor();
+                self.backoff = None;
+            }
+            Err(_) => {
+                let backoff = match self.backoff {
+                    Some(backoff) => backoff * 2,
+                    None => self.interval.max(Duration::from_secs(1)),
+                };
+                self.backoff = Some(backoff.min(self.max_backoff));
+            }
+        }
+        result
+    }
+
+    async fn poll_once(&self) -> PollResult<<Route::ResponseBody as JsonResponse>::Output, T> {
+        let request = RequestBuilder::<Route, _, _>::new()
+            .query(Q::after(self.cursor.clone()))
+            .build()
+            .map_err(PollError::Build)?;
+        let response = request
+            .send_with(self.transport)
+            .await
+            .map_err(PollError::Transport)?;
+        if !response.ok() {
+            return Err(PollError::Status(response.status()));
+        }
+        response.json().await.map_err(PollError::Body)
+    }
+
+    /// Poll forever, as a stream of each poll's result.
+    pub fn into_stream(
+        self,
+    ) -> impl Stream<Item = PollResult<<Route::ResponseBody as JsonResponse>::Output, T>> + 'a
+    where
+        Route: 'a,
+        Q: 'a,
+        S: 'a,
+    {
+        futures_util::stream::unfold(self, |mut poller| async move {
+            let result = poller.next().await;
+            Some((result, poller))
+        })
+    }
+}
+
+/// A version number that long polling handlers wait on, bumped whenever what they serve
+/// changes. Cloning it shares the version.
+#[derive(Debug, Clone)]
+pub struct Changes {
+    sender: Arc<watch::Sender<u64>>,
+}
+
+impl Default for Changes {
+    fn default() -> Self {
+        Changes {
+            sender: Arc::new(watch::Sender::new(0)),
+        }
+    }
+}
+
+impl Changes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn version(&self) -> u64 {
+        *self.sender.borrow()
+    }
+
+    /// Record a change, waking every handler waiting on an earlier version.
+    pub fn bump(&self) -> u64 {
+        let mut version = 0;
+        self.sender.send_modify(|current| {
+            *current += 1;
+            version = *current;
+        });
+        version
+    }
+
+    /// Wait until the version is past `seen`, or for at most `timeout`, and return the
+    /// current version.
+    ///
+    /// A handler responds with whatever is past the client's cursor once this returns, which
+    /// may be nothing if it timed out.
+    pub async fn wait_past(&self, seen: u64, timeout: Duration) -> u64 {
+        let mut receiver = self.sender.subscribe();
+        let changed = receiver.wait_for(|version| *version > seen);
+        let _ = actix_web::rt::time::timeout(timeout, changed).await;
+        self.version()
+    }
+}
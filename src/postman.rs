@@ -0,0 +1,337 @@
+//! Exporting routes as a [Postman](https://www.postman.com/) collection, so they can be tried
+//! out without writing a client. Insomnia imports Postman collections too.
+//!
+//! Requests are sent to `{{baseUrl}}`, a collection variable, and path parameters become
+//! Postman path variables, e.g. `/orders/:id`.
+
+use http::StatusCode;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    examples::RouteExamples, links::placeholder, FormBody, JsonBody, NoBody, NoQuery, Query, Route,
+};
+
+/// The schema Postman collections are checked against when they're imported.
+const COLLECTION_SCHEMA: &str =
+    "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Implemented by query and body types that can give an example value for a collection.
+pub trait Example {
+    /// The example, or `None` if the type has no content.
+    fn example() -> Option<Value>;
+}
+
+impl Example for NoQuery {
+    fn example() -> Option<Value> {
+        None
+    }
+}
+
+impl Example for NoBody {
+    fn example() -> Option<Value> {
+        None
+    }
+}
+
+/// The example is the query's [Default] value.
+impl<T: Default + Serialize, F> Example for Query<T, F> {
+    fn example() -> Option<Value> {
+        serde_json::to_value(T::default()).ok()
+    }
+}
+
+/// The example is the body's [Default] value.
+impl<T: Default + Serialize> Example for JsonBody<T> {
+    fn example() -> Option<Value> {
+        serde_json::to_value(T::default()).ok()
+    }
+}
+
+/// The example is the body's [Default] value.
+impl<T: Default + Serialize> Example for FormBody<T> {
+    fn example() -> Option<Value> {
+        serde_json::to_value(T::default()).ok()
+    }
+}
+
+/// How a request body is sent by Postman.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyMode {
+    Json,
+    UrlEncoded,
+}
+
+/// Implemented by request body types that Postman can send.
+pub trait CollectionBody {
+    /// How the body is sent, or `None` if requests have no body.
+    const MODE: Option<BodyMode>;
+}
+
+impl CollectionBody for NoBody {
+    const MODE: Option<BodyMode> = None;
+}
+
+impl<T> CollectionBody for JsonBody<T> {
+    const MODE: Option<BodyMode> = Some(BodyMode::Json);
+}
+
+impl<T> CollectionBody for FormBody<T> {
+    const MODE: Option<BodyMode> = Some(BodyMode::UrlEncoded);
+}
+
+/// A Postman collection (format v2.1) with a request for each added route.
+pub struct Collection {
+    name: String,
+    base_url: String,
+    items: Vec<Value>,
+}
+
+impl Collection {
+    /// An empty collection called `name`, whose `{{baseUrl}}` variable starts out as
+    /// `base_url`.
+    pub fn new(name: impl Into<String>, base_url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            base_url: base_url.into(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Add a request for `R`, with its query and body filled in from their [Example]s.
+    pub fn route<R>(self) -> Self
+    where
+        R: Route,
+        R::Query: Example,
+        R::RequestBody: Example + CollectionBody,
+    {
+        self.with_examples::<R>(R::Query::example(), R::RequestBody::example(), None)
+    }
+
+    /// Add a request for `R`, with its query and body filled in from its [RouteExamples], and
+    /// its example response saved alongside it.
+    pub fn example_route<R>(self) -> Self
+    where
+        R: RouteExamples,
+        R::RequestBody: CollectionBody,
+    {
+        let examples = R::examples();
+        let response = examples.response_body.map(|body| (examples.status, body));
+        self.with_examples::<R>(examples.query, examples.request_body, response)
+    }
+
+    /// Add a request for `R`, with its query and body filled in from their JSON schemas, for
+    /// types without a meaningful [Default].
+    ///
+    /// Schemas' own `examples` and `default`s are used where they have them, otherwise every
+    /// field is given an empty value of its type.
+    #[cfg(feature = "schema")]
+    pub fn described_route<R>(self) -> Self
+    where
+        R: Route,
+        R::Query: crate::schema::DescribeSchema,
+        R::RequestBody: crate::schema::DescribeSchema + CollectionBody,
+    {
+        use crate::schema::DescribeSchema;
+
+        let query = R::Query::schema().map(|schema| schema_example(&schema));
+        let body = R::RequestBody::schema().map(|schema| schema_example(&schema));
+        self.with_examples::<R>(query, body, None)
+    }
+
+    fn with_examples<R>(
+        mut self,
+        query: Option<Value>,
+        body: Option<Value>,
+        response: Option<(StatusCode, Value)>,
+    ) -> Self
+    where
+        R: Route,
+        R::RequestBody: CollectionBody,
+    {
+        let path: Vec<String> = R::URI
+            .trim_start_matches('/')
+            .split('/')
+            .map(|segment| match placeholder(segment) {
+                Some(name) => format!(":{name}"),
+                None => segment.to_owned(),
+            })
+            .collect();
+        let variables: Vec<Value> = R::URI
+            .split('/')
+            .filter_map(placeholder)
+            .map(|name| json!({ "key": name, "value": "" }))
+            .collect();
+        let query = query.map(|query| key_values(&query)).unwrap_or_default();
+        let raw_query = query
+            .iter()
+            .map(|pair| {
+                format!(
+                    "{}={}",
+                    pair["key"].as_str().unwrap_or_default(),
+                    pair["value"].as_str().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        let mut raw = format!("{{{{baseUrl}}}}/{}", path.join("/"));
+        if !raw_query.is_empty() {
+            raw = format!("{raw}?{raw_query}");
+        }
+
+        let mut headers = Vec::new();
+        if let Some(media_type) = R::MEDIA_TYPE {
+            headers.push(json!({ "key": "Accept", "value": media_type }));
+        }
+        let body = match (R::RequestBody::MODE, body) {
+            (Some(BodyMode::Json), Some(body)) => {
+                let content_type = R::MEDIA_TYPE.unwrap_or("application/json");
+                headers.push(json!({ "key": "Content-Type", "value": content_type }));
+                Some(json!({
+                    "mode": "raw",
+                    "raw": serde_json::to_string_pretty(&body).unwrap_or_default(),
+                    "options": { "raw": { "language": "json" } },
+                }))
+            }
+            (Some(BodyMode::UrlEncoded), Some(body)) => {
+                let content_type = R::MEDIA_TYPE.unwrap_or("application/x-www-form-urlencoded");
+                headers.push(json!({ "key": "Content-Type", "value": content_type }));
+                Some(json!({ "mode": "urlencoded", "urlencoded": key_values(&body) }))
+            }
+            _ => None,
+        };
+
+        let mut request = json!({
+            "method": R::METHOD.as_str(),
+            "header": headers,
+            "url": {
+                "raw": raw,
+                "host": ["{{baseUrl}}"],
+                "path": path,
+                "query": query,
+                "variable": variables,
+            },
+        });
+        if let Some(body) = body {
+            request["body"] = body;
+        }
+        if !R::REQUIRED_SCOPES.is_empty() {
+            request["description"] =
+                format!("Requires the scopes {}", R::REQUIRED_SCOPES.join(", ")).into();
+        }
+        let mut item = json!({ "name": R::NAME, "request": request });
+        if let Some((status, body)) = response {
+            let content_type = R::MEDIA_TYPE.unwrap_or("application/json");
+            item["response"] = json!([{
+                "name": "Example",
+                "originalRequest": item["request"].clone(),
+                "code": status.as_u16(),
+                "status": status.canonical_reason().unwrap_or_default(),
+                "header": [{ "key": "Content-Type", "value": content_type }],
+                "body": serde_json::to_string_pretty(&body).unwrap_or_default(),
+                "_postman_previewlanguage": "json",
+            }]);
+        }
+        self.items.push(item);
+        self
+    }
+
+    /// The collection in Postman's JSON format, ready to be saved and imported.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "info": {
+                "name": self.name,
+                "schema": COLLECTION_SCHEMA,
+            },
+            "item": self.items,
+            "variable": [{ "key": "baseUrl", "value": self.base_url }],
+        })
+    }
+}
+
+/// The fields of an example object as Postman key/value pairs, with values written the way
+/// they would be in a query string.
+fn key_values(example: &Value) -> Vec<Value> {
+    let Some(fields) = example.as_object() else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            json!({ "key": key, "value": value })
+        })
+        .collect()
+}
+
+/// An example value for a schema generated by schemars.
+#[cfg(feature = "schema")]
+fn schema_example(schema: &Value) -> Value {
+    let definitions = schema
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    example_of(schema, &definitions, 0)
+}
+
+/// Deep enough for any reasonable type, but stops recursive ones.
+#[cfg(feature = "schema")]
+const MAX_SCHEMA_DEPTH: usize = 8;
+
+#[cfg(feature = "schema")]
+fn example_of(schema: &Value, definitions: &serde_json::Map<String, Value>, depth: usize) -> Value {
+    if depth > MAX_SCHEMA_DEPTH {
+        return Value::Null;
+    }
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        return definitions
+            .get(name)
+            .map(|schema| example_of(schema, definitions, depth + 1))
+            .unwrap_or(Value::Null);
+    }
+    if let Some(example) = schema
+        .get("examples")
+        .and_then(|examples| examples.get(0))
+        .or_else(|| schema.get("default"))
+        .or_else(|| schema.get("enum").and_then(|values| values.get(0)))
+    {
+        return example.clone();
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        if let Some(first) = schema.get(keyword).and_then(|schemas| schemas.get(0)) {
+            return example_of(first, definitions, depth + 1);
+        }
+    }
+
+    let kind = match schema.get("type") {
+        Some(Value::Array(kinds)) => kinds.iter().find(|kind| *kind != "null"),
+        kind => kind,
+    };
+    match kind.and_then(Value::as_str) {
+        Some("string") => Value::String(String::new()),
+        Some("integer" | "number") => Value::from(0),
+        Some("boolean") => Value::Bool(false),
+        Some("array") => match schema.get("items") {
+            Some(items) => Value::Array(vec![example_of(items, definitions, depth + 1)]),
+            None => Value::Array(Vec::new()),
+        },
+        Some("object") => Value::Object(
+            schema
+                .get("properties")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .map(|(name, property)| {
+                    (name.clone(), example_of(property, definitions, depth + 1))
+                })
+                .collect(),
+        ),
+        _ => Value::Null,
+    }
+}
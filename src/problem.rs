@@ -0,0 +1,133 @@
+//! Errors sent as RFC 7807 Problem Details, `application/problem+json`.
+
+use std::fmt::{self, Display};
+
+use actix_web::{HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{Response, TransportResponse};
+
+/// The content type problem details are sent with.
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// A machine readable description of an error.
+///
+/// Returning one from a handler as its error sends it with its `status`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProblemDetails {
+    /// A URI identifying the kind of problem.
+    #[serde(rename = "type", default = "about_blank")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// An explanation specific to this occurrence of the problem.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// A URI identifying this occurrence of the problem.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Any other members, specific to the kind of problem.
+    #[serde(flatten)]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
+}
+
+fn about_blank() -> String {
+    "about:blank".to_owned()
+}
+
+impl ProblemDetails {
+    /// A problem with no particular kind, described by `status`'s reason phrase.
+    pub fn new(status: http::StatusCode) -> Self {
+        ProblemDetails {
+            kind: about_blank(),
+            title: status.canonical_reason().map(str::to_owned),
+            status: Some(status.as_u16()),
+            detail: None,
+            instance: None,
+            extensions: serde_json::Map::new(),
+        }
+    }
+
+    pub fn kind(mut self, kind: &str, title: &str) -> Self {
+        self.kind = kind.to_owned();
+        self.title = Some(title.to_owned());
+        self
+    }
+
+    pub fn detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_owned());
+        self
+    }
+
+    pub fn instance(mut self, instance: &str) -> Self {
+        self.instance = Some(instance.to_owned());
+        self
+    }
+
+    pub fn extension(mut self, name: &str, value: serde_json::Value) -> Self {
+        self.extensions.insert(name.to_owned(), value);
+        self
+    }
+}
+
+impl Display for ProblemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.title.as_deref().unwrap_or(&self.kind))?;
+        if let Some(detail) = &self.detail {
+            write!(f, ": {detail}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ProblemDetails {}
+
+impl ResponseError for ProblemDetails {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        self.status
+            .and_then(|status| actix_web::http::StatusCode::from_u16(status).ok())
+            .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match serde_json::to_vec(self) {
+            Ok(body) => HttpResponse::build(self.status_code())
+                .content_type(CONTENT_TYPE)
+                .body(body),
+            Err(_) => HttpResponse::new(self.status_code()),
+        }
+    }
+}
+
+/// The error returned by [Response::problem] for an unsuccessful response.
+#[derive(Debug, Error)]
+pub enum ProblemError<TransportError> {
+    #[error("Request failed: {0}")]
+    Problem(ProblemDetails),
+    /// The response wasn't successful, but didn't describe the problem.
+    #[error("Request failed with status {0}")]
+    Status(http::StatusCode),
+    #[error("Failed to receive the problem details")]
+    Transport(#[source] TransportError),
+}
+
+impl<Route: crate::Route, R: TransportResponse> Response<Route, R> {
+    /// Fail if the response wasn't successful, with its problem details if it sent some.
+    pub async fn problem(self) -> Result<Self, ProblemError<R::Error>> {
+        if self.ok() {
+            return Ok(self);
+        }
+        let status = self.status();
+        if self.content_type().as_deref() != Some(CONTENT_TYPE) {
+            return Err(ProblemError::Status(status));
+        }
+        let body = self.body().await.map_err(ProblemError::Transport)?;
+        match serde_json::from_slice(&body) {
+            Ok(problem) => Err(ProblemError::Problem(problem)),
+            Err(_) => Err(ProblemError::Status(status)),
+        }
+    }
+}
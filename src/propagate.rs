@@ -0,0 +1,220 @@
+//! Calling other services' typed routes from within a handler, carrying the inbound request's
+//! trace headers and remaining deadline along, and giving client requests a deadline to begin with.
+
+use std::{
+    future::{ready, Future, Ready},
+    pin::pin,
+    time::{Duration, Instant},
+};
+
+use actix_web::HttpMessage;
+use bytes::Bytes;
+use futures_util::future::{select, Either};
+use thiserror::Error;
+
+use crate::{
+    request_id::{RequestId, REQUEST_ID_HEADER, TRACEPARENT_HEADER},
+    ClientTransport, FromRequest,
+};
+
+/// The header carrying how many milliseconds the caller will wait for the response.
+pub const DEADLINE_HEADER: &str = "x-deadline-ms";
+
+const TRACESTATE_HEADER: &str = "tracestate";
+
+/// An extractor for the caller's deadline, read from its [DEADLINE_HEADER] when the request
+/// arrived.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline(pub Option<Instant>);
+
+impl Deadline {
+    fn from_headers(headers: &actix_web::http::header::HeaderMap) -> Self {
+        let deadline = headers
+            .get(DEADLINE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|millis| millis.parse().ok())
+            .map(|millis| Instant::now() + Duration::from_millis(millis));
+        Deadline(deadline)
+    }
+
+    /// How long the caller will keep waiting, or `None` if it didn't send a deadline.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether the caller has already given up on the response.
+    pub fn expired(&self) -> bool {
+        self.remaining()
+            .is_some_and(|remaining| remaining.is_zero())
+    }
+}
+
+impl actix_web::FromRequest for Deadline {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        ready(Ok(Deadline::from_headers(req.headers())))
+    }
+}
+
+impl<Query, Body> FromRequest<Query, Body> for Deadline {}
+
+/// An extractor for what outbound calls made while handling a request should carry: its trace
+/// headers and, if the caller sent one, its deadline.
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    headers: Vec<(&'static str, String)>,
+    deadline: Option<Instant>,
+}
+
+impl Upstream {
+    /// When the caller stops waiting for the response.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Wrap `transport` so that requests sent with it carry this request's context.
+    pub fn transport<T: ClientTransport>(&self, transport: T) -> Propagating<T> {
+        Propagating {
+            inner: transport,
+            upstream: self.clone(),
+        }
+    }
+}
+
+impl actix_web::FromRequest for Upstream {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+
+        let mut headers = Vec::new();
+        let request_id = match req.extensions().get::<RequestId>() {
+            Some(id) => Some(id.0.clone()),
+            None => header(REQUEST_ID_HEADER),
+        };
+        if let Some(id) = request_id {
+            headers.push((REQUEST_ID_HEADER, id));
+        }
+        for name in [TRACEPARENT_HEADER, TRACESTATE_HEADER] {
+            if let Some(value) = header(name) {
+                headers.push((name, value));
+            }
+        }
+
+        let Deadline(deadline) = Deadline::from_headers(req.headers());
+
+        ready(Ok(Upstream { headers, deadline }))
+    }
+}
+
+impl<Query, Body> FromRequest<Query, Body> for Upstream {}
+
+/// The error returned by a [Propagating] or [WithDeadline] transport.
+#[derive(Debug, Error)]
+pub enum PropagateError<TransportError> {
+    #[error("The request's deadline has passed")]
+    DeadlineExceeded,
+    #[error("Failed to send the request")]
+    Transport(#[source] TransportError),
+}
+
+/// A [ClientTransport] that adds an [Upstream]'s trace headers and remaining deadline to each
+/// request, and gives up on requests that outlive the deadline.
+#[derive(Debug, Clone)]
+pub struct Propagating<T> {
+    inner: T,
+    upstream: Upstream,
+}
+
+impl<T: ClientTransport> ClientTransport for Propagating<T> {
+    type Response = T::Response;
+    type Error = PropagateError<T::Error>;
+
+    async fn send(&self, mut request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let headers = request.headers_mut();
+        for (name, value) in &self.upstream.headers {
+            if let Ok(value) = http::HeaderValue::try_from(value) {
+                headers.insert(*name, value);
+            }
+        }
+
+        let Some(deadline) = self.upstream.deadline else {
+            return self
+                .inner
+                .send(request)
+                .await
+                .map_err(PropagateError::Transport);
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(PropagateError::DeadlineExceeded);
+        }
+        headers.insert(
+            DEADLINE_HEADER,
+            http::HeaderValue::from(remaining.as_millis() as u64),
+        );
+
+        match actix_web::rt::time::timeout(remaining, self.inner.send(request)).await {
+            Ok(result) => result.map_err(PropagateError::Transport),
+            Err(_) => Err(PropagateError::DeadlineExceeded),
+        }
+    }
+}
+
+/// A [ClientTransport] that gives each request a timeout budget, sent along in the
+/// [DEADLINE_HEADER] so the server can stop working once the client has given up.
+///
+/// `sleep` should return a future that completes after the given duration, using whichever
+/// runtime the client runs on.
+#[derive(Debug, Clone)]
+pub struct WithDeadline<T, S> {
+    inner: T,
+    timeout: Duration,
+    sleep: S,
+}
+
+impl<T, S> WithDeadline<T, S> {
+    pub fn new(inner: T, timeout: Duration, sleep: S) -> Self {
+        WithDeadline {
+            inner,
+            timeout,
+            sleep,
+        }
+    }
+}
+
+impl<T: ClientTransport, S: Fn(Duration) -> F, F: Future<Output = ()>> ClientTransport
+    for WithDeadline<T, S>
+{
+    type Response = T::Response;
+    type Error = PropagateError<T::Error>;
+
+    async fn send(&self, mut request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        request.headers_mut().insert(
+            DEADLINE_HEADER,
+            http::HeaderValue::from(self.timeout.as_millis() as u64),
+        );
+
+        let send = pin!(self.inner.send(request));
+        let sleep = pin!((self.sleep)(self.timeout));
+        match select(send, sleep).await {
+            Either::Left((result, _)) => result.map_err(PropagateError::Transport),
+            Either::Right(_) => Err(PropagateError::DeadlineExceeded),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Masking the values of a route's [Route::SENSITIVE_FIELDS](crate::Route::SENSITIVE_FIELDS),
+//! given with the `sensitive: ["password"]` route option.
+//!
+//! The crate's own observability features use these: debug logging, the response bodies kept
+//! in errors, recorded fixtures and audit events. Fields are matched by name at any depth.
+
+use bytes::Bytes;
+use serde_json::Value;
+
+/// What sensitive values are replaced with.
+pub const MASK: &str = "***";
+
+/// A URL encoded query string's pairs, with sensitive values masked.
+pub fn redact_pairs(query: &str, sensitive: &[&str]) -> Vec<(String, String)> {
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, value)| {
+            if sensitive.contains(&key.as_str()) {
+                (key, MASK.to_owned())
+            } else {
+                (key, value)
+            }
+        })
+        .collect()
+}
+
+/// A URL encoded query string or form with sensitive values masked. It's returned unchanged
+/// if there are no sensitive fields.
+pub fn redact_query(query: &str, sensitive: &[&str]) -> String {
+    if sensitive.is_empty() {
+        return query.to_owned();
+    }
+    serde_urlencoded::to_string(redact_pairs(query, sensitive)).unwrap_or_default()
+}
+
+/// Mask the values of sensitive fields anywhere in `value`.
+pub fn redact_json(value: &mut Value, sensitive: &[&str]) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                if sensitive.contains(&key.as_str()) {
+                    *value = Value::String(MASK.to_owned());
+                } else {
+                    redact_json(value, sensitive);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                redact_json(value, sensitive);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A request or response body with sensitive values masked, if it's JSON or a URL encoded
+/// form. Other bodies, and any body when there are no sensitive fields, are returned
+/// unchanged.
+pub fn redact_body(headers: &http::HeaderMap, body: &Bytes, sensitive: &[&str]) -> Bytes {
+    if sensitive.is_empty() || body.is_empty() {
+        return body.clone();
+    }
+    if let Ok(mut json) = serde_json::from_slice::<Value>(body) {
+        redact_json(&mut json, sensitive);
+        return serde_json::to_vec(&json).map_or_else(|_| body.clone(), Bytes::from);
+    }
+    if is_form(headers) {
+        return redact_query(&String::from_utf8_lossy(body), sensitive).into();
+    }
+    body.clone()
+}
+
+pub(crate) fn is_form(headers: &http::HeaderMap) -> bool {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"))
+}
+
+/// A URI with the sensitive values in its query masked.
+pub(crate) fn redact_uri(uri: &http::Uri, sensitive: &[&str]) -> String {
+    match uri.query() {
+        Some(query) if !sensitive.is_empty() => {
+            let path = uri.to_string();
+            let path = path.split('?').next().unwrap_or_default();
+            format!("{path}?{}", redact_query(query, sensitive))
+        }
+        _ => uri.to_string(),
+    }
+}
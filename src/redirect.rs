@@ -0,0 +1,522 @@
+//! Redirects: following them on transports that don't, and typed routes whose response is a
+//! redirect to another route.
+
+use std::marker::PhantomData;
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{
+    links::{fill_uri, matches_uri, LinkError},
+    ClientTransport, IntoResponse, NoBody, NoCheckResponse, NoQuery, RequestBuilder, Response,
+    TransportResponse,
+};
+
+/// A response body type for routes that respond by redirecting to `Target`, e.g. a route that
+/// creates something and redirects to it.
+pub struct Redirect<Target>(PhantomData<Target>);
+
+/// A redirect response, from [Response::redirected].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirected {
+    pub status: http::StatusCode,
+    pub location: String,
+}
+
+impl<Route: crate::Route, R: TransportResponse> Response<Route, R> {
+    /// The redirect this response is, if it is one.
+    ///
+    /// Browsers follow redirects themselves, so with [Gloo](crate::gloo::Gloo) this only sees
+    /// redirects the browser couldn't follow.
+    pub fn redirected(&self) -> Option<Redirected> {
+        if !self.status().is_redirection() {
+            return None;
+        }
+        let location = self.headers().get(http::header::LOCATION)?.to_str().ok()?;
+        Some(Redirected {
+            status: self.status(),
+            location: location.to_owned(),
+        })
+    }
+}
+
+/// The error returned by [Response::redirect_target].
+#[derive(Debug, Error)]
+pub enum RedirectTargetError {
+    #[error("Expected a redirect, got status {0}")]
+    NotRedirected(http::StatusCode),
+    #[error("Redirect to {location} doesn't point at route {route}")]
+    WrongRoute {
+        location: String,
+        route: &'static str,
+    },
+}
+
+impl<Target: crate::Route, Route: crate::Route<ResponseBody = Redirect<Target>>, R>
+    Response<Route, R>
+where
+    R: TransportResponse,
+{
+    /// A request to the route the response redirected to, with the path params and query
+    /// from its `Location`.
+    pub fn redirect_target(
+        &self,
+    ) -> Result<RequestBuilder<Target, NoQuery, NoBody>, RedirectTargetError> {
+        let redirected = self
+            .redirected()
+            .ok_or(RedirectTargetError::NotRedirected(self.status()))?;
+        let uri: http::Uri =
+            redirected
+                .location
+                .parse()
+                .map_err(|_| RedirectTargetError::WrongRoute {
+                    location: redirected.location.clone(),
+                    route: Target::NAME,
+                })?;
+        if !matches_uri(Target::URI, uri.path()) {
+            return Err(RedirectTargetError::WrongRoute {
+                location: redirected.location,
+                route: Target::NAME,
+            });
+        }
+        let mut builder = RequestBuilder::with_uri(uri.path());
+        if let Some(query) = uri.query() {
+            builder.head.append_query(query);
+        }
+        Ok(builder)
+    }
+}
+
+/// A responder for routes declared with [Redirect], pointing at `Target`.
+pub struct RedirectTo<Target> {
+    _marker: PhantomData<Target>,
+    status: actix_web::http::StatusCode,
+    location: String,
+}
+
+impl<Target: crate::Route> RedirectTo<Target> {
+    /// A `303 See Other` to `Target`, filling in its URI's `{name}` segments from `params`.
+    pub fn new(params: &[(&str, &str)]) -> Result<Self, LinkError> {
+        let location = fill_uri(Target::URI, |name| {
+            params
+                .iter()
+                .find(|(param, _)| *param == name)
+                .map(|(_, value)| *value)
+        })?;
+        Ok(RedirectTo {
+            _marker: PhantomData,
+            status: actix_web::http::StatusCode::SEE_OTHER,
+            location,
+        })
+    }
+
+    /// Redirect with `308 Permanent Redirect` instead.
+    pub fn permanent(mut self) -> Self {
+        self.status = actix_web::http::StatusCode::PERMANENT_REDIRECT;
+        self
+    }
+
+    /// Redirect with `307 Temporary Redirect` instead, which keeps the request's method.
+    pub fn temporary(mut self) -> Self {
+        self.status = actix_web::http::StatusCode::TEMPORARY_REDIRECT;
+        self
+    }
+}
+
+impl<Target> actix_web::Responder for RedirectTo<Target> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &actix_web::HttpRequest) -> actix_web::HttpResponse<Self::Body> {
+        actix_web::HttpResponse::build(self.status)
+            .insert_header((actix_web::http::header::LOCATION, self.location))
+            .finish()
+    }
+}
+
+impl<Target> IntoResponse<Redirect<Target>> for RedirectTo<Target> {}
+
+impl<Target, T: IntoResponse<Redirect<Target>>, E> IntoResponse<Redirect<Target>> for Result<T, E> {}
+
+impl<Target, T> IntoResponse<Redirect<Target>> for NoCheckResponse<T> {}
+
+/// The error returned by [FollowRedirects].
+#[derive(Debug, Error)]
+pub enum FollowError<TransportError> {
+    #[error("Failed to send the request")]
+    Transport(#[source] TransportError),
+    #[error("Gave up after {0} redirects")]
+    TooManyRedirects(usize),
+    #[error("The redirect location {0} isn't a valid URI")]
+    BadLocation(String),
+    #[error("Refused to follow a redirect from HTTPS to {0}")]
+    Downgrade(String),
+}
+
+/// A [ClientTransport] that follows redirects for transports that don't, e.g.
+/// [Hyper](crate::hyper::Hyper), up to a limit.
+///
+/// `303 See Other`, and `301` or `302` in response to a `POST`, are followed with a `GET`
+/// without a body, as browsers do. Redirects to another origin are followed without the
+/// request's credentials, e.g. its `Authorization` and `Cookie` headers, and redirects from
+/// HTTPS to HTTP aren't followed.
+pub struct FollowRedirects<T> {
+    inner: T,
+    max_redirects: usize,
+}
+
+impl<T> FollowRedirects<T> {
+    pub fn new(inner: T) -> Self {
+        FollowRedirects {
+            inner,
+            max_redirects: 10,
+        }
+    }
+
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+}
+
+/// The headers holding a request's credentials, which aren't sent on to another origin.
+const CREDENTIAL_HEADERS: [http::HeaderName; 3] = [
+    http::header::AUTHORIZATION,
+    http::header::COOKIE,
+    http::header::PROXY_AUTHORIZATION,
+];
+
+/// The parts of a URI reference, as split by RFC 3986 appendix B. The fragment is dropped, as
+/// it isn't sent.
+struct Reference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+}
+
+impl<'a> Reference<'a> {
+    fn parse(reference: &'a str) -> Self {
+        let reference = reference.split('#').next().unwrap_or_default();
+        let (scheme, rest) = match reference.split_once(':') {
+            Some((scheme, rest))
+                if scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) =>
+            {
+                (Some(scheme), rest)
+            }
+            _ => (None, reference),
+        };
+        let (authority, rest) = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let end = rest.find(['/', '?']).unwrap_or(rest.len());
+                (Some(&rest[..end]), &rest[end..])
+            }
+            None => (None, rest),
+        };
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+        Reference {
+            scheme,
+            authority,
+            path,
+            query,
+        }
+    }
+}
+
+/// Resolve `location` against the URI of the request that was redirected, as in RFC 3986
+/// section 5.2.
+fn resolve(base: &http::Uri, location: &str) -> Option<http::Uri> {
+    let reference = Reference::parse(location);
+    let base_path = base.path();
+    let (scheme, authority, path, query) = match reference {
+        Reference {
+            scheme: Some(scheme),
+            authority,
+            path,
+            query,
+        } => (Some(scheme), authority, remove_dot_segments(path), query),
+        Reference {
+            authority: Some(authority),
+            path,
+            query,
+            ..
+        } => (
+            base.scheme_str(),
+            Some(authority),
+            remove_dot_segments(path),
+            query,
+        ),
+        Reference {
+            path: "", query, ..
+        } => (
+            base.scheme_str(),
+            base.authority().map(|authority| authority.as_str()),
+            base_path.to_owned(),
+            query.or(base.query()),
+        ),
+        Reference { path, query, .. } => {
+            let path = if path.starts_with('/') {
+                path.to_owned()
+            } else {
+                // Merge the reference with the base path's directory.
+                let directory = base_path.rfind('/').map_or("/", |end| &base_path[..=end]);
+                format!("{directory}{path}")
+            };
+            (
+                base.scheme_str(),
+                base.authority().map(|authority| authority.as_str()),
+                remove_dot_segments(&path),
+                query,
+            )
+        }
+    };
+
+    let mut uri = String::new();
+    if let Some(authority) = authority {
+        // http::Uri can't hold an authority without a scheme.
+        uri = format!("{}://{authority}", scheme?);
+    }
+    uri.push_str(if path.is_empty() { "/" } else { &path });
+    if let Some(query) = query {
+        uri = format!("{uri}?{query}");
+    }
+    uri.parse().ok()
+}
+
+/// Remove the `.` and `..` segments from `path`, as in RFC 3986 section 5.2.4.
+fn remove_dot_segments(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').collect();
+    let mut output: Vec<&str> = Vec::new();
+    for (i, &segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match segment {
+            "." | ".." => {
+                // The empty segment before a leading `/` can't be removed.
+                if segment == ".." && output != [""] {
+                    output.pop();
+                }
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+    output.join("/")
+}
+
+/// Whether `a` and `b` have the same scheme, host and port.
+fn same_origin(a: &http::Uri, b: &http::Uri) -> bool {
+    let port = |uri: &http::Uri| {
+        uri.port_u16().or(match uri.scheme_str() {
+            Some("http") => Some(80),
+            Some("https") => Some(443),
+            _ => None,
+        })
+    };
+    a.scheme() == b.scheme() && a.host() == b.host() && port(a) == port(b)
+}
+
+impl<T: ClientTransport> ClientTransport for FollowRedirects<T> {
+    type Response = T::Response;
+    type Error = FollowError<T::Error>;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let mut request = request;
+        let mut redirects = 0;
+        loop {
+            let next = http::Request::builder()
+                .method(request.method().clone())
+                .uri(request.uri().clone())
+                .body(request.body().clone())
+                .map(|mut next| {
+                    *next.headers_mut() = request.headers().clone();
+                    next
+                })
+                .expect("a copy of a valid request is valid");
+            let response = self
+                .inner
+                .send(request)
+                .await
+                .map_err(FollowError::Transport)?;
+
+            let status = response.status();
+            let location = response
+                .headers()
+                .get(http::header::LOCATION)
+                .and_then(|value| value.to_str().ok());
+            let (true, Some(location)) = (status.is_redirection(), location) else {
+                return Ok(response);
+            };
+            if redirects == self.max_redirects {
+                return Err(FollowError::TooManyRedirects(redirects));
+            }
+            redirects += 1;
+
+            let mut next = next;
+            let uri = resolve(next.uri(), location)
+                .ok_or_else(|| FollowError::BadLocation(location.to_owned()))?;
+            if next.uri().scheme() == Some(&http::uri::Scheme::HTTPS)
+                && uri.scheme() != Some(&http::uri::Scheme::HTTPS)
+            {
+                return Err(FollowError::Downgrade(uri.to_string()));
+            }
+            if !same_origin(next.uri(), &uri) {
+                for header in CREDENTIAL_HEADERS {
+                    next.headers_mut().remove(header);
+                }
+                #[cfg(feature = "webhook")]
+                next.headers_mut().remove(crate::webhook::SIGNATURE_HEADER);
+            }
+            *next.uri_mut() = uri;
+            let becomes_get = status == http::StatusCode::SEE_OTHER
+                || (matches!(
+                    status,
+                    http::StatusCode::MOVED_PERMANENTLY | http::StatusCode::FOUND
+                ) && next.method() == http::Method::POST);
+            if becomes_get {
+                *next.method_mut() = http::Method::GET;
+                *next.body_mut() = Bytes::new();
+                next.headers_mut().remove(http::header::CONTENT_TYPE);
+                next.headers_mut().remove(http::header::CONTENT_LENGTH);
+            }
+            request = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::BufferedResponse;
+
+    #[test]
+    fn resolves_references_against_the_base() {
+        let base: http::Uri = "http://a/b/c/d;p?q".parse().unwrap();
+        let resolved = |location| resolve(&base, location).unwrap().to_string();
+
+        assert_eq!(resolved("https://other/x?y"), "https://other/x?y");
+        assert_eq!(resolved("//g/x"), "http://g/x");
+        assert_eq!(resolved("/g"), "http://a/g");
+        assert_eq!(resolved("g"), "http://a/b/c/g");
+        assert_eq!(resolved("./g"), "http://a/b/c/g");
+        assert_eq!(resolved("g/"), "http://a/b/c/g/");
+        assert_eq!(resolved("g?y"), "http://a/b/c/g?y");
+        assert_eq!(resolved("?y"), "http://a/b/c/d;p?y");
+        assert_eq!(resolved(""), "http://a/b/c/d;p?q");
+        assert_eq!(resolved("#s"), "http://a/b/c/d;p?q");
+        assert_eq!(resolved(".."), "http://a/b/");
+        assert_eq!(resolved("../g"), "http://a/b/g");
+        assert_eq!(resolved("../../../g"), "http://a/g");
+        assert_eq!(resolved("/./g/../h"), "http://a/h");
+    }
+
+    #[test]
+    fn resolves_relative_references_against_a_relative_base() {
+        let base: http::Uri = "/items?page=1".parse().unwrap();
+
+        assert_eq!(
+            resolve(&base, "next?page=2").unwrap().to_string(),
+            "/next?page=2"
+        );
+        assert_eq!(
+            resolve(&base, "https://a/x").unwrap().to_string(),
+            "https://a/x"
+        );
+        assert!(resolve(&base, "//a/x").is_none());
+    }
+
+    /// Redirects every request to `location` once, then answers `200 OK`.
+    struct RedirectOnce {
+        location: &'static str,
+        sent: RefCell<Vec<http::Request<Bytes>>>,
+    }
+
+    impl ClientTransport for RedirectOnce {
+        type Response = BufferedResponse;
+        type Error = std::convert::Infallible;
+
+        async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+            let mut sent = self.sent.borrow_mut();
+            let mut headers = http::HeaderMap::new();
+            let status = if sent.is_empty() {
+                headers.insert(
+                    http::header::LOCATION,
+                    http::HeaderValue::from_static(self.location),
+                );
+                http::StatusCode::FOUND
+            } else {
+                http::StatusCode::OK
+            };
+            sent.push(request);
+            Ok(BufferedResponse {
+                status,
+                headers,
+                body: Bytes::new(),
+            })
+        }
+    }
+
+    async fn follow(
+        location: &'static str,
+    ) -> (
+        RedirectOnce,
+        Result<(), FollowError<std::convert::Infallible>>,
+    ) {
+        let transport = RedirectOnce {
+            location,
+            sent: RefCell::new(Vec::new()),
+        };
+        let request = http::Request::get("https://a/b")
+            .header(http::header::AUTHORIZATION, "Bearer token")
+            .header(http::header::COOKIE, "session=1")
+            .header(http::header::ACCEPT, "application/json")
+            .body(Bytes::new())
+            .unwrap();
+        let result = FollowRedirects::new(&transport).send(request).await;
+        (transport, result.map(|_| ()))
+    }
+
+    #[actix_web::test]
+    async fn keeps_credentials_on_the_same_origin() {
+        let (transport, result) = follow("/c").await;
+        result.unwrap();
+        let sent = transport.sent.into_inner();
+        assert_eq!(sent[1].uri(), "https://a/c");
+        assert!(sent[1].headers().contains_key(http::header::AUTHORIZATION));
+        assert!(sent[1].headers().contains_key(http::header::COOKIE));
+    }
+
+    #[actix_web::test]
+    async fn drops_credentials_for_another_origin() {
+        let (transport, result) = follow("https://b/c").await;
+        result.unwrap();
+        let sent = transport.sent.into_inner();
+        assert_eq!(sent[1].uri(), "https://b/c");
+        assert!(!sent[1].headers().contains_key(http::header::AUTHORIZATION));
+        assert!(!sent[1].headers().contains_key(http::header::COOKIE));
+        assert!(sent[1].headers().contains_key(http::header::ACCEPT));
+    }
+
+    #[actix_web::test]
+    async fn drops_credentials_for_another_port() {
+        let (transport, result) = follow("https://a:8443/c").await;
+        result.unwrap();
+        let sent = transport.sent.into_inner();
+        assert!(!sent[1].headers().contains_key(http::header::AUTHORIZATION));
+    }
+
+    #[actix_web::test]
+    async fn refuses_to_downgrade_to_http() {
+        let (transport, result) = follow("http://a/c").await;
+        assert!(matches!(result, Err(FollowError::Downgrade(_))));
+        assert_eq!(transport.sent.into_inner().len(), 1);
+    }
+}
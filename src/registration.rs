@@ -0,0 +1,50 @@
+//! Registering the handlers for a [routes](crate::routes) module's routes, checked at compile
+//! time so that a route can't be declared and then left without a handler, which would only
+//! show up as a `404` at runtime.
+//!
+//! The module gives each route's handler in an impl of [Handles]:
+//!
+//! ```
+//! # use actix_web::{App, HttpServer};
+//! # use typed_routing::{handled_by, registration::Handles, Method, Module, NoBody, NoQuery, Route, Router};
+//! # struct GetOrder;
+//! # impl Route for GetOrder {
+//! #     type Query = NoQuery;
+//! #     type RequestBody = NoBody;
+//! #     type ResponseBody = NoBody;
+//! #     const METHOD: Method = Method::GET;
+//! #     const NAME: &'static str = "GetOrder";
+//! #     const URI_PART: &'static str = "/orders/{id}";
+//! #     const URI: &'static str = "/orders/{id}";
+//! # }
+//! # struct Api;
+//! # impl Module for Api {
+//! #     fn register<R: Router>(self, router: R) -> R {
+//! #         router.typed_route::<GetOrder, _>(<Api as Handles<GetOrder>>::route)
+//! #     }
+//! # }
+//! # async fn get_order() -> &'static str { "" }
+//! impl Handles<GetOrder> for Api {
+//!     fn route() -> actix_web::Route {
+//!         handled_by::<GetOrder, _, _>(get_order).into_route()
+//!     }
+//! }
+//!
+//! HttpServer::new(|| Api.register(App::new()))
+//! # ;
+//! ```
+//!
+//! The module only implements [Module](crate::Module) once it handles every one of its routes,
+//! so while one is left out, registering the module fails to compile with an error naming it.
+
+/// Implemented by a [routes](crate::routes) module for each of its routes, to give the handler
+/// that its [Module::register](crate::Module::register) registers for the route.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` doesn't handle its route `{Route}`",
+    label = "`{Route}` is declared in `{Self}` but never registered",
+    note = "implement `Handles<{Route}>` for `{Self}` to register a handler for it"
+)]
+pub trait Handles<Route: crate::Route> {
+    /// The actix route to register, without a method guard, e.g. `web::route().to(handler)`.
+    fn route() -> actix_web::Route;
+}
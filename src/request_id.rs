@@ -0,0 +1,170 @@
+//! Request IDs for correlating a client's requests with the server's handling of them.
+//!
+//! Both ends send and accept an `X-Request-Id` header, along with a W3C `traceparent` header
+//! for tracing systems.
+
+use std::{
+    future::{ready, Future, Ready},
+    ops::Deref,
+    pin::Pin,
+    rc::Rc,
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    HttpMessage,
+};
+use bytes::Bytes;
+
+use crate::{ClientTransport, FromRequest};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// `len` random hex digits, from the OS's or, in browsers, `crypto.getRandomValues`'s random
+/// number generator.
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0; len.div_ceil(2)];
+    getrandom::getrandom(&mut bytes).expect("the random number generator is unavailable");
+    let mut hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    hex.truncate(len);
+    hex
+}
+
+fn generate_request_id() -> String {
+    random_hex(32)
+}
+
+/// A `traceparent` value that starts a new trace.
+fn generate_traceparent() -> String {
+    format!("00-{}-{}-01", random_hex(32), random_hex(16))
+}
+
+/// The ID of the request being handled, taken from its `X-Request-Id` header or generated by
+/// the [RequestIds] middleware if it didn't have one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl actix_web::FromRequest for RequestId {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let id = match req.extensions().get::<RequestId>() {
+            Some(id) => id.clone(),
+            None => RequestId(
+                req.headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|id| id.to_str().ok())
+                    .map_or_else(generate_request_id, str::to_owned),
+            ),
+        };
+        ready(Ok(id))
+    }
+}
+
+impl<Query, Body> FromRequest<Query, Body> for RequestId {}
+
+impl Deref for RequestId {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Middleware that gives every request a [RequestId] and a `traceparent`, keeping the ones the
+/// client sent, and echoes the ID back in the response's `X-Request-Id` header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestIds;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIds
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = RequestIdsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdsMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestIdsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let header = |req: &ServiceRequest, name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        let id = header(&req, REQUEST_ID_HEADER).unwrap_or_else(generate_request_id);
+        if !req.headers().contains_key(TRACEPARENT_HEADER) {
+            if let Ok(traceparent) = generate_traceparent().parse() {
+                req.headers_mut().insert(
+                    actix_web::http::header::HeaderName::from_static(TRACEPARENT_HEADER),
+                    traceparent,
+                );
+            }
+        }
+        req.extensions_mut().insert(RequestId(id.clone()));
+
+        let service = Rc::clone(&self.service);
+        Box::pin(async move {
+            let mut response = service.call(req).await?;
+            if let Ok(id) = id.parse() {
+                response.headers_mut().insert(
+                    actix_web::http::header::HeaderName::from_static(REQUEST_ID_HEADER),
+                    id,
+                );
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// A [ClientTransport] that gives every request an `X-Request-Id` and a `traceparent` unless it
+/// already has them.
+#[derive(Debug, Clone)]
+pub struct WithRequestId<T>(pub T);
+
+impl<T: ClientTransport> ClientTransport for WithRequestId<T> {
+    type Response = T::Response;
+    type Error = T::Error;
+
+    async fn send(&self, mut request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let headers = request.headers_mut();
+        for (name, generate) in [
+            (REQUEST_ID_HEADER, generate_request_id as fn() -> String),
+            (TRACEPARENT_HEADER, generate_traceparent),
+        ] {
+            if !headers.contains_key(name) {
+                if let Ok(value) = http::HeaderValue::try_from(generate()) {
+                    headers.insert(name, value);
+                }
+            }
+        }
+        self.0.send(request).await
+    }
+}
@@ -0,0 +1,216 @@
+//! Limiting how many requests a client has in flight, so that a burst of low priority requests
+//! (e.g. prefetches) can't hold up the ones a user is waiting on.
+
+use std::{collections::BinaryHeap, sync::Mutex};
+
+use bytes::Bytes;
+use tokio::sync::oneshot;
+
+use crate::ClientTransport;
+
+/// How urgently a request should be sent, set per route with the `priority` option of
+/// [routes](crate::routes) or per request with `RequestBuilder::priority`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Requests nobody is waiting on yet, e.g. prefetches.
+    Background,
+    Normal,
+    /// Requests a user has just asked for, e.g. by clicking a button.
+    UserInitiated,
+}
+
+/// A snapshot of a [Scheduler]'s queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueueMetrics {
+    pub in_flight: usize,
+    pub queued_background: usize,
+    pub queued_normal: usize,
+    pub queued_user_initiated: usize,
+}
+
+struct Queued {
+    priority: Priority,
+    sequence: u64,
+    start: oneshot::Sender<()>,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    /// Higher priorities first, then first come, first served.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct State {
+    in_flight: usize,
+    sequence: u64,
+    queue: BinaryHeap<Queued>,
+}
+
+impl State {
+    fn metrics(&self) -> QueueMetrics {
+        let mut metrics = QueueMetrics {
+            in_flight: self.in_flight,
+            ..QueueMetrics::default()
+        };
+        for queued in &self.queue {
+            match queued.priority {
+                Priority::Background => metrics.queued_background += 1,
+                Priority::Normal => metrics.queued_normal += 1,
+                Priority::UserInitiated => metrics.queued_user_initiated += 1,
+            }
+        }
+        metrics
+    }
+}
+
+/// A [ClientTransport] that sends at most `max_concurrent` requests at once, queueing the rest
+/// by their [Priority].
+pub struct Scheduler<T> {
+    inner: T,
+    max_concurrent: usize,
+    state: Mutex<State>,
+    observer: Option<Box<dyn Fn(QueueMetrics) + Send + Sync>>,
+}
+
+impl<T> Scheduler<T> {
+    pub fn new(inner: T, max_concurrent: usize) -> Self {
+        Scheduler {
+            inner,
+            max_concurrent: max_concurrent.max(1),
+            state: Mutex::new(State::default()),
+            observer: None,
+        }
+    }
+
+    /// Call `observer` with the queue's metrics whenever a request is queued, started or
+    /// finished, e.g. to report them as gauges.
+    pub fn observe(mut self, observer: impl Fn(QueueMetrics) + Send + Sync + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    pub fn metrics(&self) -> QueueMetrics {
+        self.state.lock().unwrap().metrics()
+    }
+
+    fn notify(&self, state: &State) {
+        if let Some(observer) = &self.observer {
+            observer(state.metrics());
+        }
+    }
+
+    /// Wait for a slot to send a request of `priority` in.
+    async fn acquire(&self, priority: Priority) -> Slot<'_, T> {
+        let started = {
+            let mut state = self.state.lock().unwrap();
+            if state.in_flight < self.max_concurrent && state.queue.is_empty() {
+                state.in_flight += 1;
+                self.notify(&state);
+                None
+            } else {
+                let (start, started) = oneshot::channel();
+                let sequence = state.sequence;
+                state.sequence += 1;
+                state.queue.push(Queued {
+                    priority,
+                    sequence,
+                    start,
+                });
+                self.notify(&state);
+                Some(started)
+            }
+        };
+        if let Some(started) = started {
+            let mut waiting = Waiting {
+                scheduler: self,
+                started,
+                done: false,
+            };
+            // Queued senders are only dropped once they've handed over a slot.
+            let _ = (&mut waiting.started).await;
+            waiting.done = true;
+        }
+        Slot(self)
+    }
+
+    /// Hand a finished request's slot to the next queued request, skipping any that have been
+    /// given up on.
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            match state.queue.pop() {
+                Some(queued) => {
+                    if queued.start.send(()).is_ok() {
+                        break;
+                    }
+                }
+                None => {
+                    state.in_flight -= 1;
+                    break;
+                }
+            }
+        }
+        self.notify(&state);
+    }
+}
+
+/// A queued request, which passes on its slot if it's given up on just after being handed one.
+struct Waiting<'a, T> {
+    scheduler: &'a Scheduler<T>,
+    started: oneshot::Receiver<()>,
+    done: bool,
+}
+
+impl<T> Drop for Waiting<'_, T> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        self.started.close();
+        if self.started.try_recv().is_ok() {
+            self.scheduler.release();
+        }
+    }
+}
+
+/// A request's place among the in flight requests, given up when it's dropped.
+struct Slot<'a, T>(&'a Scheduler<T>);
+
+impl<T> Drop for Slot<'_, T> {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for Scheduler<T> {
+    type Response = T::Response;
+    type Error = T::Error;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let priority = request
+            .extensions()
+            .get::<Priority>()
+            .copied()
+            .unwrap_or(Priority::Normal);
+        let _slot = self.acquire(priority).await;
+        self.inner.send(request).await
+    }
+}
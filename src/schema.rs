@@ -0,0 +1,371 @@
+//! Fingerprints of routes' query and body schemas, so that a client built against a different
+//! version of a route than the server's shows up as a mismatch instead of a confusing decode
+//! error.
+//!
+//! Clients send their fingerprint in an `X-Schema-Version` header with
+//! `RequestBuilder::schema_version`, and handlers check it with the [SchemaVersion] extractor.
+//! Registering a [SchemaCatalog] as a module lets clients check every route at once.
+
+use std::{
+    future::{ready, Ready},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    join::{fetch_json, FetchError},
+    ClientTransport, FormBody, FromRequest, JsonBody, Module, NoBody, NoQuery, Query,
+    RequestBuilder, Route, Router, TransportResponse,
+};
+
+/// The header clients send their route's [RouteSchema::fingerprint] in.
+pub const SCHEMA_VERSION_HEADER: &str = "x-schema-version";
+
+/// Implemented by query and body types whose shape can be described with a JSON schema.
+pub trait DescribeSchema {
+    /// The type's schema, or `None` if it has no content.
+    fn schema() -> Option<serde_json::Value>;
+}
+
+impl DescribeSchema for NoQuery {
+    fn schema() -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl DescribeSchema for NoBody {
+    fn schema() -> Option<serde_json::Value> {
+        None
+    }
+}
+
+fn schema_of<T: JsonSchema>() -> Option<serde_json::Value> {
+    serde_json::to_value(schema_for!(T)).ok()
+}
+
+impl<T: JsonSchema, F> DescribeSchema for Query<T, F> {
+    fn schema() -> Option<serde_json::Value> {
+        schema_of::<T>()
+    }
+}
+
+impl<T: JsonSchema> DescribeSchema for JsonBody<T> {
+    fn schema() -> Option<serde_json::Value> {
+        schema_of::<T>()
+    }
+}
+
+impl<T: JsonSchema> DescribeSchema for FormBody<T> {
+    fn schema() -> Option<serde_json::Value> {
+        schema_of::<T>()
+    }
+}
+
+/// Implemented by every route whose query and body types implement [DescribeSchema].
+pub trait RouteSchema: Route {
+    /// A hash of the route's method, URI and the schemas of its query and body types, as 16
+    /// hex digits. It only changes when one of those does.
+    fn fingerprint() -> String;
+
+    fn query_schema() -> Option<serde_json::Value>;
+    fn request_schema() -> Option<serde_json::Value>;
+    fn response_schema() -> Option<serde_json::Value>;
+}
+
+impl<R: Route> RouteSchema for R
+where
+    R::Query: DescribeSchema,
+    R::RequestBody: DescribeSchema,
+    R::ResponseBody: DescribeSchema,
+{
+    fn fingerprint() -> String {
+        let shape = serde_json::json!([
+            R::METHOD.as_str(),
+            R::URI,
+            R::Query::schema(),
+            R::RequestBody::schema(),
+            R::ResponseBody::schema(),
+        ]);
+        format!("{:016x}", fnv1a(shape.to_string().as_bytes()))
+    }
+
+    fn query_schema() -> Option<serde_json::Value> {
+        R::Query::schema()
+    }
+
+    fn request_schema() -> Option<serde_json::Value> {
+        R::RequestBody::schema()
+    }
+
+    fn response_schema() -> Option<serde_json::Value> {
+        R::ResponseBody::schema()
+    }
+}
+
+/// 64 bit FNV-1a, which unlike std's hashers is the same on every platform and release.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl<Route: RouteSchema, Query, Body> RequestBuilder<Route, Query, Body> {
+    /// Send the route's [RouteSchema::fingerprint], so the server can tell if it was built
+    /// against a different version of the route.
+    pub fn schema_version(mut self) -> Self {
+        if let Ok(value) = http::HeaderValue::try_from(Route::fingerprint()) {
+            self.head.headers.insert(SCHEMA_VERSION_HEADER, value);
+        }
+        self
+    }
+}
+
+/// App data deciding what [SchemaVersion] does about a client whose fingerprint doesn't match.
+/// Without it, mismatches are only reported to the handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaPolicy {
+    #[default]
+    Warn,
+    /// Fail the request with `409 Conflict`.
+    Reject,
+}
+
+/// An extractor that compares the client's `X-Schema-Version` header with `Route`'s
+/// fingerprint.
+pub struct SchemaVersion<Route> {
+    _marker: PhantomData<Route>,
+    /// The fingerprint the client sent, if any.
+    pub received: Option<String>,
+}
+
+impl<Route: RouteSchema> SchemaVersion<Route> {
+    /// Whether the client's fingerprint matches the server's, or `None` if it didn't send one.
+    pub fn matches(&self) -> Option<bool> {
+        self.received
+            .as_ref()
+            .map(|received| *received == Route::fingerprint())
+    }
+}
+
+impl<Route: RouteSchema> actix_web::FromRequest for SchemaVersion<Route> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let version = SchemaVersion {
+            _marker: PhantomData,
+            received: req
+                .headers()
+                .get(SCHEMA_VERSION_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+        };
+        let policy = req.app_data::<SchemaPolicy>().copied().unwrap_or_default();
+        if policy == SchemaPolicy::Reject && version.matches() == Some(false) {
+            return ready(Err(actix_web::error::ErrorConflict(format!(
+                "The client was built against a different version of {}",
+                Route::NAME
+            ))));
+        }
+        ready(Ok(version))
+    }
+}
+
+impl<Route, Query, Body> FromRequest<Query, Body> for SchemaVersion<Route> {}
+
+/// One route's entry in a [SchemaCatalog].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteFingerprint {
+    pub name: String,
+    pub method: String,
+    pub uri: String,
+    pub fingerprint: String,
+    /// The schemas the fingerprint was made from, so [catalog::diff](crate::catalog::diff) can
+    /// tell what changed. Catalogs from older versions of the crate don't have them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<serde_json::Value>,
+}
+
+/// The version of the JSON format [SchemaCatalog]s are serialized in. It's bumped whenever a
+/// change to the format would make older versions of the crate misread it.
+pub const CATALOG_FORMAT_VERSION: u32 = 1;
+
+/// The fingerprints of a set of routes, served by the server at [SchemaRoute] and compared
+/// with the client's own by [SchemaCatalog::verify_compatibility].
+///
+/// It serializes to a stable JSON format, so it can also be saved, e.g. alongside a release,
+/// and read by other tools:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "routes": [
+///     {
+///       "name": "GetOrder",
+///       "method": "GET",
+///       "uri": "/orders/{id}",
+///       "fingerprint": "9c0d1b6a2e4f8a73",
+///       "query": { "...": "the query's JSON schema, if it has one" },
+///       "request": { "...": "the request body's JSON schema, if it has one" },
+///       "response": { "...": "the response body's JSON schema, if it has one" }
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Catalogs in a newer version of the format fail to deserialize. The bare array of routes
+/// that earlier versions of the crate serialized is still read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaCatalog(pub Vec<RouteFingerprint>);
+
+impl Serialize for SchemaCatalog {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Versioned<'a> {
+            version: u32,
+            routes: &'a [RouteFingerprint],
+        }
+
+        Versioned {
+            version: CATALOG_FORMAT_VERSION,
+            routes: &self.0,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaCatalog {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Versioned {
+                version: u32,
+                routes: Vec<RouteFingerprint>,
+            },
+            Unversioned(Vec<RouteFingerprint>),
+        }
+
+        match Stored::deserialize(deserializer)? {
+            Stored::Versioned { version, routes } if version <= CATALOG_FORMAT_VERSION => {
+                Ok(SchemaCatalog(routes))
+            }
+            Stored::Versioned { version, .. } => Err(serde::de::Error::custom(format!(
+                "unsupported catalog format version {version}, expected at most {CATALOG_FORMAT_VERSION}"
+            ))),
+            Stored::Unversioned(routes) => Ok(SchemaCatalog(routes)),
+        }
+    }
+}
+
+impl SchemaCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route<R: RouteSchema>(mut self) -> Self {
+        self.0.push(RouteFingerprint {
+            name: R::NAME.to_owned(),
+            method: R::METHOD.to_string(),
+            uri: R::URI.to_owned(),
+            fingerprint: R::fingerprint(),
+            query: R::query_schema(),
+            request: R::request_schema(),
+            response: R::response_schema(),
+        });
+        self
+    }
+
+    /// The routes in this catalog that `server` serves differently or not at all.
+    pub fn incompatibilities(&self, server: &SchemaCatalog) -> Vec<Incompatibility> {
+        self.0
+            .iter()
+            .filter_map(|route| {
+                let served = server
+                    .0
+                    .iter()
+                    .find(|served| served.method == route.method && served.uri == route.uri);
+                let kind = match served {
+                    None => IncompatibilityKind::Missing,
+                    Some(served) if served.fingerprint != route.fingerprint => {
+                        IncompatibilityKind::Changed {
+                            server: served.fingerprint.clone(),
+                        }
+                    }
+                    Some(_) => return None,
+                };
+                Some(Incompatibility {
+                    route: route.clone(),
+                    kind,
+                })
+            })
+            .collect()
+    }
+
+    /// Fetch the server's catalog and compare it with this one, e.g. before the app makes its
+    /// first real request.
+    pub async fn verify_compatibility<T>(
+        &self,
+        transport: &T,
+    ) -> Result<Vec<Incompatibility>, FetchError>
+    where
+        T: ClientTransport,
+        T::Error: std::error::Error + 'static,
+        <T::Response as TransportResponse>::Error: std::error::Error + 'static,
+    {
+        let server = fetch_json(RequestBuilder::<SchemaRoute, _, _>::new(), transport).await?;
+        Ok(self.incompatibilities(&server))
+    }
+}
+
+/// A route in the client's [SchemaCatalog] that doesn't match the server's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incompatibility {
+    /// The client's view of the route.
+    pub route: RouteFingerprint,
+    pub kind: IncompatibilityKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncompatibilityKind {
+    /// The server doesn't serve the route.
+    Missing,
+    /// The server's version of the route has a different fingerprint.
+    Changed { server: String },
+}
+
+/// `GET /__schema`, which lists the server's routes and their fingerprints.
+pub struct SchemaRoute;
+
+impl Route for SchemaRoute {
+    type Query = NoQuery;
+    type RequestBody = NoBody;
+    type ResponseBody = JsonBody<SchemaCatalog>;
+    const METHOD: http::Method = http::Method::GET;
+    const NAME: &'static str = "SchemaRoute";
+    const URI_PART: &'static str = "/__schema";
+    const URI: &'static str = "/__schema";
+}
+
+impl Module for SchemaCatalog {
+    fn register<R: Router>(self, router: R) -> R {
+        let catalog = Rc::new(self);
+        router.typed_route::<SchemaRoute, _>(|| {
+            let catalog = Rc::clone(&catalog);
+            actix_web::web::route().to(move || {
+                let catalog = Rc::clone(&catalog);
+                async move { actix_web::web::Json(SchemaCatalog::clone(&catalog)) }
+            })
+        })
+    }
+}
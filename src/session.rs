@@ -0,0 +1,150 @@
+//! A [ClientTransport] that logs in when the server says a request needs a session, then
+//! retries it.
+
+use std::{future::Future, sync::Mutex};
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{ClientTransport, Request, Response, Route, TransportResponse};
+
+/// The session a [SessionClient] attaches to requests once it has logged in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Session {
+    /// Sent back in the `Cookie` header, as `name=value` pairs.
+    Cookie(String),
+    /// Sent as an `Authorization: Bearer` token.
+    Bearer(String),
+}
+
+impl Session {
+    /// A cookie session made up of every cookie a login response set with `Set-Cookie`, or
+    /// `None` if it set none.
+    pub fn from_set_cookie(headers: &http::HeaderMap) -> Option<Self> {
+        let cookies: Vec<&str> = headers
+            .get_all(http::header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| value.split(';').next())
+            .map(str::trim)
+            .collect();
+        match cookies.as_slice() {
+            [] => None,
+            cookies => Some(Session::Cookie(cookies.join("; "))),
+        }
+    }
+
+    fn attach(&self, headers: &mut http::HeaderMap) {
+        let (name, value) = match self {
+            Session::Cookie(cookies) => (http::header::COOKIE, cookies.clone()),
+            Session::Bearer(token) => (http::header::AUTHORIZATION, format!("Bearer {token}")),
+        };
+        if let Ok(value) = http::HeaderValue::try_from(value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// How a [SessionClient] logs in: the typed login request to send, with fresh credentials, and
+/// how to get a [Session] out of its response.
+pub trait Login {
+    type Route: Route;
+    type Error;
+
+    fn request(&self) -> Request<Self::Route>;
+
+    fn session<R: TransportResponse>(
+        &self,
+        response: Response<Self::Route, R>,
+    ) -> impl Future<Output = Result<Session, Self::Error>>;
+}
+
+/// The error returned when a [SessionClient] fails to send a request or log in.
+#[derive(Debug, Error)]
+pub enum SessionError<TransportError, LoginError> {
+    #[error("Failed to send the request")]
+    Transport(#[source] TransportError),
+    #[error("Login was rejected with status {0}")]
+    LoginRejected(http::StatusCode),
+    #[error("Failed to get a session from the login response")]
+    Login(#[source] LoginError),
+}
+
+/// Wraps a transport to attach the current [Session] to every request. When a request is
+/// answered with `401 Unauthorized`, it logs in with `L` and retries the request once.
+pub struct SessionClient<T, L> {
+    inner: T,
+    login: L,
+    session: Mutex<Option<Session>>,
+}
+
+impl<T: ClientTransport, L: Login> SessionClient<T, L> {
+    pub fn new(inner: T, login: L) -> Self {
+        SessionClient {
+            inner,
+            login,
+            session: Mutex::new(None),
+        }
+    }
+
+    /// Start from a session that was saved earlier, instead of logging in on the first `401`.
+    pub fn with_session(self, session: Session) -> Self {
+        *self.session.lock().unwrap() = Some(session);
+        self
+    }
+
+    pub fn session(&self) -> Option<Session> {
+        self.session.lock().unwrap().clone()
+    }
+
+    /// Forget the current session, e.g. on logout.
+    pub fn clear_session(&self) {
+        *self.session.lock().unwrap() = None;
+    }
+
+    async fn log_in(&self) -> Result<Session, SessionError<T::Error, L::Error>> {
+        let response = self
+            .login
+            .request()
+            .send_with(&self.inner)
+            .await
+            .map_err(SessionError::Transport)?;
+        if !response.ok() {
+            return Err(SessionError::LoginRejected(response.status()));
+        }
+        let session = self
+            .login
+            .session(response)
+            .await
+            .map_err(SessionError::Login)?;
+        *self.session.lock().unwrap() = Some(session.clone());
+        Ok(session)
+    }
+}
+
+impl<T: ClientTransport, L: Login> ClientTransport for SessionClient<T, L> {
+    type Response = T::Response;
+    type Error = SessionError<T::Error, L::Error>;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let (parts, body) = request.into_parts();
+        let send = |session: Option<&Session>| {
+            let mut request = http::Request::from_parts(parts.clone(), body.clone());
+            if let Some(session) = session {
+                session.attach(request.headers_mut());
+            }
+            self.inner.send(request)
+        };
+
+        let session = self.session();
+        let response = send(session.as_ref())
+            .await
+            .map_err(SessionError::Transport)?;
+        if response.status() != http::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let session = self.log_in().await?;
+        send(Some(&session)).await.map_err(SessionError::Transport)
+    }
+}
@@ -0,0 +1,116 @@
+//! Ending long-lived streaming responses when the server shuts down, so that a graceful stop
+//! doesn't wait out its timeout on streams that never finish.
+//!
+//! actix doesn't tell handlers when it's stopping, so the app begins shutting down itself,
+//! e.g. on `SIGTERM`, before stopping the server:
+//!
+//! ```
+//! # use actix_web::{App, HttpServer};
+//! # use typed_routing::shutdown::Shutdown;
+//! # async fn serve(addr: &str, mut terminate: tokio::sync::mpsc::Receiver<()>) -> std::io::Result<()> {
+//! let shutdown = Shutdown::new();
+//! let server = HttpServer::new({
+//!     let shutdown = shutdown.clone();
+//!     move || App::new().app_data(shutdown.clone()) // ...
+//! })
+//! .disable_signals()
+//! .bind(addr)?
+//! .run();
+//! let handle = server.handle();
+//! actix_web::rt::spawn(async move {
+//!     terminate.recv().await;
+//!     shutdown.begin();
+//!     handle.stop(true).await;
+//! });
+//! # server.await
+//! # }
+//! ```
+
+use std::{
+    future::{ready, Ready},
+    pin::pin,
+    sync::Arc,
+};
+
+use futures_util::{
+    future::{select, Either},
+    Stream, StreamExt,
+};
+use tokio::sync::watch;
+
+use crate::FromRequest;
+
+/// Whether the server is shutting down. Cloning it shares the state.
+///
+/// It's also an extractor, reading the [Shutdown] in the app's data. Apps without one never
+/// shut streams down.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            sender: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tell every stream to close.
+    pub fn begin(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Wait until the server begins shutting down.
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|shutting_down| *shutting_down).await;
+    }
+
+    /// Pass `stream`'s items through until it ends, or until the server begins shutting down,
+    /// when `closing` is sent as the last item instead, e.g. a message telling the client to
+    /// reconnect.
+    pub fn close<S: Stream>(&self, stream: S, closing: S::Item) -> impl Stream<Item = S::Item> {
+        let state = (Box::pin(stream), Some(closing), self.clone());
+        futures_util::stream::unfold(state, |(mut stream, closing, shutdown)| async move {
+            let closing = closing?;
+            // `None` once the server has begun shutting down.
+            let next = {
+                let wait = pin!(shutdown.wait());
+                match select(stream.next(), wait).await {
+                    Either::Left((item, _)) => Some(item),
+                    Either::Right(_) => None,
+                }
+            };
+            match next {
+                Some(Some(item)) => Some((item, (stream, Some(closing), shutdown))),
+                Some(None) => None,
+                None => Some((closing, (stream, None, shutdown))),
+            }
+        })
+    }
+}
+
+impl actix_web::FromRequest for Shutdown {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        ready(Ok(req.app_data::<Shutdown>().cloned().unwrap_or_default()))
+    }
+}
+
+impl<Query, Body> FromRequest<Query, Body> for Shutdown {}
@@ -0,0 +1,77 @@
+//! Serving a single page app's static files next to its typed API.
+
+use std::path::PathBuf;
+
+use actix_files::{Files, NamedFile};
+use actix_web::{
+    dev::{fn_service, ServiceRequest, ServiceResponse},
+    http::Method,
+    HttpResponse,
+};
+
+use crate::{Module, Router};
+
+/// A module that serves the files in a directory, falling back to the app's `index.html` for
+/// any other `GET` request so that client-side routing works on reload.
+///
+/// Requests under the API prefix are never given the index, so a typo in an API path is a
+/// `404` rather than a page of HTML. Register it after the API's routes.
+pub struct Spa {
+    dir: PathBuf,
+    index_file: String,
+    api_prefix: Option<String>,
+}
+
+impl Spa {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Spa {
+            dir: dir.into(),
+            index_file: "index.html".to_owned(),
+            api_prefix: None,
+        }
+    }
+
+    /// The file, relative to the directory, to serve for `/` and as the fallback.
+    pub fn index_file(mut self, index_file: &str) -> Self {
+        self.index_file = index_file.to_owned();
+        self
+    }
+
+    /// The scope the typed API is served under, e.g. `/api`.
+    pub fn api_prefix(mut self, api_prefix: &str) -> Self {
+        self.api_prefix = Some(api_prefix.trim_end_matches('/').to_owned());
+        self
+    }
+}
+
+fn is_under(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+impl Module for Spa {
+    fn register<R: Router>(self, router: R) -> R {
+        let index = self.dir.join(&self.index_file);
+        let api_prefix = self.api_prefix;
+        let fallback = fn_service(move |req: ServiceRequest| {
+            let index = index.clone();
+            let is_api = api_prefix
+                .as_deref()
+                .is_some_and(|prefix| is_under(req.path(), prefix));
+            async move {
+                let (req, _) = req.into_parts();
+                if is_api || !matches!(*req.method(), Method::GET | Method::HEAD) {
+                    return Ok(ServiceResponse::new(req, HttpResponse::NotFound().finish()));
+                }
+                let response = NamedFile::open_async(index).await?.into_response(&req);
+                Ok(ServiceResponse::new(req, response))
+            }
+        });
+
+        router.service(
+            Files::new("/", self.dir)
+                .index_file(self.index_file)
+                .default_handler(fallback),
+        )
+    }
+}
@@ -0,0 +1,516 @@
+//! Server-sent events: decoding a `text/event-stream` response into typed events, and
+//! [ResilientStream], which keeps an event stream going across dropped connections. Servers
+//! respond with [Sse].
+//!
+//! ```ignore
+//! routes! {
+//!     // ...
+//!     route(Method::GET, "/orders/events" => type OrderEvents (query: NoQuery, body: NoBody) -> SseBody<OrderEvent>)
+//! }
+//!
+//! let events = ResilientStream::new(
+//!     &transport,
+//!     || RequestBuilder::<OrderEvents, _, _>::new().build().unwrap(),
+//!     |delay| gloo_timers::future::sleep(delay),
+//! );
+//! let mut states = events.states();
+//! let events = events.into_stream();
+//! ```
+
+use std::{marker::PhantomData, pin::Pin, time::Duration};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::{
+    BodyError, ClientTransport, ContentType, IntoResponse, NoCheckResponse, Request, Response,
+    TransportResponse,
+};
+
+/// The header a reconnecting client sends the id of the last event it received in.
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// A type that indicates that the response's body will be an event stream whose events' data
+/// is JSON that deserializes into a `T`.
+pub struct SseBody<T>(T);
+
+impl<T> ContentType for SseBody<T> {
+    fn matches(media_type: &str) -> bool {
+        media_type == "text/event-stream"
+    }
+}
+
+/// An event received from an event stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event<T> {
+    /// The event's ID. Line breaks in it, and in [Event::event], are dropped when it's sent, as
+    /// they would end the field.
+    pub id: Option<String>,
+    /// The event's type, from its `event` field.
+    pub event: Option<String>,
+    pub data: T,
+    /// How long the server asks clients to wait before reconnecting.
+    pub retry: Option<Duration>,
+}
+
+impl<T> Event<T> {
+    /// An event with just data.
+    pub fn new(data: T) -> Self {
+        Event {
+            id: None,
+            event: None,
+            data,
+            retry: None,
+        }
+    }
+}
+
+impl<T: Serialize> Event<T> {
+    fn encode(&self) -> Result<Bytes, serde_json::Error> {
+        let mut encoded = String::new();
+        if let Some(id) = &self.id {
+            encoded.push_str(&format!("id: {}\n", single_line(id)));
+        }
+        if let Some(event) = &self.event {
+            encoded.push_str(&format!("event: {}\n", single_line(event)));
+        }
+        if let Some(retry) = self.retry {
+            encoded.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+        // Compact JSON has no newlines, so the data fits on one line.
+        encoded.push_str(&format!("data: {}\n\n", serde_json::to_string(&self.data)?));
+        Ok(encoded.into())
+    }
+}
+
+/// `value` without the line breaks that would let it start fields of its own.
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], "")
+}
+
+/// A responder that streams `S`'s events to the client as they're produced, the counterpart
+/// of [SseBody].
+///
+/// The response ends with the stream, or if an event's data fails to serialize.
+pub struct Sse<S>(pub S);
+
+impl<T: Serialize, S: Stream<Item = Event<T>> + 'static> actix_web::Responder for Sse<S> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &actix_web::HttpRequest) -> actix_web::HttpResponse<Self::Body> {
+        actix_web::HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header((actix_web::http::header::CACHE_CONTROL, "no-cache"))
+            .streaming(self.0.map(|event| event.encode()))
+    }
+}
+
+impl<T: Serialize, S: Stream<Item = Event<T>>> IntoResponse<SseBody<T>> for Sse<S> {}
+
+impl<R, T: IntoResponse<SseBody<R>>, E> IntoResponse<SseBody<R>> for Result<T, E> {}
+
+impl<R, T> IntoResponse<SseBody<R>> for NoCheckResponse<T> {}
+
+/// Why an event couldn't be decoded.
+#[derive(Debug, Error)]
+pub enum SseError {
+    #[error("An event isn't UTF-8")]
+    NotUtf8,
+    #[error("Failed to decode an event's data")]
+    Data(#[source] serde_json::Error),
+}
+
+impl<T: DeserializeOwned, Route: crate::Route<ResponseBody = SseBody<T>>, R: TransportResponse>
+    Response<Route, R>
+{
+    /// Decode the events in the body as they arrive. The stream ends with the response.
+    pub fn events(self) -> impl Stream<Item = Result<Event<T>, BodyError<SseError, R::Error>>> {
+        let state = (Box::pin(self.response.chunks()), Parser::default());
+        futures_util::stream::unfold(state, |(mut chunks, mut parser)| async move {
+            loop {
+                if let Some(event) = parser.next_event() {
+                    let event = event.map_err(BodyError::Format);
+                    return Some((event, (chunks, parser)));
+                }
+                match chunks.next().await {
+                    Some(Ok(chunk)) => parser.push(&chunk),
+                    Some(Err(err)) => {
+                        return Some((Err(BodyError::Transport(err)), (chunks, parser)))
+                    }
+                    None => return None,
+                }
+            }
+        })
+    }
+}
+
+/// Splits an event stream into events, line by line.
+#[derive(Default)]
+struct Parser {
+    buffer: Vec<u8>,
+    /// Where in `buffer` the next line starts. Everything before it has been parsed already.
+    start: usize,
+    /// How far into `buffer` has been searched for the end of the line.
+    position: usize,
+    /// Whether the last line ended with `\r`, so that a `\n` right after it is part of the same
+    /// line break.
+    after_cr: bool,
+    id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Parser {
+    /// Add the next chunk of the body to the buffer.
+    ///
+    /// The lines already parsed are dropped from its front once they're over half of it, so
+    /// that each byte is only moved a few times however many lines there are.
+    fn push(&mut self, chunk: &[u8]) {
+        if self.start > self.buffer.len() / 2 {
+            self.buffer.drain(..self.start);
+            self.position -= self.start;
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// The next whole event in the buffer, or `None` if more of the body is needed.
+    ///
+    /// Lines end with `\n`, `\r` or `\r\n`.
+    fn next_event<T: DeserializeOwned>(&mut self) -> Option<Result<Event<T>, SseError>> {
+        loop {
+            if self.after_cr {
+                match self.buffer.get(self.start) {
+                    Some(b'\n') => {
+                        self.start += 1;
+                        self.position = self.start;
+                    }
+                    Some(_) => {}
+                    None => return None,
+                }
+                self.after_cr = false;
+            }
+            let Some(end) = self.buffer[self.position..]
+                .iter()
+                .position(|&byte| byte == b'\n' || byte == b'\r')
+                .map(|end| self.position + end)
+            else {
+                self.position = self.buffer.len();
+                return None;
+            };
+            let line = self.start..end;
+            self.after_cr = self.buffer[end] == b'\r';
+            self.start = end + 1;
+            self.position = self.start;
+            let Ok(line) = std::str::from_utf8(&self.buffer[line]) else {
+                return Some(Err(SseError::NotUtf8));
+            };
+
+            if line.is_empty() {
+                let Some(data) = self.data.take() else {
+                    self.event = None;
+                    continue;
+                };
+                return Some(
+                    serde_json::from_str(&data)
+                        .map(|data| Event {
+                            id: self.id.clone(),
+                            event: self.event.take(),
+                            data,
+                            retry: self.retry.take(),
+                        })
+                        .map_err(SseError::Data),
+                );
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "id" => self.id = Some(value.to_owned()),
+                "event" => self.event = Some(value.to_owned()),
+                "data" => match &mut self.data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => self.data = Some(value.to_owned()),
+                },
+                "retry" => {
+                    if let Ok(millis) = value.parse() {
+                        self.retry = Some(Duration::from_millis(millis));
+                    }
+                }
+                // Comments, e.g. keep-alives, and unknown fields.
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The connection of a [ResilientStream].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    /// The connection dropped, or couldn't be made, and the stream will reconnect after
+    /// `retry_in`.
+    Disconnected {
+        retry_in: Duration,
+    },
+}
+
+type Events<'a, T, E> = Pin<Box<dyn Stream<Item = Result<Event<T>, BodyError<SseError, E>>> + 'a>>;
+
+/// An event stream from `Route` that reconnects whenever its connection drops, sending the id
+/// of the last event received so the server can resume from there.
+///
+/// `request` builds each connection's request. `sleep` should return a future that completes
+/// after the given duration, using whichever runtime the client runs on. Failed connections
+/// are retried with exponential backoff.
+pub struct ResilientStream<'a, Route, T, B, S> {
+    transport: &'a T,
+    request: B,
+    sleep: S,
+    retry: Duration,
+    max_backoff: Duration,
+    state: watch::Sender<ConnectionState>,
+    _marker: PhantomData<fn() -> Route>,
+}
+
+impl<'a, Route, T, B, S, Fut, D> ResilientStream<'a, Route, T, B, S>
+where
+    Route: crate::Route<ResponseBody = SseBody<D>> + 'a,
+    D: DeserializeOwned + 'a,
+    T: ClientTransport,
+    T::Response: 'a,
+    B: Fn() -> Request<Route> + 'a,
+    S: Fn(Duration) -> Fut + 'a,
+    Fut: std::future::Future<Output = ()>,
+{
+    pub fn new(transport: &'a T, request: B, sleep: S) -> Self {
+        ResilientStream {
+            transport,
+            request,
+            sleep,
+            retry: Duration::from_secs(3),
+            max_backoff: Duration::from_secs(60),
+            state: watch::Sender::new(ConnectionState::Connecting),
+            _marker: PhantomData,
+        }
+    }
+
+    /// How long to wait before reconnecting, until the server sets it with an event's `retry`
+    /// field. Defaults to three seconds.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The longest to wait after repeated failures, doubling from the retry delay. Defaults to
+    /// a minute.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Changes to the connection's state, e.g. to show that updates are paused.
+    pub fn states(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
+
+    /// The events, without end. Events that fail to decode are given as errors, and don't
+    /// interrupt the stream.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Event<D>, SseError>> + 'a {
+        let state = Connection {
+            stream: self,
+            events: None,
+            last_id: None,
+            delay: None,
+            failures: 0,
+        };
+        futures_util::stream::unfold(state, |mut connection| async move {
+            let event = connection.next().await;
+            Some((event, connection))
+        })
+    }
+}
+
+struct Connection<'a, Route, T: ClientTransport, B, S, D> {
+    stream: ResilientStream<'a, Route, T, B, S>,
+    events: Option<Events<'a, D, <T::Response as TransportResponse>::Error>>,
+    last_id: Option<String>,
+    /// How long to wait before connecting again.
+    delay: Option<Duration>,
+    failures: u32,
+}
+
+impl<'a, Route, T, B, S, Fut, D> Connection<'a, Route, T, B, S, D>
+where
+    Route: crate::Route<ResponseBody = SseBody<D>> + 'a,
+    D: DeserializeOwned + 'a,
+    T: ClientTransport,
+    T::Response: 'a,
+    B: Fn() -> Request<Route> + 'a,
+    S: Fn(Duration) -> Fut + 'a,
+    Fut: std::future::Future<Output = ()>,
+{
+    async fn next(&mut self) -> Result<Event<D>, SseError> {
+        loop {
+            if let Some(events) = &mut self.events {
+                match events.next().await {
+                    Some(Ok(event)) => {
+                        if let Some(id) = &event.id {
+                            self.last_id = Some(id.clone());
+                        }
+                        if let Some(retry) = event.retry {
+                            self.stream.retry = retry;
+                        }
+                        return Ok(event);
+                    }
+                    Some(Err(BodyError::Format(err))) => return Err(err),
+                    Some(Err(BodyError::Transport(_))) | None => {
+                        self.events = None;
+                        self.disconnect(self.stream.retry);
+                    }
+                }
+            }
+
+            if let Some(delay) = self.delay.take() {
+                (self.stream.sleep)(delay).await;
+            }
+            self.connect().await;
+        }
+    }
+
+    async fn connect(&mut self) {
+        self.stream.state.send_replace(ConnectionState::Connecting);
+        let mut request = (self.stream.request)();
+        if let Some(id) = self
+            .last_id
+            .as_deref()
+            .and_then(|id| http::HeaderValue::try_from(id).ok())
+        {
+            request
+                .request
+                .headers_mut()
+                .insert(LAST_EVENT_ID_HEADER, id);
+        }
+        match request.send_with(self.stream.transport).await {
+            Ok(response) if response.ok() => {
+                self.failures = 0;
+                self.stream.state.send_replace(ConnectionState::Connected);
+                self.events = Some(Box::pin(response.events()));
+            }
+            Ok(_) | Err(_) => {
+                let backoff = self
+                    .stream
+                    .retry
+                    .saturating_mul(2u32.saturating_pow(self.failures))
+                    .min(self.stream.max_backoff);
+                self.failures += 1;
+                self.disconnect(backoff);
+            }
+        }
+    }
+
+    fn disconnect(&mut self, retry_in: Duration) {
+        self.delay = Some(retry_in);
+        self.stream
+            .state
+            .send_replace(ConnectionState::Disconnected { retry_in });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The events in `body`, fed to the parser in chunks of `chunk_size` bytes.
+    fn parse(body: &[u8], chunk_size: usize) -> Vec<Event<serde_json::Value>> {
+        let mut parser = Parser::default();
+        let mut events = Vec::new();
+        for chunk in body.chunks(chunk_size) {
+            parser.push(chunk);
+            while let Some(event) = parser.next_event() {
+                events.push(event.unwrap());
+            }
+        }
+        events
+    }
+
+    fn event(
+        id: Option<&str>,
+        event: Option<&str>,
+        data: serde_json::Value,
+    ) -> Event<serde_json::Value> {
+        Event {
+            id: id.map(str::to_owned),
+            event: event.map(str::to_owned),
+            data,
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn splits_events_on_every_kind_of_line_break() {
+        for line_break in ["\n", "\r", "\r\n"] {
+            let body =
+                "id: 1\nevent: created\ndata: {\"a\":1}\n\n: keep-alive\n\ndata: [1,\ndata: 2]\n\n"
+                    .replace('\n', line_break);
+            for chunk_size in [1, 2, 3, 1000] {
+                assert_eq!(
+                    parse(body.as_bytes(), chunk_size),
+                    [
+                        event(Some("1"), Some("created"), serde_json::json!({ "a": 1 })),
+                        event(Some("1"), None, serde_json::json!([1, 2])),
+                    ],
+                    "line break {line_break:?}, chunks of {chunk_size}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_the_retry_of_an_event() {
+        let events = parse(b"retry: 1500\ndata: null\n\ndata: null\n\n", 4);
+        assert_eq!(events[0].retry, Some(Duration::from_millis(1500)));
+        assert_eq!(events[1].retry, None);
+    }
+
+    #[test]
+    fn waits_for_the_blank_line_ending_an_event() {
+        let mut parser = Parser::default();
+        parser.push(b"data: 1\r");
+        assert!(parser.next_event::<u32>().is_none());
+        parser.push(b"\n\r\n");
+        assert_eq!(parser.next_event::<u32>().unwrap().unwrap().data, 1);
+        assert!(parser.next_event::<u32>().is_none());
+    }
+
+    #[test]
+    fn rejects_lines_that_arent_utf8() {
+        let mut parser = Parser::default();
+        parser.push(b"data: \xff\n\n");
+        assert!(matches!(
+            parser.next_event::<u32>(),
+            Some(Err(SseError::NotUtf8))
+        ));
+    }
+
+    #[test]
+    fn parses_many_events_in_one_chunk() {
+        let body = "data: 1\n\n".repeat(100_000);
+        let mut parser = Parser::default();
+        parser.push(body.as_bytes());
+        let mut count = 0;
+        while let Some(event) = parser.next_event::<u32>() {
+            assert_eq!(event.unwrap().data, 1);
+            count += 1;
+        }
+        assert_eq!(count, 100_000);
+    }
+}
@@ -0,0 +1,176 @@
+//! Routes that respond with one of several success statuses, each with a body of its own, e.g.
+//! `201 Created` with the new resource or `202 Accepted` with a job to poll.
+//!
+//! ```ignore
+//! status_variants! {
+//!     pub enum CreateOrderResponse {
+//!         CREATED => Created(Order),
+//!         ACCEPTED => Accepted(Job),
+//!     }
+//! }
+//!
+//! routes! {
+//!     // ...
+//!     route(Method::POST, "/orders" => type CreateOrder (query: NoQuery, body: JsonBody<NewOrder>) -> StatusBody<CreateOrderResponse>)
+//! }
+//!
+//! // Handlers return the enum, which is sent with its variant's status.
+//! async fn create_order(order: Json<NewOrder>) -> CreateOrderResponse { /* ... */ }
+//!
+//! // Clients get the variant back from the response's status.
+//! match response.parse().await? {
+//!     CreateOrderResponse::Created(order) => { /* ... */ }
+//!     CreateOrderResponse::Accepted(job) => { /* ... */ }
+//! }
+//! ```
+//!
+//! Bodies are sent as JSON. A variant holding `()` can be used for statuses without a body,
+//! e.g. `NO_CONTENT`.
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+#[doc(hidden)]
+pub use serde_json::Error as JsonError;
+
+use crate::{
+    BodyError, ContentType, IntoResponse, NoCheckResponse, Response, StatusCode, TransportResponse,
+};
+
+/// Implemented by enums over a route's success responses, usually with [status_variants!](crate::status_variants).
+pub trait StatusVariants: Sized {
+    /// The status the variant is sent with.
+    fn status(&self) -> StatusCode;
+
+    /// The variant's body, as JSON.
+    fn encode(&self) -> Result<Vec<u8>, serde_json::Error>;
+
+    /// The variant for `status`, decoded from `body`, or `None` if no variant has that status.
+    fn decode(status: StatusCode, body: &[u8]) -> Option<Result<Self, serde_json::Error>>;
+}
+
+/// A type that indicates that the response's status picks which of `T`'s variants its JSON
+/// body deserializes into.
+pub struct StatusBody<T>(T);
+
+impl<T> ContentType for StatusBody<T> {
+    fn matches(media_type: &str) -> bool {
+        media_type == "application/json"
+    }
+}
+
+impl<T: StatusVariants> IntoResponse<StatusBody<T>> for T {}
+
+impl<R, T: IntoResponse<StatusBody<R>>, E> IntoResponse<StatusBody<R>> for Result<T, E> {}
+
+impl<R, T> IntoResponse<StatusBody<R>> for NoCheckResponse<T> {}
+
+/// Why a [StatusBody] couldn't be parsed.
+#[derive(Debug, Error)]
+pub enum StatusError {
+    #[error("The route has no response for status {0}")]
+    UnexpectedStatus(StatusCode),
+    #[error("Failed to decode the body")]
+    Json(#[source] serde_json::Error),
+}
+
+impl<
+        T: StatusVariants,
+        Route: crate::Route<ResponseBody = StatusBody<T>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    /// Decode the body into the variant for the response's status.
+    pub async fn parse(self) -> Result<T, BodyError<StatusError, R::Error>> {
+        let status = self.status();
+        let body = self.body().await.map_err(BodyError::Transport)?;
+        match T::decode(status, &body) {
+            Some(variant) => variant.map_err(|err| BodyError::Format(StatusError::Json(err))),
+            None => Err(BodyError::Format(StatusError::UnexpectedStatus(status))),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn encode<T: Serialize>(body: &T) -> Result<Vec<u8>, serde_json::Error> {
+    serde_json::to_vec(body)
+}
+
+/// Empty bodies decode as `null`, so that `()` variants accept them.
+#[doc(hidden)]
+pub fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, serde_json::Error> {
+    if body.is_empty() {
+        serde_json::from_slice(b"null")
+    } else {
+        serde_json::from_slice(body)
+    }
+}
+
+#[doc(hidden)]
+pub fn respond<T: StatusVariants>(variants: &T) -> actix_web::HttpResponse {
+    use crate::compat::ToActix;
+
+    match variants.encode() {
+        Ok(body) => actix_web::HttpResponse::build(variants.status().to_actix())
+            .content_type("application/json")
+            .body(body),
+        Err(err) => actix_web::error::ErrorInternalServerError(err).error_response(),
+    }
+}
+
+/// Define an enum over a route's success responses, with a variant for each status and the
+/// type of its body, to be used as the route's [StatusBody]. Statuses are named as
+/// [StatusCode]'s constants.
+///
+/// The enum is also an actix `Responder`, sending the variant's body with its status.
+#[macro_export]
+macro_rules! status_variants {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($status:ident => $variant:ident($body:ty)),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant($body)),*
+        }
+
+        impl $crate::status::StatusVariants for $name {
+            fn status(&self) -> $crate::StatusCode {
+                match self {
+                    $(Self::$variant(_) => $crate::StatusCode::$status),*
+                }
+            }
+
+            fn encode(&self) -> Result<Vec<u8>, $crate::status::JsonError> {
+                match self {
+                    $(Self::$variant(body) => $crate::status::encode(body)),*
+                }
+            }
+
+            fn decode(
+                status: $crate::StatusCode,
+                body: &[u8],
+            ) -> Option<Result<Self, $crate::status::JsonError>> {
+                $(
+                    if status == $crate::StatusCode::$status {
+                        return Some($crate::status::decode(body).map(Self::$variant));
+                    }
+                )*
+                None
+            }
+        }
+
+        impl $crate::actix_web::Responder for $name {
+            type Body = $crate::actix_web::body::BoxBody;
+
+            fn respond_to(
+                self,
+                _req: &$crate::actix_web::HttpRequest,
+            ) -> $crate::actix_web::HttpResponse<Self::Body> {
+                $crate::status::respond(&self)
+            }
+        }
+    };
+}
@@ -0,0 +1,258 @@
+//! Decoding the elements of a JSON array response as they arrive, so a UI can show the first
+//! rows of a long list before the rest has downloaded.
+
+use std::{ops::Range, pin::Pin};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::{BodyError, JsonBody, Response, TransportResponse};
+
+/// Why a response couldn't be decoded by [Response::stream_array].
+#[derive(Debug, Error)]
+pub enum ArrayStreamError {
+    #[error("The body isn't a JSON array")]
+    NotAnArray,
+    #[error("The body ended in the middle of the array")]
+    Truncated,
+    #[error("The array has a trailing comma")]
+    TrailingComma,
+    #[error("Failed to decode an element of the array")]
+    Element(#[source] serde_json::Error),
+}
+
+impl<
+        T: DeserializeOwned,
+        Route: crate::Route<ResponseBody = JsonBody<Vec<T>>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    /// Decode the body's elements one at a time as the body is received, rather than all at
+    /// once at the end like [Response::json].
+    ///
+    /// Elements are only yielded early with transports that stream bodies, see
+    /// [TransportResponse::chunks]. The stream ends after the first error.
+    pub fn stream_array(
+        self,
+    ) -> impl Stream<Item = Result<T, BodyError<ArrayStreamError, R::Error>>> {
+        let state = ArrayState {
+            chunks: Box::pin(self.response.chunks()),
+            scanner: Scanner::default(),
+            finished: false,
+        };
+        futures_util::stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+            let item = state.next().await;
+            if !matches!(item, Some(Ok(_))) {
+                state.finished = true;
+            }
+            item.map(|item| (item, state))
+        })
+    }
+}
+
+struct ArrayState<C> {
+    chunks: Pin<Box<C>>,
+    scanner: Scanner,
+    finished: bool,
+}
+
+impl<C: Stream<Item = Result<Bytes, E>>, E> ArrayState<C> {
+    async fn next<T: DeserializeOwned>(
+        &mut self,
+    ) -> Option<Result<T, BodyError<ArrayStreamError, E>>> {
+        loop {
+            match self.scanner.next_element() {
+                Ok(Some(element)) => {
+                    return Some(
+                        serde_json::from_slice(&self.scanner.buffer[element])
+                            .map_err(|err| BodyError::Format(ArrayStreamError::Element(err))),
+                    )
+                }
+                Ok(None) if self.scanner.state == ScanState::Done => return None,
+                Ok(None) => {}
+                Err(err) => return Some(Err(BodyError::Format(err))),
+            }
+            match self.chunks.next().await {
+                Some(Ok(chunk)) => self.scanner.push(&chunk),
+                Some(Err(err)) => return Some(Err(BodyError::Transport(err))),
+                None => return Some(Err(BodyError::Format(ArrayStreamError::Truncated))),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ScanState {
+    #[default]
+    Start,
+    BeforeElement,
+    AfterComma,
+    InElement,
+    Done,
+}
+
+/// Finds where the elements of a JSON array start and end, without parsing them.
+#[derive(Default)]
+struct Scanner {
+    buffer: Vec<u8>,
+    /// Where in `buffer` the element being scanned starts. Everything before it has been
+    /// returned already.
+    start: usize,
+    /// How far into `buffer` has been scanned.
+    position: usize,
+    state: ScanState,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl Scanner {
+    /// Add the next chunk of the body to the buffer.
+    ///
+    /// The elements already returned are dropped from its front once they're over half of it,
+    /// so that each byte is only moved a few times however many elements there are.
+    fn push(&mut self, chunk: &[u8]) {
+        if self.start > self.buffer.len() / 2 {
+            self.buffer.drain(..self.start);
+            self.position -= self.start;
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Where the next whole element is in the buffer, or `None` if more of the body is needed
+    /// or the array has ended.
+    fn next_element(&mut self) -> Result<Option<Range<usize>>, ArrayStreamError> {
+        while self.position < self.buffer.len() {
+            let byte = self.buffer[self.position];
+            match self.state {
+                ScanState::Start | ScanState::BeforeElement | ScanState::AfterComma
+                    if byte.is_ascii_whitespace() => {}
+                ScanState::Start if byte == b'[' => self.state = ScanState::BeforeElement,
+                ScanState::Start => return Err(ArrayStreamError::NotAnArray),
+                ScanState::BeforeElement if byte == b']' => self.state = ScanState::Done,
+                ScanState::AfterComma if byte == b']' => {
+                    return Err(ArrayStreamError::TrailingComma)
+                }
+                ScanState::BeforeElement | ScanState::AfterComma => {
+                    self.start = self.position;
+                    self.state = ScanState::InElement;
+                    continue;
+                }
+                ScanState::InElement if self.in_string => match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                },
+                ScanState::InElement => match byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => self.depth += 1,
+                    b',' | b']' if self.depth == 0 => {
+                        let element = self.start..self.position;
+                        self.state = match byte {
+                            b',' => ScanState::AfterComma,
+                            _ => ScanState::Done,
+                        };
+                        self.position += 1;
+                        self.start = self.position;
+                        return Ok(Some(element));
+                    }
+                    b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                },
+                ScanState::Done => return Ok(None),
+            }
+            self.position += 1;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The elements of `body`, fed to the scanner in chunks of `chunk_size` bytes, or the
+    /// first error.
+    fn scan(body: &[u8], chunk_size: usize) -> Result<Vec<String>, ArrayStreamError> {
+        let mut scanner = Scanner::default();
+        let mut elements = Vec::new();
+        for chunk in body.chunks(chunk_size) {
+            scanner.push(chunk);
+            while let Some(element) = scanner.next_element()? {
+                elements.push(String::from_utf8(scanner.buffer[element].to_vec()).unwrap());
+            }
+        }
+        assert_eq!(scanner.state, ScanState::Done, "the array didn't end");
+        Ok(elements)
+    }
+
+    #[test]
+    fn finds_elements_split_across_chunks() {
+        let body = br#" [ 1, {"a": [2, 3]}, "four",[5] ] "#;
+        for chunk_size in 1..body.len() {
+            assert_eq!(
+                scan(body, chunk_size).unwrap(),
+                ["1", r#"{"a": [2, 3]}"#, r#""four""#, "[5] "],
+                "chunks of {chunk_size}",
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_brackets_and_commas_in_strings() {
+        let body = br#"["a]b", {"c,": "]\"}"}, "\\"]"#;
+        for chunk_size in [1, 2, 3, body.len()] {
+            assert_eq!(
+                scan(body, chunk_size).unwrap(),
+                [r#""a]b""#, r#"{"c,": "]\"}"}"#, r#""\\""#],
+                "chunks of {chunk_size}",
+            );
+        }
+    }
+
+    #[test]
+    fn finds_no_elements_in_an_empty_array() {
+        assert!(scan(b"[ ]", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_trailing_commas() {
+        for body in [&b"[1,]"[..], b"[1, ]", b"[[1],\n]"] {
+            assert!(matches!(
+                scan(body, 1),
+                Err(ArrayStreamError::TrailingComma)
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_bodies_that_arent_arrays() {
+        assert!(matches!(
+            scan(br#"{"a": 1}"#, 1),
+            Err(ArrayStreamError::NotAnArray)
+        ));
+    }
+
+    #[test]
+    fn drops_returned_elements_from_the_buffer() {
+        let count = 100_000;
+        let body = format!("[{}1]", "1,".repeat(count - 1));
+        let mut scanner = Scanner::default();
+        let mut found = 0;
+        for chunk in body.as_bytes().chunks(64) {
+            scanner.push(chunk);
+            while scanner.next_element().unwrap().is_some() {
+                found += 1;
+            }
+            assert!(scanner.buffer.len() <= 2 * 64 + 2);
+        }
+        assert_eq!(found, count);
+    }
+}
@@ -0,0 +1,176 @@
+//! A single extractor for everything a route's request carries, so each handler's signature
+//! follows from its route.
+//!
+//! ```ignore
+//! routes! {
+//!     // ...
+//!     route(Method::PUT, "/orders/{id}" => type UpdateOrder (query: Query<UpdateOptions>, body: JsonBody<OrderUpdate>) -> JsonBody<Order>)
+//! }
+//!
+//! async fn update_order(req: Typed<UpdateOrder, (u64,)>) -> Json<Order> {
+//!     let (id,) = req.path;
+//!     // req.query is an UpdateOptions, req.body an OrderUpdate
+//! }
+//! ```
+
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use actix_web::{error::ErrorBadRequest, HttpRequest};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    format::{self, BodyFormat, FormatBody, QueryFormat},
+    FormBody, FromRequest, IntoResponse, JsonBody, NoBody, NoQuery, Query, Route,
+};
+
+type BodyFuture<T> = Pin<Box<dyn Future<Output = Result<T, actix_web::Error>>>>;
+
+/// Implemented by a route's query type to read it from a request on the server.
+pub trait ExtractQuery {
+    type Value: 'static;
+
+    fn extract(req: &HttpRequest) -> Result<Self::Value, actix_web::Error>;
+}
+
+impl ExtractQuery for NoQuery {
+    type Value = ();
+
+    fn extract(_req: &HttpRequest) -> Result<Self::Value, actix_web::Error> {
+        Ok(())
+    }
+}
+
+/// The query is decoded with its format, so a route's `query_error` handler isn't used.
+impl<T: DeserializeOwned + 'static, F: QueryFormat> ExtractQuery for Query<T, F> {
+    type Value = T;
+
+    fn extract(req: &HttpRequest) -> Result<Self::Value, actix_web::Error> {
+        F::deserialize(req.query_string()).map_err(ErrorBadRequest)
+    }
+}
+
+/// Implemented by a route's request body type to read it from a request on the server.
+pub trait ExtractBody {
+    type Value: 'static;
+
+    fn extract(req: &HttpRequest, payload: &mut actix_web::dev::Payload)
+        -> BodyFuture<Self::Value>;
+}
+
+impl ExtractBody for NoBody {
+    type Value = ();
+
+    fn extract(
+        _req: &HttpRequest,
+        _payload: &mut actix_web::dev::Payload,
+    ) -> BodyFuture<Self::Value> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Extracted with [actix_web::web::Json], so the route's `json_limit` and `json_error` apply.
+impl<T: DeserializeOwned + 'static> ExtractBody for JsonBody<T> {
+    type Value = T;
+
+    fn extract(
+        req: &HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> BodyFuture<Self::Value> {
+        let body = <actix_web::web::Json<T> as actix_web::FromRequest>::from_request(req, payload);
+        Box::pin(async move { body.await.map(actix_web::web::Json::into_inner) })
+    }
+}
+
+impl<T: DeserializeOwned + 'static> ExtractBody for FormBody<T> {
+    type Value = T;
+
+    fn extract(
+        req: &HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> BodyFuture<Self::Value> {
+        let body = <actix_web::web::Form<T> as actix_web::FromRequest>::from_request(req, payload);
+        Box::pin(async move { body.await.map(actix_web::web::Form::into_inner) })
+    }
+}
+
+impl<F: BodyFormat, T: DeserializeOwned + 'static> ExtractBody for FormatBody<F, T> {
+    type Value = T;
+
+    fn extract(
+        req: &HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> BodyFuture<Self::Value> {
+        format::extract::<F, T>(req, payload)
+    }
+}
+
+/// An extractor for a request to `R`: its path parameters, deserialized into `P` like
+/// [actix_web::web::Path], its query and its body.
+pub struct Typed<R: Route, P = ()>
+where
+    R::Query: ExtractQuery,
+    R::RequestBody: ExtractBody,
+{
+    pub path: P,
+    pub query: <R::Query as ExtractQuery>::Value,
+    pub body: <R::RequestBody as ExtractBody>::Value,
+}
+
+impl<R: Route, P: DeserializeOwned + 'static> actix_web::FromRequest for Typed<R, P>
+where
+    R::Query: ExtractQuery,
+    R::RequestBody: ExtractBody,
+{
+    type Error = actix_web::Error;
+    type Future = BodyFuture<Self>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let path = <actix_web::web::Path<P> as actix_web::FromRequest>::extract(req).into_inner();
+        let query = R::Query::extract(req);
+        let body = R::RequestBody::extract(req, payload);
+        Box::pin(async move {
+            Ok(Typed {
+                path: path?.into_inner(),
+                query: query?,
+                body: body.await?,
+            })
+        })
+    }
+}
+
+impl<R: Route, P> FromRequest<R::Query, R::RequestBody> for Typed<R, P>
+where
+    R::Query: ExtractQuery,
+    R::RequestBody: ExtractBody,
+{
+}
+
+/// A handler for `R` with state of its own, e.g. a repository it was constructed with, as an
+/// alternative to a function given to [handled_by](crate::handled_by). Register it with
+/// [Router::typed_handler](crate::Router::typed_handler).
+///
+/// Handlers can be unit tested by calling [RouteHandler::handle] with a [Typed] built by hand.
+pub trait RouteHandler<R: Route, P = ()>: 'static
+where
+    R::Query: ExtractQuery,
+    R::RequestBody: ExtractBody,
+{
+    type Output: actix_web::Responder + IntoResponse<R::ResponseBody> + 'static;
+
+    fn handle(&self, req: Typed<R, P>) -> impl Future<Output = Self::Output>;
+}
+
+/// The actix route that calls `handler`.
+pub(crate) fn handler_route<R, P, H>(handler: Rc<H>) -> actix_web::Route
+where
+    R: Route + 'static,
+    R::Query: ExtractQuery,
+    R::RequestBody: ExtractBody,
+    P: DeserializeOwned + 'static,
+    H: RouteHandler<R, P>,
+{
+    actix_web::web::route().to(move |req: Typed<R, P>| {
+        let handler = Rc::clone(&handler);
+        async move { handler.handle(req).await }
+    })
+}
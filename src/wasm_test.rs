@@ -0,0 +1,329 @@
+//! Recording responses from a real server and replaying them, so that client tests (e.g. under
+//! `wasm-bindgen-test` in a browser) can run without the backend.
+//!
+//! Record with a [Recorder] around a native transport in a test against the actix server, save
+//! the [Fixtures] as JSON, then send the same typed requests through a [Replayer] in the browser.
+//!
+//! Sensitive fields are masked in recorded fixtures, see [redact](crate::redact), so they can be
+//! checked in. Replayed requests are masked the same way before they're matched.
+
+use std::{convert::Infallible, io, path::Path, sync::Mutex};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    canonical_uri,
+    redact::{redact_body, redact_uri},
+    ClientTransport, RouteName, SensitiveFields, TransportResponse,
+};
+
+/// A request or response body, kept readable in fixture files when it's text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FixtureBody {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl From<Bytes> for FixtureBody {
+    fn from(bytes: Bytes) -> Self {
+        match String::from_utf8(bytes.to_vec()) {
+            Ok(text) => FixtureBody::Text(text),
+            Err(err) => FixtureBody::Binary(err.into_bytes()),
+        }
+    }
+}
+
+impl From<FixtureBody> for Bytes {
+    fn from(body: FixtureBody) -> Self {
+        match body {
+            FixtureBody::Text(text) => text.into(),
+            FixtureBody::Binary(bytes) => bytes.into(),
+        }
+    }
+}
+
+/// A request and the response the server gave it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fixture {
+    /// The [Route::NAME](crate::Route::NAME) of the typed route the request was sent to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+    pub method: String,
+    pub uri: String,
+    pub request_body: FixtureBody,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: FixtureBody,
+}
+
+impl Fixture {
+    fn matches(&self, request: &http::Request<Bytes>) -> bool {
+        let route = request.extensions().get::<RouteName>();
+        let same_route = match (&self.route, route) {
+            (Some(recorded), Some(route)) => recorded == route.0,
+            _ => true,
+        };
+        let sensitive = sensitive_fields(request);
+        let request_uri = redact_uri(request.uri(), sensitive).parse();
+        let same_uri = match (self.method.parse(), self.uri.parse(), request_uri) {
+            (Ok(method), Ok(uri), Ok(request_uri)) => {
+                canonical_uri(&method, &uri) == canonical_uri(request.method(), &request_uri)
+            }
+            _ => false,
+        };
+        let request_body = redact_body(request.headers(), request.body(), sensitive);
+        same_route && same_uri && Bytes::from(self.request_body.clone()) == request_body
+    }
+
+    fn response(&self) -> FixtureResponse {
+        let headers = self
+            .headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    http::HeaderName::try_from(name).ok()?,
+                    http::HeaderValue::try_from(value).ok()?,
+                ))
+            })
+            .collect();
+        FixtureResponse {
+            status: http::StatusCode::from_u16(self.status)
+                .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR),
+            headers,
+            body: self.body.clone().into(),
+        }
+    }
+}
+
+fn sensitive_fields(request: &http::Request<Bytes>) -> &'static [&'static str] {
+    request
+        .extensions()
+        .get::<SensitiveFields>()
+        .map_or(&[], |fields| fields.0)
+}
+
+/// A set of recorded [Fixture]s, in the order their requests were sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fixtures(pub Vec<Fixture>);
+
+impl Fixtures {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("fixtures can always be serialized")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Write the fixtures to a file, e.g. at the end of a recording test.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Read fixtures saved with [Fixtures::save]. In the browser, use [Fixtures::from_json] with
+    /// `include_str!` instead.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// A response recorded by a [Recorder] or replayed by a [Replayer], with its body already read.
+#[derive(Debug, Clone)]
+pub struct FixtureResponse {
+    status: http::StatusCode,
+    headers: http::HeaderMap,
+    body: Bytes,
+}
+
+impl TransportResponse for FixtureResponse {
+    type Error = Infallible;
+
+    fn status(&self) -> http::StatusCode {
+        self.status
+    }
+
+    fn headers(&self) -> &http::HeaderMap {
+        &self.headers
+    }
+
+    async fn bytes(self) -> Result<Bytes, Self::Error> {
+        Ok(self.body)
+    }
+}
+
+/// The error returned by a [Recorder].
+#[derive(Debug, Error)]
+pub enum RecordError<TransportError, BodyError> {
+    #[error("Failed to send the request")]
+    Transport(#[source] TransportError),
+    #[error("Failed to read the response")]
+    Body(#[source] BodyError),
+}
+
+/// A [ClientTransport] that sends requests with `T` and records each exchange.
+pub struct Recorder<T> {
+    inner: T,
+    fixtures: Mutex<Fixtures>,
+}
+
+impl<T> Recorder<T> {
+    pub fn new(inner: T) -> Self {
+        Recorder {
+            inner,
+            fixtures: Mutex::new(Fixtures::default()),
+        }
+    }
+
+    /// The exchanges recorded so far.
+    pub fn fixtures(&self) -> Fixtures {
+        self.fixtures.lock().unwrap().clone()
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for Recorder<T> {
+    type Response = FixtureResponse;
+    type Error = RecordError<T::Error, <T::Response as TransportResponse>::Error>;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let route = request
+            .extensions()
+            .get::<RouteName>()
+            .map(|route| route.0.to_owned());
+        let sensitive = sensitive_fields(&request);
+        let method = request.method().to_string();
+        let uri = redact_uri(request.uri(), sensitive);
+        let request_body = redact_body(request.headers(), request.body(), sensitive);
+
+        let response = self
+            .inner
+            .send(request)
+            .await
+            .map_err(RecordError::Transport)?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(RecordError::Body)?;
+
+        self.fixtures.lock().unwrap().0.push(Fixture {
+            route,
+            method,
+            uri,
+            request_body: request_body.into(),
+            status: status.as_u16(),
+            headers: headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_owned()))
+                })
+                .collect(),
+            body: redact_body(&headers, &body, sensitive).into(),
+        });
+        Ok(FixtureResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// The error returned by a [Replayer] for a request that wasn't recorded.
+#[derive(Debug, Error)]
+#[error("No fixture was recorded for {method} {uri}")]
+pub struct MissingFixture {
+    pub method: http::Method,
+    pub uri: http::Uri,
+}
+
+/// A [ClientTransport] that answers requests with recorded [Fixtures] instead of sending them.
+///
+/// A request recorded several times gets its responses in the order they were recorded, the
+/// last one repeating once they run out.
+pub struct Replayer {
+    fixtures: Vec<Fixture>,
+    replayed: Mutex<Vec<bool>>,
+}
+
+impl Replayer {
+    pub fn new(fixtures: Fixtures) -> Self {
+        Replayer {
+            replayed: Mutex::new(vec![false; fixtures.0.len()]),
+            fixtures: fixtures.0,
+        }
+    }
+}
+
+impl ClientTransport for Replayer {
+    type Response = FixtureResponse;
+    type Error = MissingFixture;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        let mut replayed = self.replayed.lock().unwrap();
+        let matching: Vec<usize> = (0..self.fixtures.len())
+            .filter(|&i| self.fixtures[i].matches(&request))
+            .collect();
+        let next = matching
+            .iter()
+            .copied()
+            .find(|&i| !replayed[i])
+            .or(matching.last().copied());
+        match next {
+            Some(i) => {
+                replayed[i] = true;
+                Ok(self.fixtures[i].response())
+            }
+            None => Err(MissingFixture {
+                method: request.method().clone(),
+                uri: request.uri().clone(),
+            }),
+        }
+    }
+}
+
+/// The error returned by a [Vcr].
+#[derive(Debug, Error)]
+pub enum VcrError<TransportError, BodyError> {
+    #[error(transparent)]
+    Record(RecordError<TransportError, BodyError>),
+    #[error(transparent)]
+    Replay(MissingFixture),
+}
+
+/// A [ClientTransport] that either records exchanges with a real server or replays them, so
+/// the same test or demo can switch between the two, e.g. based on an environment variable.
+pub enum Vcr<T> {
+    Record(Recorder<T>),
+    Replay(Replayer),
+}
+
+impl<T> Vcr<T> {
+    pub fn record(inner: T) -> Self {
+        Vcr::Record(Recorder::new(inner))
+    }
+
+    pub fn replay(fixtures: Fixtures) -> Self {
+        Vcr::Replay(Replayer::new(fixtures))
+    }
+
+    /// The exchanges recorded so far, or `None` when replaying.
+    pub fn fixtures(&self) -> Option<Fixtures> {
+        match self {
+            Vcr::Record(recorder) => Some(recorder.fixtures()),
+            Vcr::Replay(_) => None,
+        }
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for Vcr<T> {
+    type Response = FixtureResponse;
+    type Error = VcrError<T::Error, <T::Response as TransportResponse>::Error>;
+
+    async fn send(&self, request: http::Request<Bytes>) -> Result<Self::Response, Self::Error> {
+        match self {
+            Vcr::Record(recorder) => recorder.send(request).await.map_err(VcrError::Record),
+            Vcr::Replay(replayer) => replayer.send(request).await.map_err(VcrError::Replay),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Outbound webhooks: typed payloads sent to subscribers' URLs, signed with a shared secret.
+//!
+//! The signature covers the delivery's timestamp and event name as well as its body, so a
+//! captured delivery can't be replayed once it's older than [TIMESTAMP_TOLERANCE], or passed
+//! off as a different event.
+
+use std::{future::Future, marker::PhantomData, ops::Deref, pin::Pin, time::Duration};
+
+use actix_web::{
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorUnauthorized},
+    web::Bytes,
+    HttpRequest,
+};
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::{ClientTransport, TransportResponse};
+
+/// The header carrying the event name of a webhook delivery.
+pub const EVENT_HEADER: &str = "x-webhook-event";
+/// The header carrying when a webhook delivery was sent, in seconds since the Unix epoch.
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+/// The header carrying `sha256=` followed by the hex HMAC-SHA256 of the delivery's timestamp,
+/// event name and body, joined by `.`.
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// How far a delivery's timestamp can be from the receiver's clock before the [Webhook]
+/// extractor rejects it.
+pub const TIMESTAMP_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// A webhook, the outbound counterpart of a [Route](crate::Route): an event name and the
+/// payload sent with it.
+pub trait WebhookRoute {
+    type Payload: Serialize + DeserializeOwned;
+
+    /// The event name, e.g. `order.created`.
+    const EVENT: &'static str;
+}
+
+fn mac(secret: &[u8]) -> Hmac<Sha256> {
+    Hmac::new_from_slice(secret).expect("HMAC accepts keys of any length")
+}
+
+/// The MAC of a delivery, over `{timestamp}.{event}.{body}`.
+fn signed(secret: &[u8], timestamp: u64, event: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = mac(secret);
+    mac.update(format!("{timestamp}.{event}.").as_bytes());
+    mac.update(body);
+    mac
+}
+
+fn sign(secret: &[u8], timestamp: u64, event: &str, body: &[u8]) -> String {
+    let signature: String = signed(secret, timestamp, event, body)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("sha256={signature}")
+}
+
+fn verify(secret: &[u8], timestamp: u64, event: &str, body: &[u8], signature: &str) -> bool {
+    let Some(hex) = signature.strip_prefix("sha256=") else {
+        return false;
+    };
+    let bytes: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect();
+    let Some(bytes) = bytes else {
+        return false;
+    };
+    signed(secret, timestamp, event, body)
+        .verify_slice(&bytes)
+        .is_ok()
+}
+
+/// The current time in seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// A delivery that failed on every attempt, handed to the [Dispatcher]'s dead letter callback.
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub event: &'static str,
+    pub url: String,
+    pub body: Bytes,
+    /// Why the last attempt failed.
+    pub error: String,
+}
+
+/// The error returned when a webhook couldn't be delivered.
+#[derive(Debug, Error)]
+pub enum DispatchError {
+    #[error("Failed to serialize the webhook payload")]
+    Payload(#[source] serde_json::Error),
+    #[error("Failed to build the webhook request")]
+    Request(#[source] http::Error),
+    #[error("Failed to deliver the webhook after {attempts} attempts: {error}")]
+    Undelivered { attempts: u32, error: String },
+}
+
+/// Delivers webhooks with a transport, retrying failed deliveries with exponential backoff.
+///
+/// Waits between attempts with `S`, e.g. `tokio::time::sleep`.
+pub struct Dispatcher<T, S> {
+    transport: T,
+    secret: Vec<u8>,
+    sleep: S,
+    max_attempts: u32,
+    backoff: Duration,
+    dead_letter: Option<Box<dyn Fn(DeadLetter) + Send + Sync>>,
+}
+
+impl<T, S, Fut> Dispatcher<T, S>
+where
+    T: ClientTransport,
+    T::Error: std::fmt::Display,
+    S: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    pub fn new(transport: T, secret: &[u8], sleep: S) -> Self {
+        Dispatcher {
+            transport,
+            secret: secret.to_owned(),
+            sleep,
+            max_attempts: 5,
+            backoff: Duration::from_secs(1),
+            dead_letter: None,
+        }
+    }
+
+    /// How many times to try each delivery. Defaults to 5.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// How long to wait after the first failed attempt, doubling after each one after that.
+    /// Defaults to a second.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Called with every delivery that fails on all of its attempts, e.g. to store it for
+    /// redelivery later.
+    pub fn dead_letter(mut self, dead_letter: impl Fn(DeadLetter) + Send + Sync + 'static) -> Self {
+        self.dead_letter = Some(Box::new(dead_letter));
+        self
+    }
+
+    /// Deliver `payload` to `url`, which should be an absolute URL.
+    pub async fn dispatch<W: WebhookRoute>(
+        &self,
+        url: &str,
+        payload: &W::Payload,
+    ) -> Result<(), DispatchError> {
+        let body: Bytes = serde_json::to_vec(payload)
+            .map_err(DispatchError::Payload)?
+            .into();
+
+        let mut backoff = self.backoff;
+        let mut error = String::new();
+        for attempt in 1..=self.max_attempts {
+            if attempt > 1 {
+                (self.sleep)(backoff).await;
+                backoff *= 2;
+            }
+            // Each attempt is signed when it's sent, so retries aren't rejected as stale.
+            let timestamp = unix_time();
+            let request = http::Request::post(url)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, W::EVENT)
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(
+                    SIGNATURE_HEADER,
+                    sign(&self.secret, timestamp, W::EVENT, &body),
+                )
+                .body(body.clone())
+                .map_err(DispatchError::Request)?;
+            match self.transport.send(request).await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => error = format!("Status {}", response.status()),
+                Err(err) => error = err.to_string(),
+            }
+        }
+
+        if let Some(dead_letter) = &self.dead_letter {
+            dead_letter(DeadLetter {
+                event: W::EVENT,
+                url: url.to_owned(),
+                body,
+                error: error.clone(),
+            });
+        }
+        Err(DispatchError::Undelivered {
+            attempts: self.max_attempts,
+            error,
+        })
+    }
+}
+
+/// The secret a [Webhook] extractor checks signatures with, provided as app data.
+#[derive(Debug, Clone)]
+pub struct WebhookSecret(pub Vec<u8>);
+
+/// An extractor for a delivery of the webhook `W`, which rejects deliveries with a missing or
+/// invalid signature, or a timestamp further than [TIMESTAMP_TOLERANCE] from now, with
+/// `401 Unauthorized`.
+pub struct Webhook<W: WebhookRoute> {
+    _marker: PhantomData<W>,
+    pub payload: W::Payload,
+}
+
+impl<W: WebhookRoute + 'static> actix_web::FromRequest for Webhook<W> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let req = req.clone();
+        let body = Bytes::from_request(&req, payload);
+        Box::pin(async move {
+            let body = body.await?;
+            let secret = req
+                .app_data::<WebhookSecret>()
+                .ok_or_else(|| ErrorInternalServerError("No WebhookSecret configured"))?;
+            let header = |name| {
+                req.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+            };
+            let event = header(EVENT_HEADER).unwrap_or_default();
+            let timestamp = header(TIMESTAMP_HEADER)
+                .and_then(|timestamp| timestamp.parse::<u64>().ok())
+                .ok_or_else(|| ErrorUnauthorized("Missing webhook timestamp"))?;
+            let signature = header(SIGNATURE_HEADER).unwrap_or_default();
+            if !verify(&secret.0, timestamp, event, &body, signature) {
+                return Err(ErrorUnauthorized("Invalid webhook signature"));
+            }
+            if unix_time().abs_diff(timestamp) > TIMESTAMP_TOLERANCE.as_secs() {
+                return Err(ErrorUnauthorized("Stale webhook timestamp"));
+            }
+            if event != W::EVENT {
+                return Err(ErrorBadRequest(format!("Expected a {} event", W::EVENT)));
+            }
+            serde_json::from_slice(&body)
+                .map(|payload| Webhook {
+                    _marker: PhantomData,
+                    payload,
+                })
+                .map_err(ErrorBadRequest)
+        })
+    }
+}
+
+impl<W: WebhookRoute> Deref for Webhook<W> {
+    type Target = W::Payload;
+
+    fn deref(&self) -> &Self::Target {
+        &self.payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::TestRequest, FromRequest};
+    use serde::Deserialize;
+
+    use super::*;
+
+    const SECRET: &[u8] = b"secret";
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u32,
+    }
+
+    struct OrderCreated;
+
+    impl WebhookRoute for OrderCreated {
+        type Payload = Order;
+        const EVENT: &'static str = "order.created";
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_event() {
+        let signature = sign(SECRET, 100, "order.created", b"{}");
+        assert!(verify(SECRET, 100, "order.created", b"{}", &signature));
+        assert!(!verify(SECRET, 101, "order.created", b"{}", &signature));
+        assert!(!verify(SECRET, 100, "order.deleted", b"{}", &signature));
+        assert!(!verify(SECRET, 100, "order.created", b"[]", &signature));
+        assert!(!verify(b"other", 100, "order.created", b"{}", &signature));
+    }
+
+    async fn extract(timestamp: u64, event: &str, signed_event: &str) -> actix_web::Result<Order> {
+        let body = br#"{"id":1}"#;
+        let (req, mut payload) = TestRequest::post()
+            .app_data(WebhookSecret(SECRET.to_vec()))
+            .insert_header((EVENT_HEADER, event))
+            .insert_header((TIMESTAMP_HEADER, timestamp.to_string()))
+            .insert_header((
+                SIGNATURE_HEADER,
+                sign(SECRET, timestamp, signed_event, body),
+            ))
+            .set_payload(&body[..])
+            .to_http_parts();
+        Webhook::<OrderCreated>::from_request(&req, &mut payload)
+            .await
+            .map(|webhook| webhook.payload)
+    }
+
+    #[actix_web::test]
+    async fn accepts_fresh_deliveries() {
+        let order = extract(unix_time(), "order.created", "order.created").await;
+        assert_eq!(order.unwrap(), Order { id: 1 });
+    }
+
+    #[actix_web::test]
+    async fn rejects_stale_deliveries() {
+        let stale = unix_time() - TIMESTAMP_TOLERANCE.as_secs() - 1;
+        let err = extract(stale, "order.created", "order.created")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_web::test]
+    async fn rejects_relabelled_events() {
+        let err = extract(unix_time(), "order.created", "order.deleted")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn dispatchers_are_send() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Dispatcher<(), fn(Duration) -> std::future::Ready<()>>>();
+    }
+}
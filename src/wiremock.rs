@@ -0,0 +1,251 @@
+//! Mocking typed routes on a [wiremock] server, for testing code that calls them with a native
+//! transport.
+//!
+//! ```
+//! # use serde::{Deserialize, Serialize};
+//! # use typed_routing::{wiremock::MockServerExt, JsonBody, Method, NoBody, Query, Route};
+//! # #[derive(Serialize, Deserialize)]
+//! # struct UserQuery { id: u64 }
+//! # #[derive(Serialize, Deserialize)]
+//! # struct User { id: u64, name: String }
+//! # struct GetUser;
+//! # impl Route for GetUser {
+//! #     type Query = Query<UserQuery>;
+//! #     type RequestBody = NoBody;
+//! #     type ResponseBody = JsonBody<User>;
+//! #     const METHOD: Method = Method::GET;
+//! #     const NAME: &'static str = "GetUser";
+//! #     const URI_PART: &'static str = "/user";
+//! #     const URI: &'static str = "/user";
+//! # }
+//! # async fn mock(server: wiremock::MockServer) {
+//! server.mock_route::<GetUser, _>(|query, ()| User { id: query.id, name: "Ann".into() }).await;
+//! # }
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+use wiremock::{Match, Mock, MockBuilder, MockServer, Respond, ResponseTemplate};
+
+use crate::{
+    format::QueryFormat, links::matches_uri, FormBody, JsonBody, NoBody, NoQuery, Query, Route,
+};
+
+/// Implemented by query and request body types that a mocked route can decode.
+pub trait MockDecode {
+    type Value;
+
+    /// Decode a query string, or a request body.
+    fn decode(bytes: &[u8]) -> Option<Self::Value>;
+}
+
+impl MockDecode for NoQuery {
+    type Value = ();
+
+    fn decode(_bytes: &[u8]) -> Option<Self::Value> {
+        Some(())
+    }
+}
+
+impl MockDecode for NoBody {
+    type Value = ();
+
+    fn decode(_bytes: &[u8]) -> Option<Self::Value> {
+        Some(())
+    }
+}
+
+impl<T: DeserializeOwned, F: QueryFormat> MockDecode for Query<T, F> {
+    type Value = T;
+
+    fn decode(bytes: &[u8]) -> Option<Self::Value> {
+        F::deserialize(std::str::from_utf8(bytes).ok()?).ok()
+    }
+}
+
+impl<T: DeserializeOwned> MockDecode for JsonBody<T> {
+    type Value = T;
+
+    fn decode(bytes: &[u8]) -> Option<Self::Value> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+impl<T: DeserializeOwned> MockDecode for FormBody<T> {
+    type Value = T;
+
+    fn decode(bytes: &[u8]) -> Option<Self::Value> {
+        serde_urlencoded::from_bytes(bytes).ok()
+    }
+}
+
+/// Implemented by response body types that a mocked route can respond with.
+pub trait MockEncode {
+    type Value;
+
+    fn encode(value: Self::Value) -> ResponseTemplate;
+}
+
+impl MockEncode for NoBody {
+    type Value = ();
+
+    fn encode(_value: Self::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200)
+    }
+}
+
+impl<T: Serialize> MockEncode for JsonBody<T> {
+    type Value = T;
+
+    fn encode(value: Self::Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(value)
+    }
+}
+
+fn decode_query<R: Route>(request: &wiremock::Request) -> Option<<R::Query as MockDecode>::Value>
+where
+    R::Query: MockDecode,
+{
+    R::Query::decode(request.url.query().unwrap_or_default().as_bytes())
+}
+
+/// Matches requests to `R`: its method, a path fitting its URI, and a query and body that
+/// decode into its types.
+pub struct RouteMatcher<R>(PhantomData<fn() -> R>);
+
+impl<R> Default for RouteMatcher<R> {
+    fn default() -> Self {
+        RouteMatcher(PhantomData)
+    }
+}
+
+impl<R: Route> Match for RouteMatcher<R>
+where
+    R::Query: MockDecode,
+    R::RequestBody: MockDecode,
+{
+    fn matches(&self, request: &wiremock::Request) -> bool {
+        request.method == R::METHOD
+            && matches_uri(R::URI, request.url.path())
+            && decode_query::<R>(request).is_some()
+            && R::RequestBody::decode(&request.body).is_some()
+    }
+}
+
+/// Start mocking `R`, to add more matchers to or respond to in a custom way.
+pub fn given_route<R: Route + 'static>() -> MockBuilder
+where
+    R::Query: MockDecode,
+    R::RequestBody: MockDecode,
+{
+    Mock::given(RouteMatcher::<R>::default())
+}
+
+/// Matches requests whose query decodes into `expected`.
+pub struct ExpectQuery<R: Route>(pub <R::Query as MockDecode>::Value)
+where
+    R::Query: MockDecode;
+
+impl<R: Route> Match for ExpectQuery<R>
+where
+    R::Query: MockDecode,
+    <R::Query as MockDecode>::Value: PartialEq + Send + Sync,
+{
+    fn matches(&self, request: &wiremock::Request) -> bool {
+        decode_query::<R>(request).is_some_and(|query| query == self.0)
+    }
+}
+
+/// Matches requests whose body decodes into `expected`.
+pub struct ExpectBody<R: Route>(pub <R::RequestBody as MockDecode>::Value)
+where
+    R::RequestBody: MockDecode;
+
+impl<R: Route> Match for ExpectBody<R>
+where
+    R::RequestBody: MockDecode,
+    <R::RequestBody as MockDecode>::Value: PartialEq + Send + Sync,
+{
+    fn matches(&self, request: &wiremock::Request) -> bool {
+        R::RequestBody::decode(&request.body).is_some_and(|body| body == self.0)
+    }
+}
+
+/// Responds to requests to `R` with what `F` returns for their decoded query and body.
+pub struct TypedResponder<R, F> {
+    _marker: PhantomData<fn() -> R>,
+    respond: F,
+}
+
+impl<R, F> TypedResponder<R, F> {
+    pub fn new(respond: F) -> Self {
+        TypedResponder {
+            _marker: PhantomData,
+            respond,
+        }
+    }
+}
+
+impl<R: Route, F> Respond for TypedResponder<R, F>
+where
+    R::Query: MockDecode,
+    R::RequestBody: MockDecode,
+    R::ResponseBody: MockEncode,
+    F: Fn(
+            <R::Query as MockDecode>::Value,
+            <R::RequestBody as MockDecode>::Value,
+        ) -> <R::ResponseBody as MockEncode>::Value
+        + Send
+        + Sync,
+{
+    fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+        match (
+            decode_query::<R>(request),
+            R::RequestBody::decode(&request.body),
+        ) {
+            (Some(query), Some(body)) => R::ResponseBody::encode((self.respond)(query, body)),
+            _ => ResponseTemplate::new(400),
+        }
+    }
+}
+
+/// Adds [MockServerExt::mock_route] to [MockServer].
+pub trait MockServerExt {
+    /// Answer every request to `R` with what `respond` returns for its decoded query and body.
+    fn mock_route<R, F>(&self, respond: F) -> impl std::future::Future<Output = ()>
+    where
+        R: Route + 'static,
+        R::Query: MockDecode,
+        R::RequestBody: MockDecode,
+        R::ResponseBody: MockEncode,
+        F: Fn(
+                <R::Query as MockDecode>::Value,
+                <R::RequestBody as MockDecode>::Value,
+            ) -> <R::ResponseBody as MockEncode>::Value
+            + Send
+            + Sync
+            + 'static;
+}
+
+impl MockServerExt for MockServer {
+    async fn mock_route<R, F>(&self, respond: F)
+    where
+        R: Route + 'static,
+        R::Query: MockDecode,
+        R::RequestBody: MockDecode,
+        R::ResponseBody: MockEncode,
+        F: Fn(
+                <R::Query as MockDecode>::Value,
+                <R::RequestBody as MockDecode>::Value,
+            ) -> <R::ResponseBody as MockEncode>::Value
+            + Send
+            + Sync
+            + 'static,
+    {
+        given_route::<R>()
+            .respond_with(TypedResponder::<R, F>::new(respond))
+            .mount(self)
+            .await;
+    }
+}
@@ -0,0 +1,89 @@
+//! XML bodies, (de)serialized with [quick_xml]'s serde support.
+
+use std::{future::Future, ops::Deref, pin::Pin};
+
+use actix_web::{HttpRequest, HttpResponse};
+use quick_xml::DeError;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    format::{self, BodyFormat, FormatBody},
+    BodyError, FromRequest, IntoResponse, NoBody, RequestBuilder, Response, TransportResponse,
+};
+
+/// The content type XML bodies are sent with.
+pub const CONTENT_TYPE: &str = "application/xml";
+
+/// The XML [BodyFormat].
+///
+/// Requests may use either the `application/xml` or `text/xml` content type.
+pub struct XmlFormat;
+
+impl BodyFormat for XmlFormat {
+    const CONTENT_TYPE: &'static str = CONTENT_TYPE;
+
+    type Error = DeError;
+
+    fn matches(media_type: &str) -> bool {
+        matches!(media_type, CONTENT_TYPE | "text/xml")
+    }
+
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Self::Error> {
+        quick_xml::se::to_string(value).map(String::into_bytes)
+    }
+
+    fn deserialize<T: DeserializeOwned>(body: &[u8]) -> Result<T, Self::Error> {
+        quick_xml::de::from_reader(body)
+    }
+}
+
+/// A type that indicates that the request or response guarantees that its body will be XML
+/// that successfully deserializes into a `T`.
+pub type XmlBody<T> = FormatBody<XmlFormat, T>;
+
+impl<T, Route: crate::Route<RequestBody = XmlBody<T>>, Query> RequestBuilder<Route, Query, NoBody> {
+    pub fn xml(self, body: T) -> RequestBuilder<Route, Query, XmlBody<T>> {
+        self.body(body)
+    }
+}
+
+impl<T: DeserializeOwned, Route: crate::Route<ResponseBody = XmlBody<T>>, R: TransportResponse>
+    Response<Route, R>
+{
+    pub async fn xml(self) -> Result<T, BodyError<DeError, R::Error>> {
+        self.decode().await
+    }
+}
+
+/// An extractor and responder for XML bodies, the counterpart of [XmlBody].
+pub struct Xml<T>(pub T);
+
+impl<T: DeserializeOwned + 'static> actix_web::FromRequest for Xml<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let body = format::extract::<XmlFormat, T>(req, payload);
+        Box::pin(async move { body.await.map(Xml) })
+    }
+}
+
+impl<T: Serialize> actix_web::Responder for Xml<T> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        format::respond::<XmlFormat, T>(&self.0)
+    }
+}
+
+impl<T: DeserializeOwned, Query> FromRequest<Query, XmlBody<T>> for Xml<T> {}
+
+impl<T: Serialize> IntoResponse<XmlBody<T>> for Xml<T> {}
+
+impl<T> Deref for Xml<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
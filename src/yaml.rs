@@ -0,0 +1,96 @@
+//! YAML bodies, (de)serialized with [serde_yaml].
+
+use std::{future::Future, ops::Deref, pin::Pin};
+
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    format::{self, BodyFormat, FormatBody},
+    BodyError, FromRequest, IntoResponse, NoBody, RequestBuilder, Response, TransportResponse,
+};
+
+/// The content type YAML bodies are sent with.
+pub const CONTENT_TYPE: &str = "application/yaml";
+
+/// The YAML [BodyFormat].
+///
+/// Requests may also use the unofficial `application/x-yaml` and `text/yaml` content types.
+pub struct YamlFormat;
+
+impl BodyFormat for YamlFormat {
+    const CONTENT_TYPE: &'static str = CONTENT_TYPE;
+
+    type Error = serde_yaml::Error;
+
+    fn matches(media_type: &str) -> bool {
+        matches!(
+            media_type,
+            CONTENT_TYPE | "application/x-yaml" | "text/yaml"
+        )
+    }
+
+    fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>, Self::Error> {
+        serde_yaml::to_string(value).map(String::into_bytes)
+    }
+
+    fn deserialize<T: DeserializeOwned>(body: &[u8]) -> Result<T, Self::Error> {
+        serde_yaml::from_slice(body)
+    }
+}
+
+/// A type that indicates that the request or response guarantees that its body will be a YAML
+/// document that successfully deserializes into a `T`.
+pub type YamlBody<T> = FormatBody<YamlFormat, T>;
+
+impl<T, Route: crate::Route<RequestBody = YamlBody<T>>, Query>
+    RequestBuilder<Route, Query, NoBody>
+{
+    pub fn yaml(self, body: T) -> RequestBuilder<Route, Query, YamlBody<T>> {
+        self.body(body)
+    }
+}
+
+impl<
+        T: DeserializeOwned,
+        Route: crate::Route<ResponseBody = YamlBody<T>>,
+        R: TransportResponse,
+    > Response<Route, R>
+{
+    pub async fn yaml(self) -> Result<T, BodyError<serde_yaml::Error, R::Error>> {
+        self.decode().await
+    }
+}
+
+/// An extractor and responder for YAML bodies, the counterpart of [YamlBody].
+pub struct Yaml<T>(pub T);
+
+impl<T: DeserializeOwned + 'static> actix_web::FromRequest for Yaml<T> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let body = format::extract::<YamlFormat, T>(req, payload);
+        Box::pin(async move { body.await.map(Yaml) })
+    }
+}
+
+impl<T: Serialize> actix_web::Responder for Yaml<T> {
+    type Body = actix_web::body::BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        format::respond::<YamlFormat, T>(&self.0)
+    }
+}
+
+impl<T: DeserializeOwned, Query> FromRequest<Query, YamlBody<T>> for Yaml<T> {}
+
+impl<T: Serialize> IntoResponse<YamlBody<T>> for Yaml<T> {}
+
+impl<T> Deref for Yaml<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}